@@ -0,0 +1,338 @@
+//! Versioned, checksummed container for the keys, proofs and witness shares
+//! exchanged between `setup`, the coordinator and the workers.
+//!
+//! Files start with a fixed size header followed by the uncompressed
+//! canonical serialization of the value:
+//!
+//! | bytes | content                                              |
+//! |-------|------------------------------------------------------|
+//! | 8     | magic bytes, shared with `noir-r1cs` files           |
+//! | 8     | format tag, see [`Artifact::FORMAT`]                 |
+//! | 2 + 2 | major and minor version, little endian               |
+//! | 32    | digest of the circuit, see [`spartan::R1CS::digest`] |
+//! | 32    | digest of the verifier key, see [`Digests`]          |
+//! | 8     | payload length, little endian                        |
+//! | 32    | SHA-256 of the payload                               |
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::{ensure, Context as _, Result};
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+pub use noir_r1cs::Digest;
+use sha2::{Digest as _, Sha256};
+use spartan::{IndexVerifierKey, R1CSProof};
+use tracing::{info, instrument};
+
+use crate::{setup::CoordinatorKey, witness::WitnessShare, Rep3ProverKey};
+
+const MAGIC_BYTES: &[u8; 8] = b"\xDC\xDFOZkp\x01\x00";
+pub const HEADER_SIZE: usize = 124;
+
+/// Values that can be stored in an artifact file.
+pub trait Artifact {
+    const FORMAT: [u8; 8];
+    const VERSION: (u16, u16);
+    /// Whether to check curve points and field elements on read. The
+    /// checksum already catches corruption, so this is only needed for
+    /// artifacts from untrusted parties.
+    const VALIDATE: Validate = Validate::No;
+
+    /// Digest of the verifier key contained in the value, checked against
+    /// the header on read.
+    fn verifier_key_digest(&self) -> Option<Digest> {
+        None
+    }
+}
+
+impl<E: Pairing> Artifact for CoordinatorKey<E> {
+    const FORMAT: [u8; 8] = *b"CoSpCoKy";
+    const VERSION: (u16, u16) = (2, 0);
+
+    fn verifier_key_digest(&self) -> Option<Digest> {
+        Some(self.ivk.digest())
+    }
+}
+
+/// Stored in sections, see [`crate::section`].
+impl<E: Pairing> Artifact for Rep3ProverKey<E> {
+    const FORMAT: [u8; 8] = *b"CoSpWkKy";
+    const VERSION: (u16, u16) = (2, 0);
+}
+
+impl<E: Pairing> Artifact for IndexVerifierKey<E> {
+    const FORMAT: [u8; 8] = *b"SpartnVK";
+    const VERSION: (u16, u16) = (2, 0);
+    const VALIDATE: Validate = Validate::Yes;
+
+    fn verifier_key_digest(&self) -> Option<Digest> {
+        Some(self.digest())
+    }
+}
+
+impl<E: Pairing> Artifact for WitnessShare<E> {
+    const FORMAT: [u8; 8] = *b"CoSpWtSh";
+    const VERSION: (u16, u16) = (1, 0);
+}
+
+impl<E: Pairing> Artifact for R1CSProof<E> {
+    const FORMAT: [u8; 8] = *b"SpartnPf";
+    const VERSION: (u16, u16) = spartan::PROOF_FORMAT_VERSION;
+    const VALIDATE: Validate = Validate::Yes;
+}
+
+/// Digests of the circuit and verifier key an artifact belongs to. The
+/// coordinator sends them to the workers to check their keys against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Digests {
+    pub circuit: Digest,
+    pub verifier_key: Digest,
+}
+
+/// Header of an artifact file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub format: [u8; 8],
+    pub version: (u16, u16),
+    pub digests: Digests,
+    pub length: u64,
+    pub content_hash: Digest,
+}
+
+impl Header {
+    pub(crate) fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        let (major, minor) = self.version;
+        let parts: [&[u8]; 8] = [
+            MAGIC_BYTES,
+            &self.format,
+            &major.to_le_bytes(),
+            &minor.to_le_bytes(),
+            &self.digests.circuit,
+            &self.digests.verifier_key,
+            &self.length.to_le_bytes(),
+            &self.content_hash,
+        ];
+        let mut offset = 0;
+        for part in parts {
+            bytes[offset..offset + part.len()].copy_from_slice(part);
+            offset += part.len();
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_SIZE]) -> Result<Self> {
+        let mut rest = &bytes[..];
+        ensure!(take(&mut rest, 8) == MAGIC_BYTES, "Invalid magic bytes");
+        Ok(Self {
+            format: take(&mut rest, 8).try_into()?,
+            version: (
+                u16::from_le_bytes(take(&mut rest, 2).try_into()?),
+                u16::from_le_bytes(take(&mut rest, 2).try_into()?),
+            ),
+            digests: Digests {
+                circuit: take(&mut rest, 32).try_into()?,
+                verifier_key: take(&mut rest, 32).try_into()?,
+            },
+            length: u64::from_le_bytes(take(&mut rest, 8).try_into()?),
+            content_hash: take(&mut rest, 32).try_into()?,
+        })
+    }
+
+    /// Check that the header describes a `T` readable by this version, for
+    /// the expected circuit and verifier key if given.
+    pub(crate) fn check<T: Artifact>(&self, digests: Option<&Digests>) -> Result<()> {
+        let (major, minor) = T::VERSION;
+        ensure!(
+            self.format == T::FORMAT,
+            "Invalid format {:?}, expected {:?}",
+            String::from_utf8_lossy(&self.format),
+            String::from_utf8_lossy(&T::FORMAT)
+        );
+        ensure!(self.version.0 == major, "Incompatible format major version");
+        ensure!(self.version.1 >= minor, "Incompatible format minor version");
+        if let Some(expected) = digests {
+            ensure!(
+                self.digests.circuit == expected.circuit,
+                "Artifact belongs to circuit {}, expected {}",
+                hex::encode(self.digests.circuit),
+                hex::encode(expected.circuit)
+            );
+            ensure!(
+                self.digests.verifier_key == expected.verifier_key,
+                "Artifact belongs to verifier key {}, expected {}",
+                hex::encode(self.digests.verifier_key),
+                hex::encode(expected.verifier_key)
+            );
+        }
+        Ok(())
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], size: usize) -> &'a [u8] {
+    let (head, tail) = bytes.split_at(size);
+    *bytes = tail;
+    head
+}
+
+/// Write an artifact for the circuit and verifier key with the given digests.
+#[instrument(skip(value, digests))]
+pub fn write_artifact<T: Artifact + CanonicalSerialize>(
+    value: &T,
+    digests: &Digests,
+    path: &Path,
+) -> Result<()> {
+    if let Some(verifier_key) = value.verifier_key_digest() {
+        ensure!(
+            verifier_key == digests.verifier_key,
+            "Value contains a different verifier key"
+        );
+    }
+    let mut payload = Vec::with_capacity(value.uncompressed_size());
+    value
+        .serialize_uncompressed(&mut payload)
+        .context("while serializing")?;
+    let header = Header {
+        format: T::FORMAT,
+        version: T::VERSION,
+        digests: *digests,
+        length: payload.len() as u64,
+        content_hash: Sha256::digest(&payload).into(),
+    };
+
+    let mut file = File::create(path).context("while creating output file")?;
+    file.write_all(&header.to_bytes())
+        .context("while writing header")?;
+    file.write_all(&payload).context("while writing payload")?;
+    file.sync_all().context("while syncing output file")?;
+    info!(?path, size = HEADER_SIZE + payload.len(), "Wrote artifact");
+    Ok(())
+}
+
+/// Read the header of an artifact file.
+pub fn read_header(path: &Path) -> Result<Header> {
+    let mut file = File::open(path).with_context(|| format!("while opening {path:?}"))?;
+    read_header_from(&mut file)
+}
+
+pub(crate) fn read_header_from(file: &mut File) -> Result<Header> {
+    let mut bytes = [0; HEADER_SIZE];
+    file.read_exact(&mut bytes)
+        .context("while reading header")?;
+    Header::from_bytes(&bytes)
+}
+
+/// Read an artifact, checking its format, version and checksum. If digests
+/// are given, the artifact must belong to that circuit and verifier key. A
+/// verifier key contained in the value must match the digest in the header.
+#[instrument(skip(digests), fields(size = path.metadata().map(|m| m.len()).ok()))]
+pub fn read_artifact<T: Artifact + CanonicalDeserialize>(
+    path: &Path,
+    digests: Option<&Digests>,
+) -> Result<T> {
+    let mut file = File::open(path).with_context(|| format!("while opening {path:?}"))?;
+    let header = read_header_from(&mut file)?;
+    header.check::<T>(digests)?;
+    let payload = read_payload(&mut file, &header)?;
+
+    let mut reader = &payload[..];
+    let value = T::deserialize_with_mode(&mut reader, Compress::No, T::VALIDATE)
+        .context("while deserializing")?;
+    ensure!(reader.is_empty(), "Trailing bytes after value");
+    if let Some(verifier_key) = value.verifier_key_digest() {
+        ensure!(
+            verifier_key == header.digests.verifier_key,
+            "Verifier key does not match digest {}",
+            hex::encode(header.digests.verifier_key)
+        );
+    }
+    Ok(value)
+}
+
+/// Check the length and checksum of an artifact of any format, without
+/// deserializing it. Not applicable to [`crate::Sectioned`] artifacts.
+pub fn check_artifact(path: &Path) -> Result<Header> {
+    let mut file = File::open(path).with_context(|| format!("while opening {path:?}"))?;
+    let header = read_header_from(&mut file)?;
+    read_payload(&mut file, &header)?;
+    Ok(header)
+}
+
+fn read_payload(file: &mut File, header: &Header) -> Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(header.length.try_into()?);
+    file.read_to_end(&mut payload)
+        .context("while reading payload")?;
+    ensure!(
+        payload.len() as u64 == header.length,
+        "Payload is {} bytes, expected {}",
+        payload.len(),
+        header.length
+    );
+    ensure!(
+        Sha256::digest(&payload)[..] == header.content_hash,
+        "Checksum mismatch"
+    );
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    impl Artifact for Vec<u64> {
+        const FORMAT: [u8; 8] = *b"TestVec\0";
+        const VERSION: (u16, u16) = (1, 2);
+    }
+
+    #[test]
+    fn test_roundtrip_and_tamper() {
+        let dir = std::env::temp_dir().join(format!("co-spartan-artifact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.bin");
+        let digests = Digests {
+            circuit: [7; 32],
+            verifier_key: [8; 32],
+        };
+        let value: Vec<u64> = (0..100).collect();
+
+        write_artifact(&value, &digests, &path).unwrap();
+        assert_eq!(read_artifact::<Vec<u64>>(&path, None).unwrap(), value);
+        assert_eq!(
+            read_artifact::<Vec<u64>>(&path, Some(&digests)).unwrap(),
+            value
+        );
+        for wrong in [
+            Digests {
+                circuit: [0; 32],
+                ..digests
+            },
+            Digests {
+                verifier_key: [0; 32],
+                ..digests
+            },
+        ] {
+            assert!(read_artifact::<Vec<u64>>(&path, Some(&wrong)).is_err());
+        }
+        let header = read_header(&path).unwrap();
+        assert_eq!((header.format, header.version), (*b"TestVec\0", (1, 2)));
+
+        // Flip a payload byte
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_SIZE + 10] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(read_artifact::<Vec<u64>>(&path, None).is_err());
+
+        // Truncate
+        bytes[HEADER_SIZE + 10] ^= 1;
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(read_artifact::<Vec<u64>>(&path, None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
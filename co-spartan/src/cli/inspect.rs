@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use ark_ec::pairing::Pairing;
+use co_spartan::{
+    artifact::{check_artifact, read_header},
+    read_artifact, read_sectioned,
+    setup::CoordinatorKey,
+    Artifact, Rep3ProverKey,
+};
+use spartan::{IndexVerifierKey, R1CSProof};
+
+/// Print the header of each artifact and check its checksum. Keys and proofs
+/// are also deserialized, checking that keys contain the verifier key of the
+/// header. Directories are inspected one level deep.
+pub fn inspect<E: Pairing>(paths: Vec<PathBuf>) {
+    for path in paths {
+        let files = if path.is_dir() {
+            let mut files = fs::read_dir(&path)
+                .unwrap_or_else(|error| panic!("couldn't read directory {path:?}: {error}"))
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        } else {
+            vec![path]
+        };
+        for file in files {
+            println!("{}", file.display());
+            if let Err(error) = inspect_file::<E>(&file) {
+                println!("  error: {error:#}");
+            }
+        }
+    }
+}
+
+fn inspect_file<E: Pairing>(path: &Path) -> Result<()> {
+    let header = read_header(path)?;
+    let (major, minor) = header.version;
+    println!(
+        "  format: {} v{major}.{minor}",
+        String::from_utf8_lossy(&header.format)
+    );
+    println!("  circuit digest: {}", hex::encode(header.digests.circuit));
+    println!(
+        "  verifier key digest: {}",
+        hex::encode(header.digests.verifier_key)
+    );
+    println!("  payload: {} bytes", header.length);
+
+    match header.format {
+        format if format == Rep3ProverKey::<E>::FORMAT => {
+            read_sectioned::<Rep3ProverKey<E>>(path, None).context("while reading sections")?;
+            println!("  section table: ok");
+            return Ok(());
+        }
+        format if format == CoordinatorKey::<E>::FORMAT => {
+            read_artifact::<CoordinatorKey<E>>(path, None)?;
+        }
+        format if format == IndexVerifierKey::<E>::FORMAT => {
+            read_artifact::<IndexVerifierKey<E>>(path, None)?;
+        }
+        format if format == R1CSProof::<E>::FORMAT => {
+            read_artifact::<R1CSProof<E>>(path, None)?;
+        }
+        _ => {
+            check_artifact(path)?;
+        }
+    }
+    println!("  checksum: ok");
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use ark_ff::PrimeField;
+use noir_r1cs::{FieldElement, NoirProofScheme, R1CSTriplets, R1CS};
+
+/// The R1CS instance to prove.
+#[derive(clap::Args)]
+#[group(required = true, multiple = false)]
+pub struct Instance {
+    /// A Noir program prepared by `noir-r1cs prepare`.
+    #[clap(long, value_name = "FILE")]
+    r1cs_noir_scheme_path: Option<PathBuf>,
+
+    /// An R1CS instance as (row, col, value) triplets, as written by
+    /// `noir-r1cs export-r1cs` or by hand. The witness is taken from the file.
+    #[clap(long, value_name = "FILE")]
+    r1cs_triplets_path: Option<PathBuf>,
+}
+
+/// Inputs of a Noir program, not needed for R1CS triplets.
+#[derive(clap::Args)]
+#[group(multiple = false)]
+pub struct Inputs {
+    /// A `Prover.toml` or `.json` file.
+    #[clap(long, value_name = "FILE")]
+    r1cs_input_path: Option<PathBuf>,
+
+    /// A JSON object from parameter name to value.
+    #[clap(long, value_name = "JSON")]
+    r1cs_input_json: Option<String>,
+}
+
+/// Public inputs of a proof, the values following the constant one in the
+/// witness, in decimal.
+#[derive(clap::Args)]
+#[group(required = true, multiple = false)]
+pub struct PublicInputs {
+    /// A file with one value per line, as written by `work
+    /// --public-inputs-path`.
+    #[clap(long, value_name = "FILE")]
+    public_inputs_path: Option<PathBuf>,
+
+    /// Comma separated values, empty for none.
+    #[clap(long, value_name = "VALUES", value_delimiter = ',')]
+    public_inputs: Option<Vec<String>>,
+}
+
+impl PublicInputs {
+    pub fn values<F: PrimeField>(&self) -> Result<Vec<F>> {
+        let values = if let Some(path) = &self.public_inputs_path {
+            fs::read_to_string(path)
+                .with_context(|| format!("while reading {path:?}"))?
+                .lines()
+                .map(str::to_owned)
+                .collect()
+        } else {
+            self.public_inputs.clone().unwrap_or_default()
+        };
+        values
+            .iter()
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| F::from_str(value).map_err(|_| anyhow!("Invalid public input {value:?}")))
+            .collect()
+    }
+}
+
+/// Write public inputs to be read back by [`PublicInputs`].
+pub fn write_public_inputs<F: PrimeField>(path: &Path, values: &[F]) -> Result<()> {
+    let lines = values
+        .iter()
+        .map(|value| format!("{value}\n"))
+        .collect::<String>();
+    fs::write(path, lines).with_context(|| format!("while writing {path:?}"))
+}
+
+impl Instance {
+    pub fn r1cs(&self) -> Result<R1CS> {
+        if let Some(path) = &self.r1cs_noir_scheme_path {
+            let scheme: NoirProofScheme =
+                noir_r1cs::read(path).context("while reading Noir proof scheme")?;
+            scheme.check_digest()?;
+            Ok(scheme.r1cs)
+        } else {
+            R1CS::try_from(&self.triplets()?).context("while importing R1CS triplets")
+        }
+    }
+
+    /// The R1CS together with a satisfying witness. Noir programs solve the
+    /// witness from the given inputs.
+    pub fn r1cs_and_witness(&self, inputs: &Inputs) -> Result<(R1CS, Vec<FieldElement>)> {
+        if let Some(path) = &self.r1cs_noir_scheme_path {
+            let scheme: NoirProofScheme =
+                noir_r1cs::read(path).context("while reading Noir proof scheme")?;
+            scheme.check_digest()?;
+            let witness = match (&inputs.r1cs_input_path, &inputs.r1cs_input_json) {
+                (Some(path), _) => scheme.solve_witness(path),
+                (_, Some(json)) => scheme.solve_witness_for_json(json),
+                _ => bail!("Noir programs require --r1cs-input-path or --r1cs-input-json"),
+            };
+            let witness = witness.context("while solving witness")?;
+            Ok((scheme.r1cs, witness))
+        } else {
+            let triplets = self.triplets()?;
+            ensure!(
+                !triplets.witness.is_empty(),
+                "R1CS triplets do not contain a witness"
+            );
+            let r1cs = R1CS::try_from(&triplets).context("while importing R1CS triplets")?;
+            r1cs.verify_witness(&triplets.witness)
+                .context("while verifying the triplets witness")?;
+            Ok((r1cs, triplets.witness))
+        }
+    }
+
+    fn triplets(&self) -> Result<R1CSTriplets> {
+        let path = self
+            .r1cs_triplets_path
+            .as_ref()
+            .context("Missing --r1cs-triplets-path")?;
+        noir_r1cs::read(path).context("while reading R1CS triplets")
+    }
+}
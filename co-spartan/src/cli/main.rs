@@ -1,14 +1,20 @@
+mod inspect;
+mod instance;
 mod setup;
+mod verify;
 mod work;
 
 use std::path::PathBuf;
 
 use ark_bn254::Bn254;
 use clap::{Parser, Subcommand};
+use inspect::inspect;
+use instance::{Inputs, Instance, PublicInputs};
 use mimalloc::MiMalloc;
 use setup::setup;
 use tracing_forest::ForestLayer;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use verify::verify;
 use work::work;
 
 #[global_allocator]
@@ -23,8 +29,8 @@ struct Args {
 #[derive(Subcommand)]
 enum Command {
     Setup {
-        #[clap(long, value_name = "DIR")]
-        r1cs_noir_scheme_path: PathBuf,
+        #[clap(flatten)]
+        instance: Instance,
 
         #[clap(long, value_name = "NUM")]
         log_num_workers_per_party: usize,
@@ -37,11 +43,11 @@ enum Command {
     },
 
     Work {
-        #[clap(long, value_name = "DIR")]
-        r1cs_noir_scheme_path: PathBuf,
+        #[clap(flatten)]
+        instance: Instance,
 
-        #[clap(long, value_name = "DIR")]
-        r1cs_input_path: PathBuf,
+        #[clap(flatten)]
+        inputs: Inputs,
 
         /// The number of workers who will do the committing and proving. Each worker has 1 core.
         #[clap(long, value_name = "NUM")]
@@ -58,6 +64,34 @@ enum Command {
 
         #[clap(long, value_name = "DIR", default_value = "./artifacts")]
         artifacts_dir: PathBuf,
+
+        /// Write the proof to FILE.
+        #[clap(long, value_name = "FILE")]
+        proof_path: Option<PathBuf>,
+
+        /// Write the public inputs of the proof to FILE, for `verify`.
+        #[clap(long, value_name = "FILE")]
+        public_inputs_path: Option<PathBuf>,
+    },
+
+    /// Verify a proof written by `work`.
+    Verify {
+        #[clap(flatten)]
+        public_inputs: PublicInputs,
+
+        /// The verifier key written by `setup`.
+        #[clap(long, value_name = "FILE")]
+        verifier_key: PathBuf,
+
+        #[clap(long, value_name = "FILE")]
+        proof: PathBuf,
+    },
+
+    /// Print the headers and digests of keys and proofs.
+    Inspect {
+        /// Artifact files or directories of artifacts.
+        #[clap(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
     },
 }
 
@@ -67,35 +101,45 @@ fn main() {
 
     match args.command {
         Command::Setup {
-            r1cs_noir_scheme_path,
+            instance,
             log_num_workers_per_party,
             log_num_public_workers,
             artifacts_dir,
         } => setup::<Bn254>(
             artifacts_dir,
-            r1cs_noir_scheme_path,
+            instance,
             log_num_workers_per_party,
             log_num_public_workers,
         ),
         Command::Work {
-            r1cs_noir_scheme_path,
-            r1cs_input_path,
+            instance,
+            inputs,
             artifacts_dir,
             log_num_workers_per_party,
             log_num_public_workers,
             worker_id,
             local,
+            proof_path,
+            public_inputs_path,
         } => {
             work::<Bn254>(
                 artifacts_dir,
-                r1cs_noir_scheme_path,
-                r1cs_input_path,
+                instance,
+                inputs,
                 log_num_workers_per_party,
                 log_num_public_workers,
                 local,
                 worker_id,
+                proof_path,
+                public_inputs_path,
             );
         }
+        Command::Verify {
+            public_inputs,
+            verifier_key,
+            proof,
+        } => verify::<Bn254>(public_inputs, verifier_key, proof),
+        Command::Inspect { paths } => inspect::<Bn254>(paths),
     }
 }
 
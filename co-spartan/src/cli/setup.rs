@@ -27,7 +27,7 @@ use co_spartan::{
         SSRandom,
     },
     utils::{pad_to_power_of_two, split_vec},
-    Rep3ProverKey,
+    write_artifact, write_sectioned, Digests, Rep3ProverKey,
 };
 use crossbeam::thread;
 use itertools::{merge, Itertools};
@@ -39,7 +39,7 @@ use mpi::{
     traits::*,
     Count,
 };
-use noir_r1cs::{FieldElement, NoirProofScheme};
+use noir_r1cs::FieldElement;
 use rand::{rngs::StdRng, seq::SliceRandom, RngCore, SeedableRng};
 use rayon::prelude::*;
 use spartan::{
@@ -48,9 +48,11 @@ use spartan::{
     IndexProverKey, IndexVerifierKey, Indexer, SRS,
 };
 
+use crate::instance::Instance;
+
 pub fn setup<E: Pairing>(
     artifacts_dir_path: PathBuf,
-    r1cs_noir_scheme_path: PathBuf,
+    instance: Instance,
     log_num_workers_per_party: usize,
     log_num_public_workers: Option<usize>,
 ) where
@@ -61,8 +63,12 @@ pub fn setup<E: Pairing>(
 
     let mut rng = StdRng::seed_from_u64(12);
 
-    let mut proof_scheme: NoirProofScheme = noir_r1cs::read(&r1cs_noir_scheme_path).unwrap();
-    let r1cs: spartan::R1CS<E::ScalarField> = proof_scheme.r1cs.into();
+    let r1cs: spartan::R1CS<E::ScalarField> = instance
+        .r1cs()
+        .unwrap_or_else(|error| panic!("couldn't load instance: {error:#}"))
+        .into();
+    let circuit_digest = r1cs.digest();
+    tracing::info!("circuit digest: {}", hex::encode(circuit_digest));
 
     let (coordinator_key, prover_keys) = co_spartan::setup_rep3::<E>(
         &r1cs,
@@ -72,6 +78,11 @@ pub fn setup<E: Pairing>(
     );
 
     let log_instance_size = coordinator_key.log_instance_size;
+    let digests = Digests {
+        circuit: circuit_digest,
+        verifier_key: coordinator_key.ivk.digest(),
+    };
+    tracing::info!("verifier key digest: {}", hex::encode(digests.verifier_key));
 
     let key_out_path_dir = artifacts_dir_path.join(format!(
         "keys_{}_{}",
@@ -81,19 +92,17 @@ pub fn setup<E: Pairing>(
 
     for i in 0..1 << log_num_workers_per_party {
         for j in 0..3 {
-            let mut buf = Vec::new();
-            prover_keys[i][j].serialize_uncompressed(&mut buf).unwrap();
-
-            let mut file_name = key_out_path_dir.join(format!("worker_{}.key", 3 * i + j));
-            let mut f =
-                File::create(&file_name).expect(&format!("could not create file {:?}", file_name));
-            f.write_all(&buf).unwrap();
+            let file_name = key_out_path_dir.join(format!("worker_{}.key", 3 * i + j));
+            write_sectioned(&prover_keys[i][j], &digests, &file_name)
+                .expect(&format!("could not write file {:?}", file_name));
         }
     }
 
-    let mut buf = Vec::new();
-    coordinator_key.serialize_uncompressed(&mut buf).unwrap();
-    let mut file_name = key_out_path_dir.join("coordinator.key");
-    let mut f = File::create(&file_name).expect(&format!("could not create file {:?}", file_name));
-    f.write_all(&buf).unwrap();
+    let file_name = key_out_path_dir.join("coordinator.key");
+    write_artifact(&coordinator_key, &digests, &file_name)
+        .expect(&format!("could not write file {:?}", file_name));
+
+    let file_name = key_out_path_dir.join("verifier.key");
+    write_artifact(&coordinator_key.ivk, &digests, &file_name)
+        .expect(&format!("could not write file {:?}", file_name));
 }
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use ark_ec::pairing::Pairing;
+use co_spartan::{artifact::read_header, read_artifact};
+use spartan::{transcript::TranscriptMerlin, IndexVerifierKey, R1CSProof};
+
+use crate::instance::PublicInputs;
+
+/// Verify a proof written by `work --proof-path` against the verifier key
+/// written by `setup`, for the given public inputs. Neither the circuit nor
+/// the witness is needed.
+pub fn verify<E: Pairing>(public_inputs: PublicInputs, key_path: PathBuf, proof_path: PathBuf) {
+    let public_inputs: Vec<E::ScalarField> = public_inputs
+        .values()
+        .unwrap_or_else(|error| panic!("couldn't read public inputs: {error:#}"));
+
+    // The key is checked against the verifier key digest of its header, and
+    // the proof must belong to the same circuit and verifier key
+    let header = read_header(&key_path)
+        .unwrap_or_else(|error| panic!("couldn't read file {key_path:?}: {error:#}"));
+    let vk: IndexVerifierKey<E> = read_artifact(&key_path, Some(&header.digests))
+        .unwrap_or_else(|error| panic!("couldn't read file {key_path:?}: {error:#}"));
+    let proof: R1CSProof<E> = read_artifact(&proof_path, Some(&header.digests))
+        .unwrap_or_else(|error| panic!("couldn't read file {proof_path:?}: {error:#}"));
+
+    if let Err(e) = proof.verify(&vk, &public_inputs, &mut TranscriptMerlin::new(b"dfs")) {
+        println!("proof verification failed: {:?}", e);
+        std::process::exit(1);
+    }
+    println!("proof verified");
+}
@@ -36,7 +36,9 @@ use co_spartan::{
         mpi::{Rep3CoordinatorMPI, Rep3WorkerMPI},
         NetworkCoordinator, NetworkWorker,
     },
+    read_artifact, read_sectioned,
     setup::CoordinatorKey,
+    write_artifact, Digests,
 };
 use crossbeam::thread;
 use itertools::{merge, Itertools};
@@ -47,24 +49,28 @@ use mpi::{
     traits::*,
     Count,
 };
-use noir_r1cs::NoirProofScheme;
 use rand::RngCore;
 use rayon::prelude::*;
 use spartan::{transcript::TranscriptMerlin, IndexProverKey, IndexVerifierKey, Indexer, SRS};
 
 // use ark_ec::bn::Bls12;
-use crate::current_num_threads;
+use crate::{
+    current_num_threads,
+    instance::{write_public_inputs, Inputs, Instance},
+};
 
 const ROOT_RANK: i32 = 0;
 
 pub fn work<E: Pairing>(
     artifacts_dir: PathBuf,
-    r1cs_noir_scheme_path: PathBuf,
-    r1cs_input_path: PathBuf,
+    instance: Instance,
+    inputs: Inputs,
     log_num_workers_per_party: usize,
     log_num_public_workers: Option<usize>,
     local: bool,
     worker_id: Option<usize>,
+    proof_path: Option<PathBuf>,
+    public_inputs_path: Option<PathBuf>,
 ) where
     E::ScalarField: PrimeField<BigInt = BigInt<4>>,
 {
@@ -95,11 +101,13 @@ pub fn work<E: Pairing>(
     if is_coordinator {
         coordinator_work::<E, _>(
             keys_dir,
-            r1cs_noir_scheme_path,
-            r1cs_input_path,
+            instance,
+            inputs,
             log_num_workers_per_party,
             log_num_public_workers,
             communicator,
+            proof_path,
+            public_inputs_path,
         );
     } else {
         let worker_id = if local {
@@ -121,11 +129,13 @@ pub fn work<E: Pairing>(
 #[tracing::instrument(skip_all, name = "coordinator_work")]
 fn coordinator_work<E: Pairing, C: Communicator>(
     keys_dir: PathBuf,
-    r1cs_noir_scheme_path: PathBuf,
-    r1cs_input_path: PathBuf,
+    instance: Instance,
+    inputs: Inputs,
     log_num_workers_per_party: usize,
     log_num_public_workers: usize,
     communicator: C,
+    proof_path: Option<PathBuf>,
+    public_inputs_path: Option<PathBuf>,
 ) where
     E::ScalarField: PrimeField<BigInt = BigInt<4>>,
 {
@@ -134,33 +144,33 @@ fn coordinator_work<E: Pairing, C: Communicator>(
     let root_process = communicator.process_at_rank(ROOT_RANK);
     let mut log = Vec::new();
 
-    let pk = {
-        let mut buf = Vec::new();
-
-        let mut file_name = keys_dir.join("coordinator.key");
-
-        let mut f = File::open(&file_name).expect(&format!("couldn't open file {:?}", keys_dir));
-        let _ = f.read_to_end(&mut buf);
-        let buf_slice = buf.as_slice();
-
-        CoordinatorKey::<E>::deserialize_uncompressed_unchecked(buf_slice).unwrap()
-    };
-
-    let mut proof_scheme: NoirProofScheme = noir_r1cs::read(&r1cs_noir_scheme_path).unwrap();
-    let mut z: Vec<E::ScalarField> = proof_scheme
-        .solve_witness(&r1cs_input_path)
-        .unwrap()
+    let (r1cs, z) = instance
+        .r1cs_and_witness(&inputs)
+        .unwrap_or_else(|error| panic!("couldn't load instance: {error:#}"));
+    let z: Vec<E::ScalarField> = z
         .iter()
         .map(|v| E::ScalarField::from_bigint(v.into_bigint()).unwrap())
         .collect();
-    let r1cs: spartan::R1CS<E::ScalarField> = proof_scheme.r1cs.into();
+    let r1cs: spartan::R1CS<E::ScalarField> = r1cs.into();
+    let circuit_digest = r1cs.digest();
+
+    // The key is checked against the verifier key digest of its header
+    let (pk, digests) = {
+        let file_name = keys_dir.join("coordinator.key");
+        let header = co_spartan::artifact::read_header(&file_name)
+            .expect(&format!("couldn't read file {:?}", file_name));
+        let digests = Digests {
+            circuit: circuit_digest,
+            verifier_key: header.digests.verifier_key,
+        };
+        let pk: CoordinatorKey<E> = read_artifact(&file_name, Some(&digests))
+            .expect(&format!("couldn't read file {:?}", file_name));
+        (pk, digests)
+    };
+    let public_inputs = z[1..=r1cs.public_inputs].to_vec();
 
-    let witness_shares = co_spartan::split_witness::<E>(
-        z,
-        r1cs.log2_instance_size(),
-        log_num_workers_per_party,
-        &mut rng,
-    );
+    let witness_shares =
+        co_spartan::split_witness::<E>(&z, &r1cs, log_num_workers_per_party, &mut rng);
 
     let log_instance_size = pk.log_instance_size;
 
@@ -175,6 +185,9 @@ fn coordinator_work<E: Pairing, C: Communicator>(
 
     let _: Vec<_> = network.receive_responses("ready".to_string());
 
+    // Workers check their keys against the same circuit and verifier key
+    network.broadcast_request(digests);
+
     let witness_shares = witness_shares
         .into_iter()
         .flatten()
@@ -196,16 +209,26 @@ fn coordinator_work<E: Pairing, C: Communicator>(
         &pk.ipk,
         &pk.pub_ipk,
         &pk.ivk,
+        &public_inputs,
         &mut transcript,
         &mut network,
     );
 
     let mut verifier_transcript = TranscriptMerlin::new(b"dfs");
-    if let Err(e) = proof.verify(&pk.ivk, &Vec::new(), &mut verifier_transcript) {
+    if let Err(e) = proof.verify(&pk.ivk, &public_inputs, &mut verifier_transcript) {
         println!("proof verification failed: {:?}", e);
         std::process::exit(1);
     }
 
+    if let Some(proof_path) = &proof_path {
+        write_artifact(&proof, &digests, proof_path)
+            .expect(&format!("couldn't write file {:?}", proof_path));
+    }
+    if let Some(public_inputs_path) = &public_inputs_path {
+        write_public_inputs(public_inputs_path, &public_inputs)
+            .unwrap_or_else(|error| panic!("couldn't write public inputs: {error:#}"));
+    }
+
     tracing::info!("coordinator time: {:?}", coordinator_time);
     tracing::info_span!("proof size").in_scope(|| {
         proof.log_size_report();
@@ -235,16 +258,23 @@ fn worker_work<E: Pairing, C: Communicator>(
     let root_process = communicator.process_at_rank(ROOT_RANK);
     let mut log = Vec::new();
 
-    let pk = {
-        let mut buf = Vec::new();
-
-        let mut file_name = keys_dir.join(format!("worker_{}.key", worker_id));
+    let mut network = Rep3WorkerMPI::new(
+        root_process,
+        &mut log,
+        log_num_workers_per_party,
+        log_num_public_workers,
+        size,
+        rank as usize,
+    );
 
-        let mut f = File::open(&file_name).expect(&format!("couldn't open file {:?}", file_name));
-        let _ = f.read_to_end(&mut buf);
-        let buf_slice = buf.as_slice();
+    network.send_response("ready".to_string());
 
-        co_spartan::Rep3ProverKey::<E>::deserialize_uncompressed_unchecked(buf_slice).unwrap()
+    // Sections of the key are loaded as they are used
+    let digests: Digests = network.receive_request();
+    let mut pk: co_spartan::Rep3ProverKey<E> = {
+        let file_name = keys_dir.join(format!("worker_{}.key", worker_id));
+        read_sectioned(&file_name, Some(&digests))
+            .expect(&format!("couldn't read file {:?}", file_name))
     };
 
     let current_num_threads = current_num_threads();
@@ -269,17 +299,6 @@ fn worker_work<E: Pairing, C: Communicator>(
     seed_1.feed(&pk.seed_1.as_bytes());
     let mut random = SSRandom::<Blake2s512Rng>::new(seed_0, seed_1);
 
-    let mut network = Rep3WorkerMPI::new(
-        root_process,
-        &mut log,
-        log_num_workers_per_party,
-        log_num_public_workers,
-        size,
-        rank as usize,
-    );
-
-    network.send_response("ready".to_string());
-
     let witness_share = network.receive_request();
 
     co_spartan::SpartanProverWorker::new(
@@ -288,7 +307,8 @@ fn worker_work<E: Pairing, C: Communicator>(
         pub_log_chunk_size,
         pub_start_eq,
     )
-    .prove(&pk, witness_share, &mut random, active, &mut network);
+    .prove(&mut pk, witness_share, &mut random, active, &mut network)
+    .unwrap_or_else(|error| panic!("worker failed: {error:#}"));
 
     let (send_bytes, recv_bytes) = network.total_bandwidth_used();
     tracing::info!(
@@ -27,7 +27,7 @@ use ark_std::{
 use spartan::{
     logup::LogLookupProof,
     math::{MaskPolynomial, Math},
-    transcript::Transcript,
+    transcript::{init_transcript, Transcript},
     utils::{aggregate_proof, combine_comm, merge_proof},
     verifier::{BatchOracleEval, DFSVerifier, VerifierState},
     zk::{generate_mask_polynomial, ZKMLCommit, ZKMLCommitterKey, ZKMLProof, ZKSumcheckProof},
@@ -101,6 +101,7 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
         index: &IndexProverKey<E>,
         pub_index: &IndexProverKey<E>,
         vk: &IndexVerifierKey<E>,
+        public_inputs: &[E::ScalarField],
         transcript: &mut T,
         network: &mut N,
     ) -> (R1CSProof<E>, Duration)
@@ -111,6 +112,7 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
 
         let time = Instant::now();
         let mut verifier_state: VerifierState<E> = DFSVerifier::verifier_init(index.padded_num_var);
+        init_transcript(transcript, vk, public_inputs);
         state.time_elapsed += time.elapsed();
 
         Self::first_round(&mut state, &index, 2, None, network, transcript);
@@ -144,7 +146,6 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
 
         let lookup_proof = Self::fourth_round(
             pub_index,
-            vk,
             &mut state,
             v_msg4.verifier_message[0],
             network,
@@ -192,7 +193,7 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
         let p_hat = if let Some(mask_num_vars) = mask_num_var {
             generate_mask_polynomial(&mut mask_rng, mask_num_vars, hiding_bound, false)
         } else {
-            generate_mask_polynomial(&mut mask_rng, index.padded_num_var, hiding_bound, false)
+            generate_mask_polynomial(&mut mask_rng, index.padded_num_var - 1, hiding_bound, false)
         };
         let labeled_p_hat =
             LabeledPolynomial::new("p_hat".to_owned(), p_hat, Some(hiding_bound), None);
@@ -207,7 +208,7 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
             (base_commitment_vec[0].g_product + hiding_commitment).into();
         let commitment = Commitment {
             g_product: hidden_commitment,
-            nv: index.padded_num_var,
+            nv: index.padded_num_var - 1,
         };
 
         transcript.append_serializable(b"w_commitment", &commitment);
@@ -406,8 +407,13 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
         state.r_y = final_point.to_vec();
         state.time_elapsed += time;
 
-        let (val_ws, time) =
-            rep3_eval_poly_coordinator::<E, N>(index.padded_num_var, 1, &final_point, network);
+        // `w` is the odd half of `z`, evaluated at `r_y[1..]`
+        let (val_ws, time) = rep3_eval_poly_coordinator::<E, N>(
+            index.padded_num_var - 1,
+            1,
+            &final_point[1..],
+            network,
+        );
         state.val_w = val_ws[0];
         state.time_elapsed += time;
 
@@ -440,9 +446,8 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
         state.time_elapsed += time;
 
         let (zk_open_pf, time) = rep3_zk_open_poly_coordinator(
-            index.padded_num_var,
-            &state.witness_comm.clone().unwrap(),
-            &state.r_y[..],
+            index.padded_num_var - 1,
+            &state.r_y[1..],
             &index.ck_w,
             &state.witness_mask,
             network,
@@ -455,7 +460,6 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
     #[tracing::instrument(skip_all, name = "SpartanProverCoordinator::fourth_round")]
     fn fourth_round(
         pub_index: &IndexProverKey<E>,
-        vk: &IndexVerifierKey<E>,
         state: &mut ProverState<E>,
         v_msg: E::ScalarField,
         network: &mut N,
@@ -535,27 +539,21 @@ impl<E: Pairing, N: NetworkCoordinator> SpartanProverCoordinator<E, N> {
         let eta: E::ScalarField = transcript.get_scalar_challenge(b"eta");
         network.broadcast_request(eta.clone());
 
-        let (batch_oracle, time) = batch_open_poly_coordinator(
+        // The workers open the `h` polynomials, eq_tilde_rx, eq_tilde_ry,
+        // val_a, val_b, val_c, the addresses and their multiplicities. Only the
+        // commitments to the `h` polynomials are sent, the verifier has the
+        // others.
+        let (mut batch_oracle, time) = batch_open_poly_coordinator(
             poly_info.num_variables,
-            15,
+            13,
             &pub_index.ck_index,
-            &[
-                comms[0].clone(),
-                comms[1].clone(),
-                comms[2].clone(),
-                comms[3].clone(),
-                state.eq_tilde_rx_comm.clone().unwrap(),
-                state.eq_tilde_ry_comm.clone().unwrap(),
-                vk.val_a_oracle.clone(),
-                vk.val_b_oracle.clone(),
-                vk.val_c_oracle.clone(),
-            ],
             &final_point,
             pub_index.ck_index.g,
             network,
             false,
         );
 
+        batch_oracle.commitment = comms;
         state.time_elapsed += time;
 
         LogLookupProof {
@@ -865,7 +863,6 @@ pub fn batch_open_poly_coordinator<'a, E: Pairing, N: NetworkCoordinator>(
     num_var: usize,
     num_poly: usize,
     merge_ck: &CommitterKey<E>,
-    comms: &[Commitment<E>],
     final_point: &[E::ScalarField],
     g: E::G1Affine,
     network: &mut N,
@@ -917,25 +914,18 @@ pub fn batch_open_poly_coordinator<'a, E: Pairing, N: NetworkCoordinator>(
     let batch_proof = merge_proof(&pf1, &pf2);
 
     let mut evals = Vec::new();
-    let mut debug_evals = Vec::new();
     for i in 0..num_poly {
         let mut e = Vec::new();
         for j in 0..1 << log_num_workers {
             e.push(es[j][i]);
         }
         let ep = DenseMultilinearExtension::from_evaluations_vec(log_num_workers, e);
-        if i < comms.len() {
-            evals.push(ep.evaluate(&final_point[num_var - log_num_workers..num_var].to_vec()));
-        } else {
-            debug_evals
-                .push(ep.evaluate(&final_point[num_var - log_num_workers..num_var].to_vec()));
-        }
+        evals.push(ep.evaluate(&final_point[num_var - log_num_workers..num_var].to_vec()));
     }
 
     let batch_oracle = BatchOracleEval {
         val: evals,
-        debug_val: debug_evals,
-        commitment: comms.to_vec(),
+        commitment: Vec::new(),
         proof: batch_proof,
     };
     (batch_oracle, time.elapsed())
@@ -944,22 +934,13 @@ pub fn batch_open_poly_coordinator<'a, E: Pairing, N: NetworkCoordinator>(
 #[tracing::instrument(skip_all, name = "rep3_zk_open_poly_coordinator")]
 pub fn rep3_zk_open_poly_coordinator<'a, E: Pairing, N: NetworkCoordinator>(
     num_var: usize,
-    comm: &Commitment<E>,
     final_point: &[E::ScalarField],
     ck: &ZKMLCommitterKey<E, SparsePolynomial<E::ScalarField, SparseTerm>>,
     p_hat: &LabeledPolynomial<E::ScalarField, SparsePolynomial<E::ScalarField, SparseTerm>>,
     network: &mut N,
 ) -> (ZKMLProof<E>, Duration) {
-    let (batch_oracle, tot_time) = batch_open_poly_coordinator(
-        num_var,
-        1,
-        &ck.0,
-        &[comm.clone()],
-        final_point,
-        ck.0.g,
-        network,
-        true,
-    );
+    let (batch_oracle, tot_time) =
+        batch_open_poly_coordinator(num_var, 1, &ck.0, final_point, ck.0.g, network, true);
 
     let time = Instant::now();
 
@@ -986,3 +967,112 @@ pub fn rep3_zk_open_poly_coordinator<'a, E: Pairing, N: NetworkCoordinator>(
         tot_time + time.elapsed(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use ark_bn254::{Bn254, Fr};
+    use ark_linear_sumcheck::rng::{Blake2s512Rng, FeedableRNG};
+    use noir_r1cs::R1CSBuilder;
+    use rand::{rngs::StdRng, SeedableRng};
+    use spartan::{transcript::TranscriptMerlin, R1CS};
+
+    use super::*;
+    use crate::{
+        mpc::SSRandom, network::local::Rep3CoordinatorLocal, setup_rep3, split_witness,
+        SpartanProverWorker,
+    };
+
+    /// Prove with a coordinator and six workers running as threads, and check
+    /// the proof with the verifier of `spartan`.
+    #[test]
+    fn test_local_end_to_end() {
+        let (log_num_workers_per_party, log_num_public_workers) = (1, 2);
+
+        // A chain `x_{i+1} = x_i · (x_i + 1)` from a public `x_0 = 3`
+        let mut r1cs = R1CSBuilder::new();
+        let one = r1cs.new_witness();
+        let mut x = r1cs.new_witness();
+        r1cs.public_inputs = 1;
+        let mut witness = vec![Fr::one(), Fr::from(3u64)];
+        for _ in 0..40 {
+            let next = r1cs.new_witness();
+            r1cs.add_constraint(
+                &[(Fr::one(), x)],
+                &[(Fr::one(), x), (Fr::one(), one)],
+                &[(Fr::one(), next)],
+            );
+            witness.push(witness[x] * (witness[x] + Fr::one()));
+            x = next;
+        }
+        let r1cs = R1CS::<Fr>::from(r1cs.build());
+        let public_inputs = witness[1..=r1cs.public_inputs].to_vec();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let (coordinator_key, prover_keys) = setup_rep3::<Bn254>(
+            &r1cs,
+            log_num_workers_per_party,
+            log_num_public_workers,
+            &mut rng,
+        );
+        let mut witness_shares =
+            split_witness::<Bn254>(&witness, &r1cs, log_num_workers_per_party, &mut rng)
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+        witness_shares.sort_by_key(|(worker_id, _)| *worker_id);
+
+        let (mut network, worker_networks) =
+            Rep3CoordinatorLocal::new(log_num_workers_per_party, log_num_public_workers);
+        let proof = thread::scope(|scope| {
+            let worker_keys = prover_keys.into_iter().flatten();
+            for (worker_id, (mut pk, mut network)) in worker_keys.zip(worker_networks).enumerate() {
+                scope.spawn(move || {
+                    let log_chunk_size = pk.num_variables - log_num_workers_per_party;
+                    let pub_log_chunk_size = pk.num_variables - log_num_public_workers;
+                    let mut seed_0 = Blake2s512Rng::setup();
+                    seed_0.feed(&pk.seed_0.as_bytes()).unwrap();
+                    let mut seed_1 = Blake2s512Rng::setup();
+                    seed_1.feed(&pk.seed_1.as_bytes()).unwrap();
+                    let witness_share = network.receive_request();
+                    SpartanProverWorker::new(
+                        log_chunk_size,
+                        (1 << log_chunk_size) * (worker_id / 3),
+                        pub_log_chunk_size,
+                        (1 << pub_log_chunk_size) * worker_id,
+                    )
+                    .prove(
+                        &mut pk,
+                        witness_share,
+                        &mut SSRandom::new(seed_0, seed_1),
+                        worker_id < (1 << log_num_public_workers),
+                        &mut network,
+                    )
+                    .unwrap();
+                });
+            }
+
+            network.send_requests(witness_shares.into_iter().map(|(_, z)| z).collect());
+            let (proof, _) = SpartanProverCoordinator::<Bn254, _>::prove(
+                &coordinator_key.ipk,
+                &coordinator_key.pub_ipk,
+                &coordinator_key.ivk,
+                &public_inputs,
+                &mut TranscriptMerlin::new(b"dfs"),
+                &mut network,
+            );
+            proof
+        });
+
+        let verify = |public_inputs: &[Fr]| {
+            proof.verify(
+                &coordinator_key.ivk,
+                public_inputs,
+                &mut TranscriptMerlin::new(b"dfs"),
+            )
+        };
+        verify(&public_inputs).unwrap();
+        assert!(verify(&[Fr::from(4u64)]).is_err());
+    }
+}
@@ -1,13 +1,17 @@
+pub mod artifact;
 pub mod coordinator;
 pub mod mpc;
 pub mod network;
+pub mod section;
 pub mod setup;
 pub mod sumcheck;
 pub mod utils;
 pub mod witness;
 pub mod worker;
 
+pub use artifact::{read_artifact, write_artifact, Artifact, Digests};
 pub use coordinator::SpartanProverCoordinator;
+pub use section::{read_sectioned, write_sectioned, Sectioned};
 pub use setup::setup_rep3;
 pub use witness::split_witness;
 pub use worker::{Rep3ProverKey, SpartanProverWorker};
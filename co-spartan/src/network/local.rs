@@ -0,0 +1,145 @@
+//! Network between threads of one process, for running the coordinator and
+//! all workers locally, e.g. in tests.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use super::{mpi::serialize_to_vec, NetworkCoordinator, NetworkWorker};
+
+pub struct Rep3CoordinatorLocal {
+    pub log_num_workers_per_party: usize,
+    pub log_num_public_workers: usize,
+    pub total_send_bytes: usize,
+    pub total_recv_bytes: usize,
+    requests: Vec<Sender<Vec<u8>>>,
+    responses: Vec<Receiver<Vec<u8>>>,
+}
+
+pub struct Rep3WorkerLocal {
+    pub log_num_workers_per_party: usize,
+    pub log_num_public_workers: usize,
+    pub rank: usize,
+    pub total_send_bytes: usize,
+    pub total_recv_bytes: usize,
+    requests: Receiver<Vec<u8>>,
+    responses: Sender<Vec<u8>>,
+}
+
+impl Rep3CoordinatorLocal {
+    /// The coordinator and its `3 · 2^log_num_workers_per_party` workers. As
+    /// with MPI, worker `i` has rank `i + 1`.
+    pub fn new(
+        log_num_workers_per_party: usize,
+        log_num_public_workers: usize,
+    ) -> (Self, Vec<Rep3WorkerLocal>) {
+        let num_workers = (1 << log_num_workers_per_party) * 3;
+        let mut requests = Vec::with_capacity(num_workers);
+        let mut responses = Vec::with_capacity(num_workers);
+        let mut workers = Vec::with_capacity(num_workers);
+        for worker_id in 0..num_workers {
+            let (request_sender, request_receiver) = channel();
+            let (response_sender, response_receiver) = channel();
+            requests.push(request_sender);
+            responses.push(response_receiver);
+            workers.push(Rep3WorkerLocal {
+                log_num_workers_per_party,
+                log_num_public_workers,
+                rank: worker_id + 1,
+                total_send_bytes: 0,
+                total_recv_bytes: 0,
+                requests: request_receiver,
+                responses: response_sender,
+            });
+        }
+        let coordinator = Self {
+            log_num_workers_per_party,
+            log_num_public_workers,
+            total_send_bytes: 0,
+            total_recv_bytes: 0,
+            requests,
+            responses,
+        };
+        (coordinator, workers)
+    }
+}
+
+impl NetworkCoordinator for Rep3CoordinatorLocal {
+    fn receive_responses<T: CanonicalSerialize + CanonicalDeserialize>(
+        &mut self,
+        _default_response: T,
+    ) -> Vec<T> {
+        self.responses
+            .iter()
+            .map(|responses| {
+                let bytes = responses.recv().expect("worker disconnected");
+                self.total_recv_bytes += bytes.len();
+                T::deserialize_uncompressed_unchecked(&bytes[..]).unwrap()
+            })
+            .collect()
+    }
+
+    fn broadcast_request<T: CanonicalSerialize + CanonicalDeserialize + Clone>(&mut self, data: T) {
+        let bytes = serialize_to_vec(&data);
+        for requests in &self.requests {
+            self.total_send_bytes += bytes.len();
+            requests.send(bytes.clone()).expect("worker disconnected");
+        }
+    }
+
+    fn send_requests<T: CanonicalSerialize + CanonicalDeserialize + Clone>(
+        &mut self,
+        data: Vec<T>,
+    ) {
+        assert_eq!(data.len(), self.requests.len(), "one request per worker");
+        for (requests, data) in self.requests.iter().zip(&data) {
+            let bytes = serialize_to_vec(data);
+            self.total_send_bytes += bytes.len();
+            requests.send(bytes).expect("worker disconnected");
+        }
+    }
+
+    fn log_num_workers_per_party(&self) -> usize {
+        self.log_num_workers_per_party
+    }
+
+    fn log_num_pub_workers(&self) -> usize {
+        self.log_num_public_workers
+    }
+
+    fn total_bandwidth_used(&self) -> (usize, usize) {
+        (self.total_send_bytes, self.total_recv_bytes)
+    }
+}
+
+impl NetworkWorker for Rep3WorkerLocal {
+    fn send_response<T: CanonicalSerialize + CanonicalDeserialize>(&mut self, data: T) {
+        let bytes = serialize_to_vec(&data);
+        self.total_send_bytes += bytes.len();
+        self.responses
+            .send(bytes)
+            .expect("coordinator disconnected");
+    }
+
+    fn receive_request<T: CanonicalSerialize + CanonicalDeserialize>(&mut self) -> T {
+        let bytes = self.requests.recv().expect("coordinator disconnected");
+        self.total_recv_bytes += bytes.len();
+        T::deserialize_uncompressed_unchecked(&bytes[..]).unwrap()
+    }
+
+    fn log_num_pub_workers(&self) -> usize {
+        self.log_num_public_workers
+    }
+
+    fn log_num_workers_per_party(&self) -> usize {
+        self.log_num_workers_per_party
+    }
+
+    fn rank(&self) -> usize {
+        self.rank
+    }
+
+    fn total_bandwidth_used(&self) -> (usize, usize) {
+        (self.total_send_bytes, self.total_recv_bytes)
+    }
+}
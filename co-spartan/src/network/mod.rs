@@ -1,3 +1,4 @@
+pub mod local;
 pub mod mpi;
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
@@ -0,0 +1,470 @@
+//! Artifacts stored as independently checksummed sections, so that a worker
+//! only loads the parts of its prover key used in the current round.
+//!
+//! The payload after the [`Header`] holds the uncompressed canonical
+//! serialization of each section, one after the other, followed by a table
+//! with a 56 byte `(name, offset, length, SHA-256)` entry per section and the
+//! number of entries as a little endian `u32`. Offsets are relative to the
+//! start of the payload. The content hash in the header covers the table, the
+//! sections are checked against their own hash when loaded.
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use anyhow::{ensure, Context as _, Result};
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use sha2::{Digest as _, Sha256};
+use tracing::{info, instrument};
+
+use crate::{
+    artifact::{read_header_from, Artifact, Digest, Digests, Header, HEADER_SIZE},
+    worker::Rep3ProverKey,
+};
+
+const ENTRY_SIZE: usize = 56;
+
+/// Artifacts stored as sections.
+pub trait Sectioned: Artifact + Sized {
+    fn write_sections(&self, writer: &mut SectionWriter) -> Result<()>;
+
+    /// Construct the value, typically with [`Lazy`] fields for the large
+    /// sections.
+    fn read_sections(reader: &Arc<SectionReader>) -> Result<Self>;
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    name: [u8; 8],
+    offset: u64,
+    length: u64,
+    hash: Digest,
+}
+
+pub struct SectionWriter {
+    file: File,
+    offset: u64,
+    entries: Vec<Entry>,
+}
+
+impl SectionWriter {
+    /// Append a section. Only one section is serialized in memory at a time.
+    pub fn add<T: CanonicalSerialize>(&mut self, name: [u8; 8], value: &T) -> Result<()> {
+        ensure!(
+            self.entries.iter().all(|entry| entry.name != name),
+            "Duplicate section {}",
+            section_name(&name)
+        );
+        let mut bytes = Vec::with_capacity(value.uncompressed_size());
+        value
+            .serialize_uncompressed(&mut bytes)
+            .with_context(|| format!("while serializing section {}", section_name(&name)))?;
+        self.file
+            .write_all(&bytes)
+            .context("while writing section")?;
+        self.entries.push(Entry {
+            name,
+            offset: self.offset,
+            length: bytes.len() as u64,
+            hash: Sha256::digest(&bytes).into(),
+        });
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Write a sectioned artifact for the circuit and verifier key with the given
+/// digests.
+#[instrument(skip(value, digests))]
+pub fn write_sectioned<T: Sectioned>(value: &T, digests: &Digests, path: &Path) -> Result<()> {
+    let mut file = File::create(path).context("while creating output file")?;
+    file.write_all(&[0; HEADER_SIZE])
+        .context("while reserving header")?;
+    let mut writer = SectionWriter {
+        file,
+        offset: 0,
+        entries: Vec::new(),
+    };
+    value.write_sections(&mut writer)?;
+    let SectionWriter {
+        mut file,
+        offset,
+        entries,
+    } = writer;
+
+    let mut table = Vec::with_capacity(entries.len() * ENTRY_SIZE + 4);
+    for entry in &entries {
+        table.extend(entry.name);
+        table.extend(entry.offset.to_le_bytes());
+        table.extend(entry.length.to_le_bytes());
+        table.extend(entry.hash);
+    }
+    table.extend((entries.len() as u32).to_le_bytes());
+    file.write_all(&table).context("while writing table")?;
+
+    let header = Header {
+        format: T::FORMAT,
+        version: T::VERSION,
+        digests: *digests,
+        length: offset + table.len() as u64,
+        content_hash: Sha256::digest(&table).into(),
+    };
+    file.seek(SeekFrom::Start(0))
+        .context("while seeking to header")?;
+    file.write_all(&header.to_bytes())
+        .context("while writing header")?;
+    file.sync_all().context("while syncing output file")?;
+    info!(
+        ?path,
+        size = HEADER_SIZE as u64 + header.length,
+        sections = entries.len(),
+        "Wrote sectioned artifact"
+    );
+    Ok(())
+}
+
+/// Read a sectioned artifact, checking its format, version and section
+/// table. Sections are checked when they are loaded.
+#[instrument(skip(digests))]
+pub fn read_sectioned<T: Sectioned>(path: &Path, digests: Option<&Digests>) -> Result<T> {
+    let reader = Arc::new(SectionReader::open::<T>(path, digests)?);
+    T::read_sections(&reader)
+}
+
+/// Open sectioned artifact to load sections from.
+pub struct SectionReader {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: Vec<Entry>,
+    validate: Validate,
+}
+
+impl SectionReader {
+    fn open<T: Sectioned>(path: &Path, digests: Option<&Digests>) -> Result<Self> {
+        let mut file = File::open(path).with_context(|| format!("while opening {path:?}"))?;
+        let header = read_header_from(&mut file)?;
+        header.check::<T>(digests)?;
+        let size = file.metadata().context("while reading file size")?.len();
+        ensure!(
+            size == HEADER_SIZE as u64 + header.length,
+            "File is {size} bytes, expected {}",
+            HEADER_SIZE as u64 + header.length
+        );
+
+        // Table at the end of the payload
+        ensure!(header.length >= 4, "Missing section table");
+        let mut count = [0; 4];
+        file.seek(SeekFrom::End(-4))?;
+        file.read_exact(&mut count)
+            .context("while reading section count")?;
+        let count = u32::from_le_bytes(count) as u64;
+        let table_length = count * ENTRY_SIZE as u64 + 4;
+        ensure!(table_length <= header.length, "Section table out of bounds");
+        let table_offset = header.length - table_length;
+        let mut table = vec![0; table_length as usize];
+        file.seek(SeekFrom::Start(HEADER_SIZE as u64 + table_offset))?;
+        file.read_exact(&mut table)
+            .context("while reading section table")?;
+        ensure!(
+            Sha256::digest(&table)[..] == header.content_hash,
+            "Checksum mismatch in section table"
+        );
+
+        let entries = table[..table.len() - 4]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|bytes| -> Result<Entry> {
+                let entry = Entry {
+                    name: bytes[..8].try_into()?,
+                    offset: u64::from_le_bytes(bytes[8..16].try_into()?),
+                    length: u64::from_le_bytes(bytes[16..24].try_into()?),
+                    hash: bytes[24..].try_into()?,
+                };
+                ensure!(
+                    entry
+                        .offset
+                        .checked_add(entry.length)
+                        .is_some_and(|end| end <= table_offset),
+                    "Section {} out of bounds",
+                    section_name(&entry.name)
+                );
+                Ok(entry)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            entries,
+            validate: T::VALIDATE,
+        })
+    }
+
+    fn entry(&self, name: [u8; 8]) -> Result<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .with_context(|| format!("Missing section {}", section_name(&name)))
+    }
+
+    /// Read and check a section. The value is deserialized straight from the
+    /// file region of the section, which is hashed along the way, so the
+    /// section is never held in memory twice.
+    #[instrument(skip(self), fields(path = ?self.path, name = section_name(&name)))]
+    pub fn read<T: CanonicalDeserialize>(&self, name: [u8; 8]) -> Result<T> {
+        let entry = self.entry(name)?;
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(HEADER_SIZE as u64 + entry.offset))?;
+        let mut reader = BufReader::new(HashingReader {
+            inner: (&mut *file).take(entry.length),
+            hasher: Sha256::new(),
+        });
+        let value = T::deserialize_with_mode(&mut reader, Compress::No, self.validate);
+
+        // Hash the rest of the section, which is empty for a well formed one
+        let trailing = io::copy(&mut reader, &mut io::sink()).context("while reading section")?;
+        let HashingReader { inner, hasher } = reader.into_inner();
+        ensure!(
+            inner.limit() == 0,
+            "Section {} is truncated",
+            section_name(&name)
+        );
+        ensure!(
+            hasher.finalize()[..] == entry.hash,
+            "Checksum mismatch in section {}",
+            section_name(&name)
+        );
+        let value = value
+            .with_context(|| format!("while deserializing section {}", section_name(&name)))?;
+        ensure!(trailing == 0, "Trailing bytes after section");
+        Ok(value)
+    }
+}
+
+/// Reader that hashes everything read through it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn section_name(name: &[u8; 8]) -> String {
+    String::from_utf8_lossy(name)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// A value in memory or in a section of a [`Sectioned`] artifact, loaded on
+/// first use.
+pub struct Lazy<T> {
+    value: OnceLock<T>,
+    source: Option<(Arc<SectionReader>, [u8; 8])>,
+}
+
+impl<T> Lazy<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: OnceLock::from(value),
+            source: None,
+        }
+    }
+
+    /// Refer to a section, checking that it exists.
+    pub fn section(reader: &Arc<SectionReader>, name: [u8; 8]) -> Result<Self> {
+        reader.entry(name)?;
+        Ok(Self {
+            value: OnceLock::new(),
+            source: Some((Arc::clone(reader), name)),
+        })
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Free the value if it can be loaded again from its section.
+    pub fn unload(&mut self) {
+        if self.source.is_some() {
+            self.value.take();
+        }
+    }
+}
+
+impl<T: CanonicalDeserialize> Lazy<T> {
+    /// The value, loaded from its section on first use.
+    pub fn get(&self) -> Result<&T> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let (reader, name) = self.source.as_ref().context("Value without section")?;
+        let value = reader.read(*name)?;
+        Ok(self.value.get_or_init(|| value))
+    }
+}
+
+impl<T: Debug> Debug for Lazy<T> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (self.value.get(), &self.source) {
+            (Some(value), _) => value.fmt(f),
+            (None, Some((_, name))) => write!(f, "<section {}>", section_name(name)),
+            (None, None) => write!(f, "<unloaded>"),
+        }
+    }
+}
+
+/// The small fields of a [`Rep3ProverKey`], loaded eagerly.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct Rep3KeyMeta {
+    party_id: usize,
+    num_parties: usize,
+    num_variables: usize,
+    num_variables_val: usize,
+    padded_num_var: usize,
+    pub_num_variables_val: usize,
+    pub_real_len_val: usize,
+    seed_0: String,
+    seed_1: String,
+    first_row: usize,
+    first_col: usize,
+}
+
+const META: [u8; 8] = *b"meta\0\0\0\0";
+const CK_W: [u8; 8] = *b"ck_w\0\0\0\0";
+const CK_INDEX: [u8; 8] = *b"ck_index";
+const INDEX: [u8; 8] = *b"index\0\0\0";
+const PUB_INDEX: [u8; 8] = *b"pubindex";
+
+impl<E: Pairing> Sectioned for Rep3ProverKey<E> {
+    fn write_sections(&self, writer: &mut SectionWriter) -> Result<()> {
+        let meta = Rep3KeyMeta {
+            party_id: self.party_id,
+            num_parties: self.num_parties,
+            num_variables: self.num_variables,
+            num_variables_val: self.num_variables_val,
+            padded_num_var: self.padded_num_var,
+            pub_num_variables_val: self.pub_num_variables_val,
+            pub_real_len_val: self.pub_real_len_val,
+            seed_0: self.seed_0.clone(),
+            seed_1: self.seed_1.clone(),
+            first_row: self.first_row,
+            first_col: self.first_col,
+        };
+        writer.add(META, &meta)?;
+        writer.add(CK_W, self.ck_w.get()?)?;
+        writer.add(CK_INDEX, self.ck_index.get()?)?;
+        writer.add(INDEX, self.index.get()?)?;
+        writer.add(PUB_INDEX, self.pub_index.get()?)
+    }
+
+    fn read_sections(reader: &Arc<SectionReader>) -> Result<Self> {
+        let meta: Rep3KeyMeta = reader.read(META)?;
+        Ok(Self {
+            party_id: meta.party_id,
+            num_parties: meta.num_parties,
+            num_variables: meta.num_variables,
+            num_variables_val: meta.num_variables_val,
+            padded_num_var: meta.padded_num_var,
+            pub_num_variables_val: meta.pub_num_variables_val,
+            pub_real_len_val: meta.pub_real_len_val,
+            seed_0: meta.seed_0,
+            seed_1: meta.seed_1,
+            first_row: meta.first_row,
+            first_col: meta.first_col,
+            ck_w: Lazy::section(reader, CK_W)?,
+            ck_index: Lazy::section(reader, CK_INDEX)?,
+            index: Lazy::section(reader, INDEX)?,
+            pub_index: Lazy::section(reader, PUB_INDEX)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    struct Pair {
+        small: u64,
+        large: Lazy<Vec<u64>>,
+    }
+
+    impl Artifact for Pair {
+        const FORMAT: [u8; 8] = *b"TestPair";
+        const VERSION: (u16, u16) = (0, 0);
+    }
+
+    impl Sectioned for Pair {
+        fn write_sections(&self, writer: &mut SectionWriter) -> Result<()> {
+            writer.add(*b"small\0\0\0", &self.small)?;
+            writer.add(*b"large\0\0\0", self.large.get()?)
+        }
+
+        fn read_sections(reader: &Arc<SectionReader>) -> Result<Self> {
+            Ok(Self {
+                small: reader.read(*b"small\0\0\0")?,
+                large: Lazy::section(reader, *b"large\0\0\0")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_lazy_sections() {
+        let dir = std::env::temp_dir().join(format!("co-spartan-section-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.bin");
+        let digests = Digests {
+            circuit: [7; 32],
+            verifier_key: [8; 32],
+        };
+        let large: Vec<u64> = (0..100).collect();
+        let pair = Pair {
+            small: 42,
+            large: Lazy::new(large.clone()),
+        };
+        write_sectioned(&pair, &digests, &path).unwrap();
+
+        let mut read: Pair = read_sectioned(&path, Some(&digests)).unwrap();
+        assert_eq!(read.small, 42);
+        assert!(!read.large.is_loaded());
+        assert_eq!(read.large.get().unwrap(), &large);
+        read.large.unload();
+        assert!(!read.large.is_loaded());
+        assert_eq!(read.large.get().unwrap(), &large);
+        let wrong = Digests {
+            verifier_key: [0; 32],
+            ..digests
+        };
+        assert!(read_sectioned::<Pair>(&path, Some(&wrong)).is_err());
+
+        // A corrupt section is only detected when loaded
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_SIZE + 8 + 10] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        let reader = Arc::new(SectionReader::open::<Pair>(&path, None).unwrap());
+        assert_eq!(reader.read::<u64>(*b"small\0\0\0").unwrap(), 42);
+        assert!(reader.read::<Vec<u64>>(*b"large\0\0\0").is_err());
+        let corrupt: Pair = read_sectioned(&path, None).unwrap();
+        assert!(corrupt.large.get().is_err());
+        assert!(!corrupt.large.is_loaded());
+
+        // A corrupt table is detected on open
+        let last = bytes.len() - 5;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(read_sectioned::<Pair>(&path, None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -6,7 +6,9 @@ use rand::RngCore;
 use spartan::{math::Math, IndexProverKey, IndexVerifierKey, Indexer, R1CS, SRS};
 
 use crate::{
+    section::Lazy,
     utils::{split_ck, split_poly, split_vec},
+    worker::{PublicIndex, WorkerIndex},
     Rep3ProverKey,
 };
 
@@ -43,19 +45,37 @@ pub fn setup_rep3<E: Pairing>(
             let seed_0 = format!("seed_{j}");
             let seed_1 = format!("seed_{next}");
 
+            let (ipk, pub_ipk) = (&ipk_vec[i], &pub_ipk_vec[cnt]);
             let pk = Rep3ProverKey {
                 party_id: i,
                 num_parties: log_num_public_workers,
-                ipk: ipk_vec[i].clone(),
-                pub_ipk: pub_ipk_vec[cnt].clone(),
-                row: pk.rows.clone(),
-                col: pk.cols.clone(),
-                val_a: pk.val_a.clone(),
-                val_b: pk.val_b.clone(),
-                val_c: pk.val_c.clone(),
                 num_variables: pk.num_variables_val,
+                num_variables_val: ipk.num_variables_val,
+                padded_num_var: ipk.padded_num_var,
+                pub_num_variables_val: pub_ipk.num_variables_val,
+                pub_real_len_val: pub_ipk.real_len_val,
                 seed_0,
                 seed_1,
+                first_row: pk.rows[0],
+                first_col: pk.cols[0],
+                ck_w: Lazy::new(ipk.ck_w.0.clone()),
+                ck_index: Lazy::new(pub_ipk.ck_index.clone()),
+                index: Lazy::new(WorkerIndex {
+                    rows_indexed: ipk.rows_indexed.clone(),
+                    cols_indexed: ipk.cols_indexed.clone(),
+                    val_a_indexed: ipk.val_a_indexed.clone(),
+                    val_b_indexed: ipk.val_b_indexed.clone(),
+                    val_c_indexed: ipk.val_c_indexed.clone(),
+                }),
+                pub_index: Lazy::new(PublicIndex {
+                    rows: pub_ipk.rows.clone(),
+                    cols: pub_ipk.cols.clone(),
+                    val_a: pub_ipk.val_a.clone(),
+                    val_b: pub_ipk.val_b.clone(),
+                    val_c: pub_ipk.val_c.clone(),
+                    freq_r: pub_ipk.freq_r.clone(),
+                    freq_c: pub_ipk.freq_c.clone(),
+                }),
             };
 
             if cnt < (1 << log_num_public_workers) - 1 {
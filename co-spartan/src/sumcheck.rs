@@ -6,7 +6,7 @@ use ark_linear_sumcheck::ml_sumcheck::{
 use ark_poly::DenseMultilinearExtension;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::rc::Rc;
-use spartan::utils::{partial_generate_eq, two_pow_n};
+use spartan::utils::two_pow_n;
 
 /// Prover State
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
@@ -70,47 +70,6 @@ pub fn poly_list_to_prover_state<F: Field>(
     res
 }
 
-pub fn append_sumcheck_polys<F: Field>(
-    h: (DenseMultilinearExtension<F>, DenseMultilinearExtension<F>),
-    phi: (DenseMultilinearExtension<F>, DenseMultilinearExtension<F>),
-    m: DenseMultilinearExtension<F>,
-    degree_diff: usize,
-    q_polys: &mut ListOfProductsOfPolynomials<F>,
-    z: &Vec<F>,
-    lambda: &F,
-    start: usize,
-    log_chunk_size: usize,
-) {
-    assert_eq!(h.0.num_vars, phi.0.num_vars);
-    assert_eq!(h.0.num_vars, m.num_vars);
-
-    let mut eta: F = *lambda;
-
-    let lagrange = partial_generate_eq(&z, start, log_chunk_size);
-
-    let q_0_h = vec![Rc::new(h.0.clone())];
-    let q_0_h_times_phi = vec![Rc::new(lagrange.clone()), Rc::new(h.0), Rc::new(phi.0)];
-    let q_0_m = vec![Rc::new(lagrange.clone()), Rc::new(m)];
-
-    q_polys.add_product(q_0_h, *lambda);
-    eta = eta * lambda;
-    q_polys.add_product(q_0_h_times_phi, eta);
-    q_polys.add_product(
-        q_0_m,
-        two_pow_n::<F>(degree_diff).inverse().unwrap() * eta.neg(),
-    );
-
-    let q_1_h = vec![Rc::new(h.1.clone())];
-    let q_1_h_times_phi = vec![Rc::new(lagrange.clone()), Rc::new(h.1), Rc::new(phi.1)];
-    let q_1_m = vec![Rc::new(lagrange)];
-
-    // eta = eta * lambda;
-    q_polys.add_product(q_1_h, lambda.neg());
-    eta = eta * lambda;
-    q_polys.add_product(q_1_h_times_phi, eta);
-    q_polys.add_product(q_1_m, eta.neg());
-}
-
 pub fn default_sumcheck_poly_list<F: Field>(
     lambda: &F,
     degree_diff: usize,
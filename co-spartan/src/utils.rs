@@ -2,9 +2,6 @@ use ark_ec::pairing::Pairing;
 use ark_ff::Field;
 use ark_poly::DenseMultilinearExtension;
 use ark_poly_commit::multilinear_pc::data_structures::CommitterKey;
-use ark_std::cfg_iter_mut;
-#[cfg(feature = "parallel")]
-use rayon::prelude::*;
 
 pub fn split_poly<F: Field>(
     polys: &DenseMultilinearExtension<F>,
@@ -82,30 +79,6 @@ pub fn split_ck<E: Pairing>(
     (res, res_2)
 }
 
-pub fn aggregate_poly<F: Field>(
-    eta: F,
-    polys: &[&DenseMultilinearExtension<F>],
-) -> DenseMultilinearExtension<F> {
-    let mut vars = 0;
-    for p in polys {
-        if p.num_vars > vars {
-            vars = p.num_vars;
-        }
-    }
-    let mut evals = vec![F::zero(); 1 << vars];
-    let mut x = F::one();
-    for p in polys {
-        cfg_iter_mut!(evals)
-            .zip(&p.evaluations)
-            .for_each(|(a, b)| *a += x * b);
-        x *= eta
-    }
-    DenseMultilinearExtension {
-        evaluations: evals,
-        num_vars: vars,
-    }
-}
-
 /// Pads the vector with 0 so that the number of elements in the vector is a
 /// power of 2
 pub fn pad_to_power_of_two<T: Default>(witness: &mut Vec<T>, log2n: usize) {
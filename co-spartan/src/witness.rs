@@ -1,52 +1,97 @@
 use ark_ec::pairing::Pairing;
+use ark_ff::Zero;
 use ark_poly::DenseMultilinearExtension;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use rand::RngCore;
+use spartan::R1CS;
 
 use crate::{
     mpc::rep3::{generate_poly_shares_rss, Rep3Poly},
     utils::{pad_to_power_of_two, split_vec},
 };
 
-pub type WitnessShare<E> = Rep3Poly<E>;
+/// A worker's share of its chunk of the committed witness `w`, together with
+/// the public values `io = (1, public_inputs)`. `z` interleaves the two, see
+/// [`spartan::R1CS::z_index`].
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WitnessShare<E: Pairing> {
+    pub w: Rep3Poly<E>,
+    pub io: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> WitnessShare<E> {
+    /// Share of `z` for the chunk starting at `start`, with `w` at the odd
+    /// positions and `io` at the even ones. The public values are added to
+    /// the additive share held by parties 0 and 2.
+    pub fn z(&self, start: usize) -> Rep3Poly<E> {
+        let interleave = |w: &DenseMultilinearExtension<E::ScalarField>, with_io: bool| {
+            let evaluations = w
+                .evaluations
+                .iter()
+                .enumerate()
+                .flat_map(|(i, value)| {
+                    let io = match self.io.get(start / 2 + i) {
+                        Some(io) if with_io => *io,
+                        _ => E::ScalarField::zero(),
+                    };
+                    [io, *value]
+                })
+                .collect();
+            DenseMultilinearExtension::from_evaluations_vec(w.num_vars + 1, evaluations)
+        };
+        Rep3Poly::new(
+            self.w.party_id,
+            interleave(&self.w.share_0, self.w.party_id == 0),
+            interleave(&self.w.share_1, self.w.party_id == 2),
+        )
+    }
+}
 
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct R1CSWitnessShare<E: Pairing> {
+    pub w: Rep3Poly<E>,
     pub z: Rep3Poly<E>,
     pub za: Rep3Poly<E>,
     pub zb: Rep3Poly<E>,
     pub zc: Rep3Poly<E>,
 }
 
+/// Secret share the R1CS witness `(1, public_inputs, w)` among the workers.
+/// Only `w` is shared, the constant one and the public values are sent in the
+/// clear, as the verifier supplies them.
 #[tracing::instrument(skip_all, name = "split_witness")]
 pub fn split_witness<E: Pairing>(
-    mut z: Vec<E::ScalarField>,
-    log_instance_size: usize,
+    witness: &[E::ScalarField],
+    r1cs: &R1CS<E::ScalarField>,
     log_num_workers_per_party: usize,
     rng: &mut impl RngCore,
 ) -> Vec<[(usize, WitnessShare<E>); 3]> {
+    let log_instance_size = r1cs.log2_instance_size();
+    let io = witness[..=r1cs.public_inputs].to_vec();
+    let mut z = r1cs.z(witness);
     pad_to_power_of_two(&mut z, log_instance_size);
+    let w = z.into_iter().skip(1).step_by(2).collect::<Vec<_>>();
 
-    let mut z_vec = split_vec(&z, log_num_workers_per_party);
+    let mut w_vec = split_vec(&w, log_num_workers_per_party);
 
-    let num_vars = log_instance_size - log_num_workers_per_party;
+    let num_vars = log_instance_size - 1 - log_num_workers_per_party;
 
     let mut witness_shares = Vec::new();
 
     for i in 0..1 << log_num_workers_per_party {
-        let z = DenseMultilinearExtension::from_evaluations_vec(
+        let w = DenseMultilinearExtension::from_evaluations_vec(
             num_vars,
-            std::mem::take(&mut z_vec[i]),
+            std::mem::take(&mut w_vec[i]),
         );
 
-        let z_shares = generate_poly_shares_rss(&z, rng);
+        let w_shares = generate_poly_shares_rss(&w, rng);
 
         let mut wit_vec = Vec::new();
         for j in 0..3 {
             let worker_id = i * 3 + j;
             let next = (j + 1) % 3;
-            let z = Rep3Poly::<E>::new(j, z_shares[j].clone(), z_shares[next].clone());
-            wit_vec.push((worker_id, z));
+            let w = Rep3Poly::<E>::new(j, w_shares[j].clone(), w_shares[next].clone());
+            wit_vec.push((worker_id, WitnessShare { w, io: io.clone() }));
         }
 
         witness_shares.push(wit_vec.try_into().unwrap());
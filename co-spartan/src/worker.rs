@@ -1,5 +1,6 @@
 use std::{cmp::max, iter, ops::Index};
 
+use anyhow::Result;
 use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
 use ark_ff::{Field, One, PrimeField, Zero};
 use ark_linear_sumcheck::{
@@ -15,15 +16,12 @@ use ark_poly_commit::multilinear_pc::{
     MultilinearPC,
 };
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{cfg_iter, marker::PhantomData, rc::Rc};
+use ark_std::{marker::PhantomData, rc::Rc};
 use rand::RngCore;
-#[cfg(feature = "parallel")]
-use rayon::prelude::*;
 use spartan::{
-    logup::LogLookupProof,
+    logup::{address_poly, append_sumcheck_polys, eq_query, eq_table, LogLookupProof},
     math::Math,
-    utils::{boost_degree, dense_scalar_prod, generate_eq, partial_generate_eq},
-    IndexProverKey,
+    utils::{aggregate_poly, boost_degree, dense_scalar_prod, generate_eq, partial_generate_eq},
 };
 
 use crate::{
@@ -33,28 +31,63 @@ use crate::{
         SSRandom,
     },
     network::NetworkWorker,
+    section::Lazy,
     sumcheck::{
-        append_sumcheck_polys, default_sumcheck_poly_list, obtain_distrbuted_sumcheck_prover_state,
+        default_sumcheck_poly_list, obtain_distrbuted_sumcheck_prover_state,
         poly_list_to_prover_state, DistrbutedSumcheckProverState,
     },
-    utils::aggregate_poly,
     witness::{R1CSWitnessShare, WitnessShare},
 };
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+/// A worker's prover key. The large parts are separate sections of the key
+/// file, see [`crate::section`], loaded when first used.
+#[derive(Debug)]
 pub struct Rep3ProverKey<E: Pairing> {
     pub party_id: usize,
     pub num_parties: usize,
-    pub ipk: IndexProverKey<E>,
-    pub pub_ipk: IndexProverKey<E>,
-    pub row: Vec<usize>,
-    pub col: Vec<usize>,
-    pub val_a: DenseMultilinearExtension<E::ScalarField>,
-    pub val_b: DenseMultilinearExtension<E::ScalarField>,
-    pub val_c: DenseMultilinearExtension<E::ScalarField>,
     pub num_variables: usize,
+    /// `num_variables_val` and `padded_num_var` of the worker's chunk of the
+    /// index.
+    pub num_variables_val: usize,
+    pub padded_num_var: usize,
+    /// `num_variables_val` and `real_len_val` of the worker's chunk of the
+    /// public index.
+    pub pub_num_variables_val: usize,
+    pub pub_real_len_val: usize,
     pub seed_0: String,
     pub seed_1: String,
+    /// Row and column of the first non-zero entry of the whole index, which
+    /// pads the address polynomials.
+    pub first_row: usize,
+    pub first_col: usize,
+    /// Committer key for the witness.
+    pub ck_w: Lazy<CommitterKey<E>>,
+    /// Committer key for the public index chunk.
+    pub ck_index: Lazy<CommitterKey<E>>,
+    pub index: Lazy<WorkerIndex<E>>,
+    pub pub_index: Lazy<PublicIndex<E>>,
+}
+
+/// The non-zero entries of `A`, `B` and `C` in the columns owned by a worker.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WorkerIndex<E: Pairing> {
+    pub rows_indexed: Vec<usize>,
+    pub cols_indexed: Vec<usize>,
+    pub val_a_indexed: DenseMultilinearExtension<E::ScalarField>,
+    pub val_b_indexed: DenseMultilinearExtension<E::ScalarField>,
+    pub val_c_indexed: DenseMultilinearExtension<E::ScalarField>,
+}
+
+/// A worker's chunk of the index polynomials, used for the lookup argument.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PublicIndex<E: Pairing> {
+    pub rows: Vec<usize>,
+    pub cols: Vec<usize>,
+    pub val_a: DenseMultilinearExtension<E::ScalarField>,
+    pub val_b: DenseMultilinearExtension<E::ScalarField>,
+    pub val_c: DenseMultilinearExtension<E::ScalarField>,
+    pub freq_r: DenseMultilinearExtension<E::ScalarField>,
+    pub freq_c: DenseMultilinearExtension<E::ScalarField>,
 }
 
 pub struct SpartanProverWorker<E: Pairing, N: NetworkWorker> {
@@ -72,8 +105,6 @@ struct ProverState<E: Pairing> {
     pub r_y: Vec<E::ScalarField>,
     pub eq_rx: Option<DenseMultilinearExtension<E::ScalarField>>,
     pub eq_ry: Option<DenseMultilinearExtension<E::ScalarField>>,
-    pub eq_tilde_rx: Option<DenseMultilinearExtension<E::ScalarField>>,
-    pub eq_tilde_ry: Option<DenseMultilinearExtension<E::ScalarField>>,
     pub eq_tilde_rx_chunk: Option<DenseMultilinearExtension<E::ScalarField>>,
     pub eq_tilde_ry_chunk: Option<DenseMultilinearExtension<E::ScalarField>>,
     pub val_m_poly_chunk: Option<DenseMultilinearExtension<E::ScalarField>>,
@@ -86,8 +117,6 @@ impl<E: Pairing> Default for ProverState<E> {
             r_y: vec![],
             eq_rx: None,
             eq_ry: None,
-            eq_tilde_rx: None,
-            eq_tilde_ry: None,
             eq_tilde_rx_chunk: None,
             eq_tilde_ry_chunk: None,
             val_m_poly_chunk: None,
@@ -115,56 +144,67 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
     #[tracing::instrument(skip_all, name = "SpartanProverWorker::prove")]
     pub fn prove<R: RngCore + FeedableRNG>(
         &mut self,
-        pk: &Rep3ProverKey<E>,
-        z: WitnessShare<E>,
+        pk: &mut Rep3ProverKey<E>,
+        witness: WitnessShare<E>,
         random_rng: &mut SSRandom<R>,
         active: bool,
         network: &mut N,
-    ) {
+    ) -> Result<()> {
         let mut state = ProverState::default();
 
-        let witness_share = self.zero_round(pk, &z);
+        let witness_share = self.zero_round(pk, witness)?;
 
-        self.first_round(&vec![&witness_share.z], &pk.ipk.ck_w.0, network);
+        self.first_round(&vec![&witness_share.w], pk.ck_w.get()?, network);
 
         self.second_round(pk, &witness_share, &mut state, random_rng, network);
 
-        self.third_round(pk, &witness_share, &mut state, random_rng, active, network);
+        self.third_round(pk, &witness_share, &mut state, random_rng, active, network)?;
+
+        // Only the public index is used from here on
+        pk.ck_w.unload();
+        pk.index.unload();
 
         if active {
-            self.fourth_round(pk, &mut state, network);
+            self.fourth_round(pk, &mut state, network)
         } else {
-            dummy_fourth_round(&pk.pub_ipk, network);
+            dummy_fourth_round(pk, network)
         }
     }
 
     // Compute Az, Bz, Cz
     #[tracing::instrument(skip_all, name = "SpartanProverWorker::zero_round")]
-    fn zero_round(&self, pk: &Rep3ProverKey<E>, z: &WitnessShare<E>) -> R1CSWitnessShare<E> {
-        let chunk_size = pk.ipk.num_variables_val.exp2();
+    fn zero_round(
+        &self,
+        pk: &Rep3ProverKey<E>,
+        witness: WitnessShare<E>,
+    ) -> Result<R1CSWitnessShare<E>> {
+        let index = index.get()?;
+        let z = &witness.z(self.start_eq);
+        let chunk_size = pk.num_variables_val.exp2();
         let mut za = vec![Rep3Share::<E>::zero().with_party(z.party_id); chunk_size];
         let mut zb = vec![Rep3Share::<E>::zero().with_party(z.party_id); chunk_size];
         let mut zc = vec![Rep3Share::<E>::zero().with_party(z.party_id); chunk_size];
 
         let c_start = pk.party_id * pk.num_variables.exp2() / pk.num_parties;
 
-        assert_eq!(pk.ipk.cols_indexed.len(), pk.ipk.rows_indexed.len());
+        assert_eq!(index.cols_indexed.len(), index.rows_indexed.len());
 
-        for i in 0..pk.ipk.cols_indexed.len() {
-            let row = pk.ipk.rows_indexed[i] - c_start;
-            let col = pk.ipk.cols_indexed[i] - c_start;
+        for i in 0..index.cols_indexed.len() {
+            let row = index.rows_indexed[i] - c_start;
+            let col = index.cols_indexed[i] - c_start;
             let z_share = z.get_share_by_idx(col);
-            za[row] += z_share * pk.ipk.val_a_indexed[i];
-            zb[row] += z_share * pk.ipk.val_b_indexed[i];
-            zc[row] += z_share * pk.ipk.val_c_indexed[i];
+            za[row] += z_share * index.val_a_indexed[i];
+            zb[row] += z_share * index.val_b_indexed[i];
+            zc[row] += z_share * index.val_c_indexed[i];
         }
 
-        R1CSWitnessShare {
+        Ok(R1CSWitnessShare {
+            w: witness.w,
             z: z.clone(),
-            za: Rep3Poly::from_rep3_evals(&za, pk.ipk.num_variables_val),
-            zb: Rep3Poly::from_rep3_evals(&zb, pk.ipk.num_variables_val),
-            zc: Rep3Poly::from_rep3_evals(&zc, pk.ipk.num_variables_val),
-        }
+            za: Rep3Poly::from_rep3_evals(&za, pk.num_variables_val),
+            zb: Rep3Poly::from_rep3_evals(&zb, pk.num_variables_val),
+            zc: Rep3Poly::from_rep3_evals(&zc, pk.num_variables_val),
+        })
     }
 
     #[tracing::instrument(skip_all, name = "SpartanProverWorker::first_round")]
@@ -183,7 +223,7 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
     ) {
         let v_msg: Vec<_> = network.receive_request();
 
-        let num_variables = pk.ipk.padded_num_var;
+        let num_variables = pk.padded_num_var;
 
         let eq_func = partial_generate_eq(&v_msg, self.start_eq, self.log_chunk_size);
 
@@ -220,27 +260,28 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
         random_rng: &mut SSRandom<R>,
         active: bool,
         network: &mut N,
-    ) {
+    ) -> Result<()> {
         let v_msg: Vec<_> = network.receive_request();
         let eq_rx = state.eq_rx.as_ref().unwrap();
+        let index = index.get()?;
 
-        let num_variables = pk.ipk.padded_num_var;
+        let num_variables = pk.padded_num_var;
         let instance_size = pk.num_variables.exp2();
-        let chunk_size = pk.ipk.num_variables_val.exp2();
+        let chunk_size = pk.num_variables_val.exp2();
         let c_start = pk.party_id * instance_size / pk.num_parties;
 
         let mut a_rx = vec![E::ScalarField::zero(); chunk_size];
         let mut b_rx = vec![E::ScalarField::zero(); chunk_size];
         let mut c_rx = vec![E::ScalarField::zero(); chunk_size];
 
-        for i in 0..pk.ipk.cols_indexed.len() {
-            let col = pk.ipk.cols_indexed[i] - c_start; // local offset 0..range_len-1
-            let row = pk.ipk.rows_indexed[i];
+        for i in 0..index.cols_indexed.len() {
+            let col = index.cols_indexed[i] - c_start; // local offset 0..range_len-1
+            let row = index.rows_indexed[i];
             let eq = eq_rx.index(row);
 
-            a_rx[col] += pk.ipk.val_a_indexed[i] * eq;
-            b_rx[col] += pk.ipk.val_b_indexed[i] * eq;
-            c_rx[col] += pk.ipk.val_c_indexed[i] * eq;
+            a_rx[col] += index.val_a_indexed[i] * eq;
+            b_rx[col] += index.val_b_indexed[i] * eq;
+            c_rx[col] += index.val_c_indexed[i] * eq;
         }
 
         let final_point = rep3_second_sumcheck_worker(
@@ -253,10 +294,11 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
             network,
         );
 
+        // `w` is the odd half of `z`, evaluated at `r_y[1..]`
         rep3_eval_poly_worker(
-            vec![&witness_share.z],
-            &final_point,
-            pk.num_variables,
+            vec![&witness_share.w],
+            &final_point[1..],
+            pk.num_variables - 1,
             network,
         );
         state.r_y = final_point.to_vec();
@@ -265,31 +307,37 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
 
         let chunk_size = self.pub_log_chunk_size.exp2();
         if active {
+            let pub_index = pub_index.get()?;
+            let ck_index = pk.ck_index.get()?;
             let mut eq_tilde_rx_chunk_evals = vec![E::ScalarField::zero(); chunk_size];
             let mut eq_tilde_ry_chunk_evals = vec![E::ScalarField::zero(); chunk_size];
 
             let mut val_a = E::ScalarField::zero();
             let mut val_b = E::ScalarField::zero();
             let mut val_c = E::ScalarField::zero();
+            // Padding entries take the first entry of the whole index
+            let (first_row, first_col) = (pk.first_row, pk.first_col);
 
-            for (i, ((((v_a, v_b), v_c), row), col)) in pk
-                .pub_ipk
+            for (i, ((((v_a, v_b), v_c), row), col)) in pub_index
                 .val_a
                 .evaluations
                 .iter()
-                .zip(pk.pub_ipk.val_b.evaluations.iter())
-                .zip(pk.pub_ipk.val_c.evaluations.iter())
-                .zip(pk.pub_ipk.rows.iter())
-                .zip(pk.pub_ipk.cols.iter())
+                .zip(pub_index.val_b.evaluations.iter())
+                .zip(pub_index.val_c.evaluations.iter())
+                .zip(pub_index.rows.iter())
+                .zip(pub_index.cols.iter())
                 .enumerate()
             {
-                if i < pk.pub_ipk.real_len_val {
+                if i < pk.pub_real_len_val {
                     val_a += *v_a * eq_rx.index(*row) * eq_ry.index(*col);
                     val_b += *v_b * eq_rx.index(*row) * eq_ry.index(*col);
                     val_c += *v_c * eq_rx.index(*row) * eq_ry.index(*col);
 
                     eq_tilde_rx_chunk_evals[i] = *eq_rx.index(*row);
                     eq_tilde_ry_chunk_evals[i] = *eq_ry.index(*col);
+                } else {
+                    eq_tilde_rx_chunk_evals[i] = *eq_rx.index(first_row);
+                    eq_tilde_ry_chunk_evals[i] = *eq_ry.index(first_col);
                 }
             }
 
@@ -307,16 +355,12 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
             let response = (val_a, val_b, val_c);
             network.send_response(response);
 
-            let val_m_poly_chunk = dense_scalar_prod(&v_msg[0], &pk.pub_ipk.val_a)
-                + dense_scalar_prod(&v_msg[1], &pk.pub_ipk.val_b)
-                + dense_scalar_prod(&v_msg[2], &pk.pub_ipk.val_c);
+            let val_m_poly_chunk = dense_scalar_prod(&v_msg[0], &pub_index.val_a)
+                + dense_scalar_prod(&v_msg[1], &pub_index.val_b)
+                + dense_scalar_prod(&v_msg[2], &pub_index.val_c);
             state.val_m_poly_chunk = Some(val_m_poly_chunk);
 
-            poly_commit_worker(
-                [eq_tilde_rx_chunk, eq_tilde_ry_chunk],
-                &pk.pub_ipk.ck_index,
-                network,
-            );
+            poly_commit_worker([eq_tilde_rx_chunk, eq_tilde_ry_chunk], ck_index, network);
         } else {
             let response = (
                 E::ScalarField::zero(),
@@ -328,7 +372,7 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
             let default_response = vec![
                 Commitment::<E> {
                     nv: 0,
-                    g_product: pk.pub_ipk.ck_index.g
+                    g_product: pk.ck_index.get()?.g
                 };
                 2
             ];
@@ -336,104 +380,52 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
         }
 
         distributed_batch_open_poly_worker(
-            iter::once(&witness_share.z).map(|p| &p.share_0),
-            &pk.ipk.ck_w.0,
-            &state.r_y,
+            iter::once(&witness_share.w).map(|p| &p.share_0),
+            pk.ck_w.get()?,
+            &state.r_y[1..],
             E::ScalarField::one(),
-            1,
-            pk.num_variables,
+            pk.num_variables - 1,
             network.log_num_workers_per_party(),
             network,
         );
-
-        let mut eq_tilde_rx_evals = vec![E::ScalarField::zero(); pk.num_variables.exp2()];
-        let mut eq_tilde_ry_evals = vec![E::ScalarField::zero(); pk.num_variables.exp2()];
-        for i in 0..pk.num_variables.exp2() {
-            if pk.row[i] != usize::MAX {
-                eq_tilde_rx_evals[i] = *eq_rx.index(pk.row[i]);
-            }
-            if pk.col[i] != usize::MAX {
-                eq_tilde_ry_evals[i] = *eq_ry.index(pk.col[i]);
-            }
-        }
-
-        state.eq_tilde_rx = Some(DenseMultilinearExtension::from_evaluations_vec(
-            pk.num_variables,
-            eq_tilde_rx_evals,
-        ));
-        state.eq_tilde_ry = Some(DenseMultilinearExtension::from_evaluations_vec(
-            pk.num_variables,
-            eq_tilde_ry_evals,
-        ));
+        Ok(())
     }
 
     #[tracing::instrument(skip_all, name = "SpartanProverWorker::fourth_round")]
-    fn fourth_round(&self, pk: &Rep3ProverKey<E>, state: &mut ProverState<E>, network: &mut N) {
+    fn fourth_round(
+        &self,
+        pk: &Rep3ProverKey<E>,
+        state: &mut ProverState<E>,
+        network: &mut N,
+    ) -> Result<()> {
+        let pub_index = pub_index.get()?;
+        let ck_index = pk.ck_index.get()?;
         let start_eq = self.pub_start_eq;
         let log_chunk_size = self.pub_log_chunk_size;
-        let eq_tilde_rx = state.eq_tilde_rx.as_ref().unwrap();
-        let eq_tilde_ry = state.eq_tilde_ry.as_ref().unwrap();
+        let eq_rx = state.eq_rx.as_ref().unwrap();
+        let eq_ry = state.eq_ry.as_ref().unwrap();
         let eq_tilde_rx_chunk = state.eq_tilde_rx_chunk.as_ref().unwrap();
         let eq_tilde_ry_chunk = state.eq_tilde_ry_chunk.as_ref().unwrap();
         let val_m_poly_chunk = state.val_m_poly_chunk.as_ref().unwrap();
 
         let v_msg = network.receive_request();
 
-        let q_num_vars = pk.pub_ipk.num_variables_val;
-
-        let mut q_row: DenseMultilinearExtension<<E as Pairing>::ScalarField> =
-            DenseMultilinearExtension::from_evaluations_vec(
-                q_num_vars,
-                // `hash_tuple` method reindexes eq_tilde_rx based ipk.row
-                // which we did in third round is eq_tilde_rx =? reindex(eq_rx)
-                hash_tuple::<E::ScalarField>(
-                    &pk.pub_ipk.rows[..pk.pub_ipk.real_len_val],
-                    eq_tilde_rx,
-                    &v_msg,
-                ),
-            );
-        let first_row = *pk.row.iter().filter(|r| **r != usize::MAX).next().unwrap();
-        let full_q_row_first =
-            E::ScalarField::from(first_row as u64) + v_msg * eq_tilde_rx[first_row];
+        let q_num_vars = pk.pub_num_variables_val;
 
-        for i in pk.pub_ipk.real_len_val..q_num_vars.exp2() {
-            q_row.evaluations[i] = full_q_row_first;
-        }
-
-        let mut q_col: DenseMultilinearExtension<<E as Pairing>::ScalarField> =
-            DenseMultilinearExtension::from_evaluations_vec(
-                q_num_vars,
-                hash_tuple::<E::ScalarField>(
-                    &pk.pub_ipk.cols[..pk.pub_ipk.num_variables_val.exp2()],
-                    eq_tilde_ry,
-                    &v_msg,
-                ),
-            );
+        // Queries `row + v · eq_tilde_rx` and `col + v · eq_tilde_ry` over the
+        // worker's chunk of the non-zero entries, tables over the same chunk
+        // of the addresses
+        let row = address_poly(&pub_index.rows, pk.first_row, q_num_vars);
+        let col = address_poly(&pub_index.cols, pk.first_col, q_num_vars);
+        let q_row = eq_query(&row, eq_tilde_rx_chunk, &v_msg);
+        let q_col = eq_query(&col, eq_tilde_ry_chunk, &v_msg);
 
-        // TODO: we just need first_col value in pk -- can avoid storing entire col, eq_tilde_ry vector?
-        let first_col = *pk.col.iter().filter(|r| **r != usize::MAX).next().unwrap();
-        let full_q_col_first =
-            E::ScalarField::from(first_col as u64) + v_msg * eq_tilde_ry[first_col];
-
-        for i in pk.pub_ipk.real_len_val..q_num_vars.exp2() {
-            q_col.evaluations[i] = full_q_col_first;
-        }
-
-        let domain = (start_eq..start_eq + (1 << log_chunk_size)).collect::<Vec<_>>();
-        let t_row = DenseMultilinearExtension::from_evaluations_vec(
-            pk.pub_ipk.num_variables_val,
-            hash_tuple::<E::ScalarField>(&domain, eq_tilde_rx, &v_msg),
-        );
-
-        assert!(eq_tilde_rx_chunk.num_vars == pk.pub_ipk.num_variables_val);
-        let t_col: DenseMultilinearExtension<<E as Pairing>::ScalarField> =
-            DenseMultilinearExtension::from_evaluations_vec(
-                pk.pub_ipk.num_variables_val,
-                hash_tuple::<E::ScalarField>(&domain, eq_tilde_ry, &v_msg),
-            );
+        assert!(eq_tilde_rx_chunk.num_vars == pk.pub_num_variables_val);
+        let t_row = eq_table(eq_rx, &v_msg, start_eq, log_chunk_size);
+        let t_col = eq_table(eq_ry, &v_msg, start_eq, log_chunk_size);
 
         let mut q_polys =
-            ListOfProductsOfPolynomials::new(max(q_num_vars, pk.pub_ipk.num_variables_val));
+            ListOfProductsOfPolynomials::new(max(q_num_vars, pk.pub_num_variables_val));
 
         let prod = vec![
             Rc::new(eq_tilde_rx_chunk.clone()),
@@ -444,21 +436,11 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
 
         let (x_r, x_c) = network.receive_request();
 
-        let lookup_pf_row = LogLookupProof::prove(
-            &q_row,
-            &t_row,
-            &pk.pub_ipk.freq_r,
-            &pk.pub_ipk.ck_index,
-            &x_r,
-        );
+        let lookup_pf_row =
+            LogLookupProof::prove(&q_row, &t_row, &pub_index.freq_r, ck_index, &x_r);
 
-        let lookup_pf_col = LogLookupProof::prove(
-            &q_col,
-            &t_col,
-            &pk.pub_ipk.freq_c,
-            &pk.pub_ipk.ck_index,
-            &x_c,
-        );
+        let lookup_pf_col =
+            LogLookupProof::prove(&q_col, &t_col, &pub_index.freq_c, ck_index, &x_c);
 
         let responses = vec![
             lookup_pf_row.1[0].clone(),
@@ -474,7 +456,7 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
         append_sumcheck_polys(
             (lookup_pf_row.0[0].clone(), lookup_pf_row.0[1].clone()),
             (lookup_pf_row.2[0].clone(), lookup_pf_row.2[1].clone()),
-            boost_degree(&pk.pub_ipk.freq_r.clone(), q_row.num_vars),
+            boost_degree(&pub_index.freq_r.clone(), q_row.num_vars),
             q_row.num_vars - t_row.num_vars,
             &mut q_polys,
             &z,
@@ -488,7 +470,7 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
         append_sumcheck_polys(
             (lookup_pf_col.0[0].clone(), lookup_pf_col.0[1].clone()),
             (lookup_pf_col.2[0].clone(), lookup_pf_col.2[1].clone()),
-            pk.pub_ipk.freq_c.clone(),
+            pub_index.freq_c.clone(),
             q_col.num_vars - t_col.num_vars,
             &mut q_polys,
             &z,
@@ -509,24 +491,22 @@ impl<E: Pairing, N: NetworkWorker> SpartanProverWorker<E, N> {
                 &lookup_pf_col.0[1],
                 &eq_tilde_rx_chunk,
                 &eq_tilde_ry_chunk,
-                &pk.pub_ipk.val_a,
-                &pk.pub_ipk.val_b,
-                &pk.pub_ipk.val_c,
-                &pk.pub_ipk.freq_r,
-                &q_row,
-                &t_row,
-                &pk.pub_ipk.freq_c,
-                &q_col,
-                &t_col,
+                &pub_index.val_a,
+                &pub_index.val_b,
+                &pub_index.val_c,
+                &row,
+                &col,
+                &pub_index.freq_r,
+                &pub_index.freq_c,
             ],
-            &pk.pub_ipk.ck_index,
+            ck_index,
             &final_point,
             eta,
-            9,
             pk.num_variables,
             network.log_num_pub_workers(),
             network,
         );
+        Ok(())
     }
 }
 
@@ -681,14 +661,13 @@ pub fn distributed_batch_open_poly_worker<'a, E: Pairing, N: NetworkWorker>(
     ck: &CommitterKey<E>,
     point: &[E::ScalarField],
     eta: E::ScalarField,
-    num_comms: usize,
     num_var: usize,
     log_num_workers: usize,
     network: &mut N,
 ) {
     let polys = polys.into_iter().collect::<Vec<_>>();
 
-    let agg_poly = aggregate_poly(eta, &polys[0..num_comms]);
+    let agg_poly = aggregate_poly(eta, &polys);
 
     let (pf, r) = distributed_open(&ck, &agg_poly, &point[0..num_var - log_num_workers]);
     let mut evals = Vec::new();
@@ -767,18 +746,6 @@ impl<E: Pairing> PartialProof<E> {
     }
 }
 
-fn hash_tuple<F: Field>(v: &[usize], eq: &DenseMultilinearExtension<F>, v_msg: &F) -> Vec<F> {
-    let mut result = cfg_iter!(v)
-        .filter(|v_i| **v_i != usize::MAX)
-        .map(|v_i| F::from(*v_i as u64) + *v_msg * eq[*v_i])
-        .collect::<Vec<_>>();
-
-    for _ in 0..result.len().next_power_of_two() - result.len() {
-        result.push(result[0]);
-    }
-    result
-}
-
 fn dummy_sumcheck_worker<F: Field, N: NetworkWorker>(
     default_last_sumcheck_state: DistrbutedSumcheckProverState<F>,
     num_variables: usize,
@@ -817,7 +784,11 @@ fn dummy_batch_open_poly_worker<'a, E: Pairing, N: NetworkWorker>(
     network.send_response(default_response);
 }
 
-fn dummy_fourth_round<'a, E: Pairing, N: NetworkWorker>(ipk: &IndexProverKey<E>, network: &mut N) {
+fn dummy_fourth_round<'a, E: Pairing, N: NetworkWorker>(
+    pk: &Rep3ProverKey<E>,
+    network: &mut N,
+) -> Result<()> {
+    let g = pk.ck_index.get()?.g;
     let _v_msg: E::ScalarField = network.receive_request();
 
     let (_x_r, _x_c): (E::ScalarField, E::ScalarField) = network.receive_request();
@@ -825,7 +796,7 @@ fn dummy_fourth_round<'a, E: Pairing, N: NetworkWorker>(ipk: &IndexProverKey<E>,
     let default_response = vec![
         Commitment::<E> {
             nv: 0,
-            g_product: ipk.ck_index.g,
+            g_product: g,
         };
         4
     ];
@@ -855,12 +826,13 @@ fn dummy_fourth_round<'a, E: Pairing, N: NetworkWorker>(ipk: &IndexProverKey<E>,
 
     dummy_sumcheck_worker(
         default_last_sumcheck_state,
-        ipk.real_len_val.log_2(),
+        pk.pub_real_len_val.log_2(),
         3,
         network,
     );
 
     let _eta: E::ScalarField = network.receive_request();
 
-    dummy_batch_open_poly_worker::<E, N>(ipk.real_len_val.log_2(), 15, ipk.ck_index.g, network);
+    dummy_batch_open_poly_worker::<E, N>(pk.pub_real_len_val.log_2(), 13, g, network);
+    Ok(())
 }
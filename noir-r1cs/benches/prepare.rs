@@ -0,0 +1,52 @@
+//! Benchmarks for compiling Noir programs, as done by `noir-r1cs prepare`.
+//!
+//! Run with `cargo bench -p noir-r1cs --bench prepare`.
+
+use std::{fs::File, path::PathBuf};
+
+use divan::{black_box, Bencher};
+use noir_r1cs::{FieldElement, Interner, NoirProofScheme};
+use noirc_artifacts::program::ProgramArtifact;
+
+const PASSPORT_EXAMPLES: &[&str] = &[
+    "compare_age",
+    "compare_birthdate",
+    "compare_citizenship",
+    "compare_expiry",
+    "data_check_expiry",
+    "data_check_integrity",
+    "disclose_bytes",
+    "disclose_flags",
+    "exclusion_check_country",
+    "inclusion_check_country",
+    "outer",
+];
+
+fn main() {
+    divan::main();
+}
+
+fn read_program(name: &str) -> ProgramArtifact {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("noir-examples/noir-passport-examples")
+        .join(format!("{name}.json"));
+    let file = File::open(&path).expect("Passport example not found");
+    serde_json::from_reader(file).expect("Invalid Noir program")
+}
+
+#[divan::bench(args = PASSPORT_EXAMPLES, sample_count = 10)]
+fn prepare(bencher: Bencher, name: &str) {
+    let program = read_program(name);
+    bencher.bench(|| NoirProofScheme::from_program(black_box(&program)).unwrap());
+}
+
+#[divan::bench(args = [1_000, 10_000, 100_000])]
+fn intern(bencher: Bencher, size: u64) {
+    bencher.bench(|| {
+        let mut interner = Interner::default();
+        for i in 0..size {
+            black_box(interner.intern(FieldElement::from(i % (size / 2))));
+        }
+        interner
+    });
+}
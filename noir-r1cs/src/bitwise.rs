@@ -0,0 +1,131 @@
+//! Lowering of bitwise operations to R1CS through bit decomposition.
+
+use ark_ff::Field;
+use ark_std::{One, Zero};
+
+use crate::{FieldElement, Hint, LinearCombination, R1CSBuilder};
+
+/// Bitwise binary operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitwiseOp {
+    And,
+    Xor,
+}
+
+/// Number of R1CS constraints for a bitwise operation on two `num_bits`
+/// witnesses. Each operand is decomposed into boolean bits (`num_bits + 1`
+/// constraints), each output bit takes one constraint and the output is
+/// recomposed with one more.
+pub fn bitwise_cost(num_bits: u32) -> usize {
+    3 * num_bits as usize + 3
+}
+
+/// Apply a bitwise operation to two vectors of boolean linear combinations.
+///
+/// Operand bits are assumed to be constrained boolean already. Output bits are
+/// boolean by construction. When either bit is a constant the output is
+/// linear and no constraint is added.
+pub(crate) fn add_bitwise(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    op: BitwiseOp,
+    lhs: &[LinearCombination],
+    rhs: &[LinearCombination],
+) -> Vec<LinearCombination> {
+    assert_eq!(lhs.len(), rhs.len(), "Operand widths do not match");
+    lhs.iter()
+        .zip(rhs.iter())
+        .map(|(a, b)| add_bit_op(r1cs, witness_one, op, a, b))
+        .collect()
+}
+
+fn add_bit_op(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    op: BitwiseOp,
+    a: &LinearCombination,
+    b: &LinearCombination,
+) -> LinearCombination {
+    let product = add_product(r1cs, witness_one, a, b);
+    match op {
+        // a & b = a * b
+        BitwiseOp::And => product,
+        // a ^ b = a + b - 2 * a * b
+        BitwiseOp::Xor => {
+            let mut output = a.clone();
+            output.extend(b.iter().copied());
+            output.extend(scale(&product, -FieldElement::from(2u64)));
+            output
+        }
+    }
+}
+
+/// Product of two linear combinations. When either is a constant the product
+/// is linear and no constraint is added.
+pub(crate) fn add_product(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    a: &[(FieldElement, usize)],
+    b: &[(FieldElement, usize)],
+) -> LinearCombination {
+    if let Some(c) = as_constant(a, witness_one) {
+        return scale(b, c);
+    }
+    if let Some(c) = as_constant(b, witness_one) {
+        return scale(a, c);
+    }
+    let one = FieldElement::one();
+    let product = r1cs.new_witness();
+    r1cs.add_constraint(a, b, &[(one, product)]);
+    vec![(one, product)]
+}
+
+/// Inverse of `value`, or zero if `value` is zero. Only the hint enforces
+/// this, so callers must constrain the result.
+pub(crate) fn add_inverse(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    value: &LinearCombination,
+) -> LinearCombination {
+    if let Some(value) = as_constant(value, witness_one) {
+        return vec![(value.inverse().unwrap_or_default(), witness_one)];
+    }
+    let output = r1cs.new_witness();
+    r1cs.add_hint(Hint::Inverse {
+        value: value.clone(),
+        output,
+    });
+    vec![(FieldElement::one(), output)]
+}
+
+/// Boolean that is one iff `value` is zero.
+pub(crate) fn add_is_zero(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    value: &LinearCombination,
+) -> LinearCombination {
+    if let Some(value) = as_constant(value, witness_one) {
+        return vec![(FieldElement::from(value.is_zero()), witness_one)];
+    }
+    let one = FieldElement::one();
+    let inverse = add_inverse(r1cs, witness_one, value);
+    let flag = r1cs.new_witness();
+    // value · inverse = 1 - flag and value · flag = 0
+    r1cs.add_constraint(value, &inverse, &[(one, witness_one), (-one, flag)]);
+    r1cs.add_constraint(value, &[(one, flag)], &[]);
+    vec![(one, flag)]
+}
+
+/// The value of a linear combination if it only depends on the constant one.
+pub(crate) fn as_constant(
+    lc: &[(FieldElement, usize)],
+    witness_one: usize,
+) -> Option<FieldElement> {
+    lc.iter().try_fold(FieldElement::zero(), |acc, &(c, w)| {
+        (w == witness_one).then(|| acc + c)
+    })
+}
+
+pub(crate) fn scale(lc: &[(FieldElement, usize)], factor: FieldElement) -> LinearCombination {
+    lc.iter().map(|&(c, w)| (c * factor, w)).collect()
+}
@@ -0,0 +1,258 @@
+//! Readers for the binary `.r1cs` and `.wtns` files produced by circom.
+//!
+//! See <https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md>.
+//! Both formats start with a magic, a version and a list of typed sections.
+//! Field elements are little endian in canonical (non-Montgomery) form.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use tracing::{info, instrument};
+
+use crate::{FieldElement, R1CSBuilder, R1CS};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+const R1CS_HEADER: u32 = 1;
+const R1CS_CONSTRAINTS: u32 = 2;
+const WTNS_HEADER: u32 = 1;
+const WTNS_VALUES: u32 = 2;
+
+/// Size in bytes of a serialized field element.
+const FIELD_SIZE: usize = 32;
+
+/// Read a circom `.r1cs` file.
+///
+/// Circom orders the wires as the constant one, public outputs, public
+/// inputs, private inputs and internal wires, so the public values are
+/// already where [`R1CS::public_inputs`] expects them.
+#[instrument(fields(size = path.metadata().map(|m| m.len()).ok()))]
+pub fn read_circom_r1cs(path: &Path) -> Result<R1CS> {
+    let bytes = fs::read(path).context("while reading circom R1CS file")?;
+    parse_r1cs(&bytes)
+}
+
+/// Read a circom `.wtns` witness file.
+#[instrument(fields(size = path.metadata().map(|m| m.len()).ok()))]
+pub fn read_circom_witness(path: &Path) -> Result<Vec<FieldElement>> {
+    let bytes = fs::read(path).context("while reading circom witness file")?;
+    parse_wtns(&bytes)
+}
+
+fn parse_r1cs(bytes: &[u8]) -> Result<R1CS> {
+    let sections = parse_sections(bytes, R1CS_MAGIC)?;
+
+    // Header
+    let mut header = section(&sections, R1CS_HEADER)?;
+    read_prime(&mut header)?;
+    let wires = read_u32(&mut header)? as usize;
+    let public_outputs = read_u32(&mut header)? as usize;
+    let public_inputs = read_u32(&mut header)? as usize;
+    let private_inputs = read_u32(&mut header)? as usize;
+    let _labels = read_u64(&mut header)?;
+    let constraints = read_u32(&mut header)? as usize;
+    ensure!(
+        1 + public_outputs + public_inputs + private_inputs <= wires,
+        "Inputs and outputs exceed the {wires} wires"
+    );
+    info!(
+        wires,
+        public_outputs, public_inputs, private_inputs, constraints, "Read circom R1CS header"
+    );
+
+    let mut r1cs = R1CSBuilder::new();
+    r1cs.public_inputs = public_outputs + public_inputs;
+    for _ in 0..wires {
+        r1cs.new_witness();
+    }
+
+    // Constraints
+    let mut body = section(&sections, R1CS_CONSTRAINTS)?;
+    for row in 0..constraints {
+        let mut read = || -> Result<_> {
+            let a = read_lc(&mut body, wires)?;
+            let b = read_lc(&mut body, wires)?;
+            let c = read_lc(&mut body, wires)?;
+            Ok((a, b, c))
+        };
+        let (a, b, c) = read().with_context(|| format!("while reading constraint {row}"))?;
+        r1cs.add_constraint(&a, &b, &c);
+    }
+    ensure!(body.is_empty(), "Trailing bytes after constraints");
+    Ok(r1cs.build())
+}
+
+/// Read a linear combination as `(coefficient, wire)` terms.
+fn read_lc(bytes: &mut &[u8], wires: usize) -> Result<Vec<(FieldElement, usize)>> {
+    let factors = read_u32(bytes)?;
+    (0..factors)
+        .map(|_| {
+            let wire = read_u32(bytes)? as usize;
+            ensure!(wire < wires, "Wire {wire} out of bounds");
+            Ok((read_field(bytes)?, wire))
+        })
+        .collect()
+}
+
+fn parse_wtns(bytes: &[u8]) -> Result<Vec<FieldElement>> {
+    let sections = parse_sections(bytes, WTNS_MAGIC)?;
+
+    let mut header = section(&sections, WTNS_HEADER)?;
+    read_prime(&mut header)?;
+    let size = read_u32(&mut header)? as usize;
+
+    let mut body = section(&sections, WTNS_VALUES)?;
+    let witness = (0..size)
+        .map(|_| read_field(&mut body))
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(body.is_empty(), "Trailing bytes after witness values");
+    Ok(witness)
+}
+
+/// Check the magic and split the file into sections by type.
+fn parse_sections<'a>(mut bytes: &'a [u8], magic: &[u8; 4]) -> Result<HashMap<u32, &'a [u8]>> {
+    ensure!(
+        take(&mut bytes, 4)? == magic,
+        "Not a circom {} file",
+        String::from_utf8_lossy(magic)
+    );
+    let version = read_u32(&mut bytes)?;
+    ensure!(
+        matches!(version, 1 | 2),
+        "Unsupported circom file version {version}"
+    );
+    let count = read_u32(&mut bytes)?;
+    let mut sections = HashMap::new();
+    for _ in 0..count {
+        let kind = read_u32(&mut bytes)?;
+        let size = read_u64(&mut bytes)?;
+        let content = take(&mut bytes, size.try_into()?)?;
+        if sections.insert(kind, content).is_some() {
+            bail!("Duplicate section {kind}");
+        }
+    }
+    Ok(sections)
+}
+
+fn section<'a>(sections: &HashMap<u32, &'a [u8]>, kind: u32) -> Result<&'a [u8]> {
+    sections
+        .get(&kind)
+        .copied()
+        .with_context(|| format!("Missing section {kind}"))
+}
+
+/// Read the field size and modulus, which must match [`FieldElement`].
+fn read_prime(bytes: &mut &[u8]) -> Result<()> {
+    let size = read_u32(bytes)? as usize;
+    ensure!(size == FIELD_SIZE, "Unsupported field size {size}");
+    let prime = BigUint::from_bytes_le(take(bytes, size)?);
+    ensure!(
+        prime == BigUint::from(FieldElement::MODULUS),
+        "Field modulus {prime} is not BN254 scalar field"
+    );
+    Ok(())
+}
+
+fn read_field(bytes: &mut &[u8]) -> Result<FieldElement> {
+    let value = BigUint::from_bytes_le(take(bytes, FIELD_SIZE)?);
+    ensure!(
+        value < BigUint::from(FieldElement::MODULUS),
+        "Field element not reduced"
+    );
+    Ok(FieldElement::from(value))
+}
+
+fn read_u32(bytes: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(bytes, 4)?.try_into()?))
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(take(bytes, 8)?.try_into()?))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], size: usize) -> Result<&'a [u8]> {
+    ensure!(bytes.len() >= size, "Unexpected end of file");
+    let (head, tail) = bytes.split_at(size);
+    *bytes = tail;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::BigInteger;
+
+    use super::*;
+
+    fn file(magic: &[u8], sections: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bytes.extend(1_u32.to_le_bytes());
+        bytes.extend((sections.len() as u32).to_le_bytes());
+        for (kind, content) in sections {
+            bytes.extend(kind.to_le_bytes());
+            bytes.extend((content.len() as u64).to_le_bytes());
+            bytes.extend(content);
+        }
+        bytes
+    }
+
+    fn field(value: FieldElement) -> Vec<u8> {
+        value.into_bigint().to_bytes_le()
+    }
+
+    fn prime() -> Vec<u8> {
+        let mut bytes = (FIELD_SIZE as u32).to_le_bytes().to_vec();
+        bytes.extend(FieldElement::MODULUS.to_bytes_le());
+        bytes
+    }
+
+    #[test]
+    fn test_square() {
+        // Wires: one, y (public output), x (private input). Constraint x · x = y
+        let one = FieldElement::from(1);
+        let mut header = prime();
+        for value in [3_u32, 1, 0, 1] {
+            header.extend(value.to_le_bytes());
+        }
+        header.extend(0_u64.to_le_bytes());
+        header.extend(1_u32.to_le_bytes());
+        let mut constraints = Vec::new();
+        for wire in [2_u32, 2, 1] {
+            constraints.extend(1_u32.to_le_bytes());
+            constraints.extend(wire.to_le_bytes());
+            constraints.extend(field(one));
+        }
+        // Sections need not be in order
+        let r1cs = parse_r1cs(&file(
+            R1CS_MAGIC,
+            &[(R1CS_CONSTRAINTS, constraints), (R1CS_HEADER, header)],
+        ))
+        .unwrap();
+        assert_eq!(
+            (r1cs.public_inputs, r1cs.witnesses, r1cs.constraints),
+            (1, 3, 1)
+        );
+
+        let mut header = prime();
+        header.extend(3_u32.to_le_bytes());
+        let values = [1, 9, 3].map(FieldElement::from);
+        let witness = parse_wtns(&file(
+            WTNS_MAGIC,
+            &[
+                (WTNS_HEADER, header),
+                (
+                    WTNS_VALUES,
+                    values.iter().copied().flat_map(field).collect(),
+                ),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(witness, values);
+        r1cs.verify_witness(&witness).unwrap();
+        assert_eq!(r1cs.public_values(&witness), &values[1..2]);
+
+        assert!(parse_wtns(&file(R1CS_MAGIC, &[])).is_err());
+    }
+}
@@ -1,529 +1,455 @@
-//! This binary analyzes ACIR circuit statistics and provides insights into
-//! constraints, witness size, and various operations used in the circuit.
+//! Statistics of a compiled Noir program: what its ACIR uses, what the gadgets
+//! are expected to cost, and what the R1CS it compiles to costs, attributed
+//! back to the ACIR opcodes.
 
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
-    fs,
-    ops::AddAssign,
+    collections::{BTreeMap, HashSet},
+    fs::File,
     path::PathBuf,
 };
 
 use acir::{
-    circuit::{opcodes::BlackBoxFuncCall, Opcode, Program},
+    circuit::{
+        opcodes::{BlackBoxFuncCall, ConstantOrWitnessEnum},
+        Circuit, Opcode,
+    },
     native_types::Expression,
 };
 use acir_field::FieldElement;
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 use argh::FromArgs;
-use base64::Engine;
+use noir_r1cs::{
+    bitwise_cost, range_check_cost, sha256_compression_cost, NoirProofScheme, RangeCheckStrategy,
+    SourceMap, MAX_LOG_BASE, R1CS,
+};
+use noirc_artifacts::program::ProgramArtifact;
+use serde::Serialize;
 use tracing::instrument;
 
 use super::Command;
 
-/// Simple program to show circuit statistics
+/// Show ACIR and R1CS statistics of a compiled Noir program
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand, name = "circuit_stats")]
 pub struct Args {
-    /// path to circuit file
+    /// path to the compiled Noir program
     #[argh(positional)]
     circuit_path: PathBuf,
-    // /// path to witness file
-    // #[argh(positional)]
-    // witness_path: PathBuf,
+
+    /// print the report as JSON, for comparing circuits in CI
+    #[argh(switch)]
+    json: bool,
+
+    /// digit size in bits of the batched range checks compared against bit
+    /// decomposition
+    #[argh(option, default = "8")]
+    range_check_log_base: u32,
 }
 
-fn program_at_path(acir_path: String) -> Program<FieldElement> {
-    let json_string =
-        fs::read_to_string(acir_path).expect("There was a problem reading the file content");
-    let json_str: &str = &json_string;
-    let json: serde_json::Value =
-        serde_json::from_str(json_str).expect("There was a problem parsing the json program");
-    let Some(bytecode_str) = json["bytecode"].as_str() else {
-        panic!("Expected a different circuit format")
-    };
-    let bytecode: &[u8] = &base64::prelude::BASE64_STANDARD
-        .decode(bytecode_str)
-        .expect("There was a problem decoding the program from base 64");
-    let program = Program::deserialize_program(bytecode);
-    program.unwrap()
+/// Statistics of a program. Maps are sorted by key so that the JSON of two
+/// versions of a program can be diffed.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    acir: AcirStats,
+    costs: CostStats,
+    r1cs: R1csStats,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct AcirStats {
+    functions: usize,
+    public_inputs: usize,
+    private_inputs: usize,
+    return_values: usize,
+    /// Witnesses with calls inlined, counting a callee once per call.
+    witnesses: usize,
+    /// Opcodes by variant with calls inlined.
+    opcodes: BTreeMap<&'static str, usize>,
+    /// Black box calls by function.
+    black_box_calls: BTreeMap<&'static str, usize>,
+    /// Multiplication terms over all `AssertZero` opcodes.
+    mul_terms: usize,
+    /// Range checks by number of bits.
+    range_checks: BTreeMap<u32, usize>,
+    /// Bitwise operations by function and number of bits.
+    bitwise: BTreeMap<&'static str, BTreeMap<u32, usize>>,
+    memory: MemoryStats,
+    unique_brillig_functions: usize,
+    unique_acir_functions_called: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct MemoryStats {
+    blocks: usize,
+    /// Total initial size of all blocks.
+    size: usize,
+    reads: usize,
+    writes: usize,
+    /// Whether no location is written after it has been read.
+    read_only: bool,
+}
+
+/// Constraints predicted from the ACIR by the cost of each gadget.
+#[derive(Debug, Default, Serialize)]
+struct CostStats {
+    /// Range check constraints when decomposing each checked value into bits.
+    range_checks_bits: usize,
+    /// Range check constraints when batching digits of `range_check_log_base`
+    /// bits into one lookup.
+    range_checks_batched: usize,
+    range_check_log_base: u32,
+    /// Constraints per bitwise call by number of bits, the same for `AND` and
+    /// `XOR`.
+    bitwise_per_call: BTreeMap<u32, usize>,
+    /// Constraints of all bitwise calls.
+    bitwise: usize,
+    sha256_compression_per_call: usize,
+    /// Constraints of all `Sha256Compression` calls.
+    sha256_compression: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct R1csStats {
+    constraints: usize,
+    witnesses: usize,
+    public_inputs: usize,
+    /// Nonzero entries by matrix.
+    nonzeros: BTreeMap<&'static str, usize>,
+    /// Constraints by the kind of opcode they were compiled from, named as in
+    /// `acir.opcodes` with black box calls by function. Constraints not tied
+    /// to a single opcode are counted as `(public io)` and `(range checks)`,
+    /// as range checks are merged and emitted after all opcodes.
+    constraints_by_opcode: BTreeMap<&'static str, usize>,
 }
 
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        main(self);
+        ensure!(
+            (1..=MAX_LOG_BASE).contains(&self.range_check_log_base),
+            "Range check digits must have between 1 and {MAX_LOG_BASE} bits"
+        );
+        let file = File::open(&self.circuit_path).context("while opening Noir program")?;
+        let program: ProgramArtifact =
+            serde_json::from_reader(file).context("while reading Noir program")?;
+        let scheme =
+            NoirProofScheme::from_program(&program).context("while compiling Noir program")?;
+
+        let functions = &program.bytecode.functions;
+        let acir = acir_stats(functions)?;
+        let report = Report {
+            costs: cost_stats(functions, &acir, self.range_check_log_base)?,
+            acir,
+            r1cs: r1cs_stats(functions, &scheme.r1cs, scheme.source_map.as_ref()),
+        };
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print_report(&report);
+        }
         Ok(())
     }
 }
 
-fn main(arg: &Args) {
-    let program = program_at_path(arg.circuit_path.to_string_lossy().to_string());
-
-    assert!(
-        program.functions.len() == 1,
-        "only one function supported at the moment",
-    );
-
-    let Program {
-        mut functions,
-        unconstrained_functions: _,
-    } = program;
-    let circuit = functions.pop().unwrap();
+/// Opcodes of the entry point with every `Call` followed by the opcodes of the
+/// callee, in execution order.
+fn inlined_opcodes(functions: &[Circuit<FieldElement>]) -> Result<Vec<&Opcode<FieldElement>>> {
+    fn visit<'a>(
+        functions: &'a [Circuit<FieldElement>],
+        function: usize,
+        stack: &mut Vec<usize>,
+        opcodes: &mut Vec<&'a Opcode<FieldElement>>,
+    ) -> Result<()> {
+        ensure!(!stack.contains(&function), "Recursive ACIR call");
+        let circuit = functions
+            .get(function)
+            .with_context(|| format!("Unknown ACIR function {function}"))?;
+        stack.push(function);
+        for opcode in circuit.opcodes.iter() {
+            opcodes.push(opcode);
+            if let Opcode::Call { id, .. } = opcode {
+                visit(functions, id.0 as usize, stack, opcodes)?;
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+    let mut opcodes = Vec::new();
+    visit(functions, 0, &mut Vec::new(), &mut opcodes)?;
+    Ok(opcodes)
+}
 
-    println!("Private inputs len: {:?}", circuit.private_parameters.len());
-    println!(
-        "Public inputs len:  {:?}",
-        circuit.public_parameters.0.len()
-    );
-    println!("Return values len:  {:?}", circuit.return_values.0.len());
+fn opcode_variant(opcode: &Opcode<FieldElement>) -> &'static str {
+    match opcode {
+        Opcode::AssertZero(_) => "AssertZero",
+        Opcode::BlackBoxFuncCall(_) => "BlackBoxFuncCall",
+        Opcode::MemoryOp { .. } => "MemoryOp",
+        Opcode::MemoryInit { .. } => "MemoryInit",
+        Opcode::BrilligCall { .. } => "BrilligCall",
+        Opcode::Call { .. } => "Call",
+    }
+}
 
-    let mut total_num_mul_terms = 0;
-    let mut total_num_assert_zero_opcodes = 0;
+/// Name of the opcode variant, or of the function for black box calls.
+fn opcode_kind(opcode: &Opcode<FieldElement>) -> &'static str {
+    match opcode {
+        Opcode::BlackBoxFuncCall(call) => black_box_name(call),
+        _ => opcode_variant(opcode),
+    }
+}
 
-    // --- These are all the blackbox functions ---
-    // --- Manual because the variants don't equal each other when they have
-    // arguments ---
-    let mut blackbox_func_call_variants: HashMap<&str, usize> = vec![
-        ("AES128Encrypt", 0),
-        ("AND", 0),
-        ("XOR", 0),
-        ("RANGE", 0),
-        ("Blake2s", 0),
-        ("Blake3", 0),
-        ("EcdsaSecp256k1", 0),
-        ("EcdsaSecp256r1", 0),
-        ("MultiScalarMul", 0),
-        ("EmbeddedCurveAdd", 0),
-        ("Keccakf1600", 0),
-        ("RecursiveAggregation", 0),
-        ("BigIntAdd", 0),
-        ("BigIntSub", 0),
-        ("BigIntMul", 0),
-        ("BigIntDiv", 0),
-        ("BigIntFromLeBytes", 0),
-        ("BigIntToLeBytes", 0),
-        ("Poseidon2Permutation", 0),
-        ("Sha256Compression", 0),
-    ]
-    .into_iter()
-    .collect();
+fn black_box_name(call: &BlackBoxFuncCall<FieldElement>) -> &'static str {
+    match call {
+        BlackBoxFuncCall::AES128Encrypt { .. } => "AES128Encrypt",
+        BlackBoxFuncCall::AND { .. } => "AND",
+        BlackBoxFuncCall::XOR { .. } => "XOR",
+        BlackBoxFuncCall::RANGE { .. } => "RANGE",
+        BlackBoxFuncCall::Blake2s { .. } => "Blake2s",
+        BlackBoxFuncCall::Blake3 { .. } => "Blake3",
+        BlackBoxFuncCall::EcdsaSecp256k1 { .. } => "EcdsaSecp256k1",
+        BlackBoxFuncCall::EcdsaSecp256r1 { .. } => "EcdsaSecp256r1",
+        BlackBoxFuncCall::MultiScalarMul { .. } => "MultiScalarMul",
+        BlackBoxFuncCall::EmbeddedCurveAdd { .. } => "EmbeddedCurveAdd",
+        BlackBoxFuncCall::Keccakf1600 { .. } => "Keccakf1600",
+        BlackBoxFuncCall::RecursiveAggregation { .. } => "RecursiveAggregation",
+        BlackBoxFuncCall::BigIntAdd { .. } => "BigIntAdd",
+        BlackBoxFuncCall::BigIntSub { .. } => "BigIntSub",
+        BlackBoxFuncCall::BigIntMul { .. } => "BigIntMul",
+        BlackBoxFuncCall::BigIntDiv { .. } => "BigIntDiv",
+        BlackBoxFuncCall::BigIntFromLeBytes { .. } => "BigIntFromLeBytes",
+        BlackBoxFuncCall::BigIntToLeBytes { .. } => "BigIntToLeBytes",
+        BlackBoxFuncCall::Poseidon2Permutation { .. } => "Poseidon2Permutation",
+        BlackBoxFuncCall::Sha256Compression { .. } => "Sha256Compression",
+    }
+}
 
-    // --- Data tracking for range checks ---
-    let mut range_check_bit_counts: HashMap<u32, usize> = HashMap::new();
+fn acir_stats(functions: &[Circuit<FieldElement>]) -> Result<AcirStats> {
+    let circuit = functions.first().context("Program has no functions")?;
+    let mut stats = AcirStats {
+        functions: functions.len(),
+        public_inputs: circuit.public_parameters.0.len(),
+        private_inputs: circuit.private_parameters.len(),
+        return_values: circuit.return_values.0.len(),
+        witnesses: circuit.current_witness_index as usize + 1,
+        ..AcirStats::default()
+    };
 
-    // --- Data tracking for memory operations ---
-    let mut mem_read_counter = 0;
-    let mut mem_write_counter = 0;
-    let mut mem_alloc_counter = 0;
-    let mut mem_alloc_total_size = 0;
     // We want to know if any writes happen AFTER reads to a specific location
     let mut mem_read_locs = HashSet::new();
-    let mut all_reads_after_writes = true;
+    let mut write_after_read = false;
+    let mut brillig_functions = HashSet::new();
+    let mut acir_functions = HashSet::new();
 
-    // --- Tracking for `BrilligCall` and `Call` ---
-    let mut num_brillig_call_opcodes = 0;
-    let mut unique_brillig_call_opcodes = HashSet::new();
-    let mut num_call_opcodes = 0;
-    let mut unique_call_opcodes = HashSet::new();
-
-    // --- I'm guessing Noir does it so that you just "execute" the opcodes in a
-    // linear manner --- --- Or at least that they correspond to constraints
-    // which should be satisfied in that "order" ---
-    for opcode in circuit.opcodes.iter() {
+    // Calls are inlined, so the callee's opcodes are counted once per call
+    for opcode in inlined_opcodes(functions)? {
+        *stats.opcodes.entry(opcode_variant(opcode)).or_default() += 1;
         match opcode {
-            Opcode::AssertZero(expr) => {
-                // println!("AssertZero: {:?}", opcode);
-                total_num_mul_terms += expr.num_mul_terms();
-                total_num_assert_zero_opcodes += 1;
-            }
-            Opcode::BlackBoxFuncCall(variant) => {
-                // println!("BlackBoxFuncCall: {:?}", variant);
-                match variant {
-                    BlackBoxFuncCall::AES128Encrypt {
-                        inputs: _,
-                        iv: _,
-                        key: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("AES128Encrypt")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::AND {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("AND")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::XOR {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("XOR")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
+            Opcode::AssertZero(expr) => stats.mul_terms += expr.num_mul_terms(),
+            Opcode::BlackBoxFuncCall(call) => {
+                *stats
+                    .black_box_calls
+                    .entry(black_box_name(call))
+                    .or_default() += 1;
+                match call {
+                    BlackBoxFuncCall::AND { lhs, .. } | BlackBoxFuncCall::XOR { lhs, .. } => {
+                        *stats
+                            .bitwise
+                            .entry(black_box_name(call))
+                            .or_default()
+                            .entry(lhs.num_bits())
+                            .or_default() += 1;
                     }
                     BlackBoxFuncCall::RANGE { input } => {
-                        // --- We keep track of the total number of RANGE calls ---
-                        blackbox_func_call_variants
-                            .entry("RANGE")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                        // --- We also keep track of the RANGE calls, broken down by num bits ---
-                        if let Entry::Occupied(mut x) =
-                            range_check_bit_counts.entry(input.num_bits())
-                        {
-                            x.get_mut().add_assign(1);
-                        } else {
-                            range_check_bit_counts.insert(input.num_bits(), 1);
-                        }
-                    }
-
-                    BlackBoxFuncCall::Blake2s {
-                        inputs: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("Blake2s")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::Blake3 {
-                        inputs: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("Blake3")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::EcdsaSecp256k1 {
-                        public_key_x: _,
-                        public_key_y: _,
-                        signature: _,
-                        hashed_message: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("EcdsaSecp256k1")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::EcdsaSecp256r1 {
-                        public_key_x: _,
-                        public_key_y: _,
-                        signature: _,
-                        hashed_message: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("EcdsaSecp256r1")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::MultiScalarMul {
-                        points: _,
-                        scalars: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("MultiScalarMul")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::EmbeddedCurveAdd {
-                        input1: _,
-                        input2: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("EmbeddedCurveAdd")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::Keccakf1600 {
-                        inputs: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("Keccakf1600")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::RecursiveAggregation {
-                        verification_key: _,
-                        proof: _,
-                        public_inputs: _,
-                        key_hash: _,
-                        proof_type: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("RecursiveAggregation")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntAdd {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntAdd")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntSub {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntSub")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntMul {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntMul")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntDiv {
-                        lhs: _,
-                        rhs: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntDiv")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntFromLeBytes {
-                        inputs: _,
-                        modulus: _,
-                        output: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntFromLeBytes")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::BigIntToLeBytes {
-                        input: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("BigIntToLeBytes")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::Poseidon2Permutation {
-                        inputs: _,
-                        outputs: _,
-                        len: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("Poseidon2Permutation")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
-                    }
-                    BlackBoxFuncCall::Sha256Compression {
-                        inputs: _,
-                        hash_values: _,
-                        outputs: _,
-                    } => {
-                        blackbox_func_call_variants
-                            .entry("Sha256Compression")
-                            .and_modify(|count| {
-                                count.add_assign(1);
-                            });
+                        *stats.range_checks.entry(input.num_bits()).or_default() += 1;
                     }
+                    _ => {}
                 }
             }
-            Opcode::MemoryOp {
-                block_id,
-                op,
-                predicate: _,
-            } => {
-                // --- For MemoryOp there are only two variants: read and write ---
-                // --- Additionally, each MemoryOp tells you how to read/write a *single* index
-                // --- --- Therefore we will simply count the total number of
-                // reads and writes --- (Additionally, check whether each write
-                // happens before all reads to that address)
-
+            Opcode::MemoryOp { block_id, op, .. } => {
                 // `Expression::zero()` means read, and `Expression::one()` means write
                 if op.operation == Expression::zero() {
-                    mem_read_counter += 1;
+                    stats.memory.reads += 1;
                     mem_read_locs.insert((block_id, op.index.clone()));
                 } else {
-                    mem_write_counter += 1;
-                    // --- If we've read from this location before and *then* wrote to it ---
-                    if mem_read_locs.contains(&(block_id, op.index.clone())) {
-                        println!("Write after read at {:?}, {:?}", block_id, op.index.clone());
-                        all_reads_after_writes = false;
-                    }
+                    stats.memory.writes += 1;
+                    write_after_read |= mem_read_locs.contains(&(block_id, op.index.clone()));
                 }
-
-                // println!("MemoryOp: {:?}", opcode);
             }
-            Opcode::MemoryInit {
-                block_id: _,
-                init,
-                block_type: _,
-            } => {
-                mem_alloc_counter += 1;
-                mem_alloc_total_size += init.len();
-                // println!("MemoryInit: {:?}", opcode);
-
-                // TODO(ryancao): handle block type?
-                // pub enum BlockType {
-                //     Memory,
-                //     CallData(u32),
-                //     ReturnData,
-                // }
+            Opcode::MemoryInit { init, .. } => {
+                stats.memory.blocks += 1;
+                stats.memory.size += init.len();
             }
-            Opcode::BrilligCall {
-                id,
-                inputs: _,
-                outputs: _,
-                predicate: _,
-            } => {
-                // println!("BrilligCall: {:?}", opcode);
-                // --- Brillig calls aren't really translated into constraints ---
-                // --- Therefore we can profile them but won't worry about them ---
-                num_brillig_call_opcodes += 1;
-                unique_brillig_call_opcodes.insert(id);
+            // Brillig calls are unconstrained and don't produce constraints
+            Opcode::BrilligCall { id, .. } => {
+                brillig_functions.insert(id);
             }
-            Opcode::Call {
-                id,
-                inputs: _,
-                outputs: _,
-                predicate: _,
-            } => {
-                // println!("Call: {:?}", opcode);
-                // --- So these are calls to separate circuits... Not sure where to get those
-                // circuits, but oh well ---
-                num_call_opcodes += 1;
-                unique_call_opcodes.insert(id);
+            Opcode::Call { id, .. } => {
+                acir_functions.insert(id);
+                stats.witnesses += functions[id.0 as usize].current_witness_index as usize + 1;
             }
         }
     }
+    stats.memory.read_only = !write_after_read;
+    stats.unique_brillig_functions = brillig_functions.len();
+    stats.unique_acir_functions_called = acir_functions.len();
+    Ok(stats)
+}
 
-    // --- AssertZero ---
-    println!("Num AssertZero: {:}", total_num_assert_zero_opcodes,);
-    println!("Num `mul_term`s: {:}", total_num_mul_terms);
+fn cost_stats(
+    functions: &[Circuit<FieldElement>],
+    acir: &AcirStats,
+    range_check_log_base: u32,
+) -> Result<CostStats> {
+    // Witnesses of different calls to a function are not told apart, which
+    // slightly underestimates the batched range checks.
+    let range_checks = inlined_opcodes(functions)?
+        .into_iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) => match input.input() {
+                ConstantOrWitnessEnum::Witness(witness) => {
+                    Some((witness.as_usize(), input.num_bits()))
+                }
+                ConstantOrWitnessEnum::Constant(_) => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
 
-    // --- Blackbox functions ---
-    blackbox_func_call_variants.iter().for_each(|(k, v)| {
-        if *v > 0 {
-            println!("Blackbox fn {:?}: {:?}", k, v);
+    let mut stats = CostStats {
+        range_checks_bits: range_check_cost(RangeCheckStrategy::Bits, &range_checks),
+        range_checks_batched: range_check_cost(
+            RangeCheckStrategy::Batched {
+                log_base: range_check_log_base,
+            },
+            &range_checks,
+        ),
+        range_check_log_base,
+        sha256_compression_per_call: sha256_compression_cost(),
+        ..CostStats::default()
+    };
+    for counts in acir.bitwise.values() {
+        for (&num_bits, count) in counts {
+            let cost = bitwise_cost(num_bits);
+            stats.bitwise_per_call.insert(num_bits, cost);
+            stats.bitwise += cost * count;
         }
-    });
-    let mut key_value_pairs: Vec<(u32, usize)> = range_check_bit_counts.into_iter().collect();
-    key_value_pairs.sort_by(|x, y| y.1.cmp(&x.1));
-    key_value_pairs.iter().for_each(|(k, v)| {
-        println!("RANGE check: {:?} bits had {:?} lookups", k, v);
-    });
+    }
+    let sha256_calls = acir
+        .black_box_calls
+        .get("Sha256Compression")
+        .copied()
+        .unwrap_or(0);
+    stats.sha256_compression = stats.sha256_compression_per_call * sha256_calls;
+    Ok(stats)
+}
 
-    // --- Memory operations ---
-    println!("Num Memory inits: {:?}", mem_alloc_counter);
-    println!("Total memory alloc size: {:?}", mem_alloc_total_size);
-    println!("Num Memory writes: {:?}", mem_write_counter);
-    println!("Num Memory reads: {:?}", mem_read_counter);
-    println!("Read-only: {:?}", all_reads_after_writes);
+fn r1cs_stats(
+    functions: &[Circuit<FieldElement>],
+    r1cs: &R1CS,
+    source_map: Option<&SourceMap>,
+) -> R1csStats {
+    let mut stats = R1csStats {
+        constraints: r1cs.constraints,
+        witnesses: r1cs.witnesses,
+        public_inputs: r1cs.public_inputs,
+        nonzeros: [
+            ("a", r1cs.a.num_entries()),
+            ("b", r1cs.b.num_entries()),
+            ("c", r1cs.c.num_entries()),
+        ]
+        .into_iter()
+        .collect(),
+        ..R1csStats::default()
+    };
+    let Some(source_map) = source_map else {
+        return stats;
+    };
+
+    // Unattributed rows before the first opcode are public value equalities,
+    // the ones after are the deferred range checks.
+    let mut seen_opcode = false;
+    for row in 0..r1cs.constraints {
+        let opcode = source_map
+            .origin(row)
+            .and_then(|origin| origin.call_stack.last())
+            .and_then(|&(function, index)| functions.get(function as usize)?.opcodes.get(index));
+        let kind = match opcode {
+            Some(opcode) => {
+                seen_opcode = true;
+                opcode_kind(opcode)
+            }
+            None if seen_opcode => "(range checks)",
+            None => "(public io)",
+        };
+        *stats.constraints_by_opcode.entry(kind).or_default() += 1;
+    }
+    stats
+}
 
-    // --- Brillig + Call operations ---
-    println!("Num Brillig calls: {:?}", num_brillig_call_opcodes);
+fn print_report(report: &Report) {
+    let acir = &report.acir;
+    println!("ACIR functions:     {}", acir.functions);
+    println!("Public inputs len:  {}", acir.public_inputs);
+    println!("Private inputs len: {}", acir.private_inputs);
+    println!("Return values len:  {}", acir.return_values);
+    println!("Witnesses:          {}", acir.witnesses);
+    for (kind, count) in &acir.opcodes {
+        println!("Num {kind}: {count}");
+    }
+    println!("Num `mul_term`s: {}", acir.mul_terms);
+    for (name, count) in &acir.black_box_calls {
+        println!("Blackbox fn {name}: {count}");
+    }
+    for (bits, count) in &acir.range_checks {
+        println!("RANGE check: {bits} bits had {count} lookups");
+    }
+    for (name, counts) in &acir.bitwise {
+        for (bits, count) in counts {
+            println!("{name} on {bits} bits: {count} calls");
+        }
+    }
+    let memory = &acir.memory;
+    println!("Num Memory inits: {}", memory.blocks);
+    println!("Total memory alloc size: {}", memory.size);
+    println!("Num Memory writes: {}", memory.writes);
+    println!("Num Memory reads: {}", memory.reads);
+    println!("Read-only: {}", memory.read_only);
     println!(
-        "Num Unique Brillig opcodes: {:?}",
-        unique_brillig_call_opcodes.len()
+        "Num Unique Brillig functions: {}",
+        acir.unique_brillig_functions
     );
-    println!("Num Calls: {:?}", num_call_opcodes);
-    println!("Num Unique calls: {:?}", unique_call_opcodes.len());
+    println!("Num Unique calls: {}", acir.unique_acir_functions_called);
 
     println!("----------------------------------------------------------------");
 
-    // --- Finally, we analyze the total number of constraints + witness size ---
-    let mut total_num_r1cs_rows = 0;
-    let mut total_num_witness_size = 0;
-    total_num_r1cs_rows += total_num_assert_zero_opcodes + total_num_mul_terms;
+    let costs = &report.costs;
     println!(
-        "{:?} constraints from AssertZero",
-        total_num_assert_zero_opcodes + total_num_mul_terms
+        "Range check constraints: {} with bit decomposition, {} batched with {}-bit digits",
+        costs.range_checks_bits, costs.range_checks_batched, costs.range_check_log_base
     );
-    // SHA adds 31264 R1CS constraints and 30959 witnesses
-    if let Entry::Occupied(x) = blackbox_func_call_variants.entry("Sha256Compression") {
-        println!("{:?} constraints from SHA", 31264 * x.get());
-        println!("{:?} witnesses from SHA", 30959 * x.get());
-        total_num_r1cs_rows += 31264 * x.get();
-        total_num_witness_size += 30959 * x.get();
-    }
-    // Poseidon adds 415 R1CS constraints and 417 wires
-    if let Entry::Occupied(x) = blackbox_func_call_variants.entry("Poseidon2Permutation") {
-        println!("{:?} constraints from Poseidon2", 415 * x.get());
-        println!("{:?} witnesses from Poseidon2", 417 * x.get());
-        total_num_r1cs_rows += 415 * x.get();
-        total_num_witness_size += 417 * x.get();
+    for (bits, cost) in &costs.bitwise_per_call {
+        println!("Bitwise operation on {bits} bits: {cost} constraints per call");
     }
-    // We do a simple (i.e. non-LogUp) approach for computing range checks
-    let mut total_num_range_check_constraints = 0;
-    let mut total_num_range_check_witnesses = 0;
-    key_value_pairs.iter().for_each(|(k, v)| {
-        total_num_range_check_constraints += 2 * v; // Let's assume for now that we can do the decomp in a single R1CS row
-        total_num_range_check_witnesses += (*k as usize) * v; // Total # of
-                                                              // extra witnesses
-    });
+    println!("{} constraints from bitwise operations", costs.bitwise);
     println!(
-        "{:?} constraints from range checks",
-        total_num_range_check_constraints
+        "{} constraints from SHA ({} per compression)",
+        costs.sha256_compression, costs.sha256_compression_per_call
     );
-    println!(
-        "{:?} witnesses from range checks",
-        total_num_range_check_witnesses
-    );
-    total_num_r1cs_rows += total_num_range_check_constraints;
-    total_num_witness_size += total_num_range_check_witnesses;
 
-    // TODO(ryancao): COMPUTE THE MEMORY CHECKER CONSTRAINTS
-    println!("TODO(ryancao): MISSING MEMORY CHECKER CONSTRAINTS");
-    println!(
-        "{:?} total R1CS constraints ({:?} num vars)",
-        total_num_r1cs_rows,
-        (total_num_r1cs_rows as f64).log2()
-    );
+    println!("----------------------------------------------------------------");
+
+    let r1cs = &report.r1cs;
     println!(
-        "{:?} total R1CS witness size, ({:?} num vars)",
-        total_num_witness_size,
-        (total_num_witness_size as f64).log2()
+        "{} R1CS constraints, {} witnesses, {} public inputs",
+        r1cs.constraints, r1cs.witnesses, r1cs.public_inputs
     );
+    for (matrix, count) in &r1cs.nonzeros {
+        println!("{} nonzero entries in {}", count, matrix.to_uppercase());
+    }
+    for (kind, count) in &r1cs.constraints_by_opcode {
+        println!(
+            "{count} constraints from {kind} ({:.1}%)",
+            100.0 * *count as f64 / r1cs.constraints.max(1) as f64
+        );
+    }
 }
@@ -0,0 +1,36 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use noir_r1cs::{read, FileFormat, NoirProofScheme, R1CSTriplets, R1CS};
+use tracing::instrument;
+
+use super::Command;
+
+/// Print the digest of the R1CS of a prepared Noir program or R1CS triplets
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "digest")]
+pub struct Args {
+    /// path to the prepared proof scheme, or R1CS triplets with extension
+    /// `.r1cst`
+    #[argh(positional)]
+    path: PathBuf,
+}
+
+impl Command for Args {
+    #[instrument(skip_all)]
+    fn run(&self) -> Result<()> {
+        let extension = self.path.extension().and_then(OsStr::to_str);
+        let digest = if extension == Some(R1CSTriplets::EXTENSION) {
+            let triplets: R1CSTriplets = read(&self.path).context("while reading R1CS triplets")?;
+            R1CS::try_from(&triplets)?.digest()
+        } else {
+            let scheme: NoirProofScheme =
+                read(&self.path).context("while reading Noir proof scheme")?;
+            scheme.check_digest()?;
+            scheme.r1cs_digest
+        };
+        println!("{}", hex::encode(digest));
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use std::{ops::Range, path::PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use argh::FromArgs;
+use noir_r1cs::{read, write, NoirProofScheme, R1CSTriplets};
+use tracing::{info, instrument};
+
+use super::Command;
+
+/// Export the R1CS of a prepared Noir program as (row, col, value) triplets
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "export-r1cs")]
+pub struct Args {
+    /// path to the prepared proof scheme
+    #[argh(positional)]
+    scheme_path: PathBuf,
+
+    /// path to the input values, to include the solved witness
+    #[argh(option, long = "input", short = 'i')]
+    input_path: Option<PathBuf>,
+
+    /// output path for the R1CS triplets
+    #[argh(
+        option,
+        long = "out",
+        short = 'o',
+        default = "PathBuf::from(\"r1cs.json\")"
+    )]
+    output_path: PathBuf,
+
+    /// range of constraints to print, e.g. `10..20`
+    #[argh(option, from_str_fn(parse_range))]
+    print: Option<Range<usize>>,
+}
+
+fn parse_range(value: &str) -> Result<Range<usize>, String> {
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| format!("expected a range start..end, got {value:?}"))?;
+    let parse = |s: &str| s.parse::<usize>().map_err(|e| format!("{s:?}: {e}"));
+    Ok(parse(start)?..parse(end)?)
+}
+
+impl Command for Args {
+    #[instrument(skip_all)]
+    fn run(&self) -> Result<()> {
+        let scheme: NoirProofScheme =
+            read(&self.scheme_path).context("while reading Noir proof scheme")?;
+        let r1cs = &scheme.r1cs;
+        info!(
+            constraints = r1cs.constraints,
+            witnesses = r1cs.witnesses,
+            "Read Noir proof scheme"
+        );
+
+        let mut triplets = R1CSTriplets::from(r1cs);
+        if let Some(input_path) = &self.input_path {
+            triplets.witness = scheme
+                .solve_witness(input_path)
+                .context("while solving witness")?;
+        }
+
+        if let Some(rows) = &self.print {
+            ensure!(
+                rows.end <= r1cs.constraints,
+                "Constraint range {rows:?} exceeds the {} constraints",
+                r1cs.constraints
+            );
+            let witness = self.input_path.is_some().then_some(&triplets.witness[..]);
+            for row in rows.clone() {
+                println!("{}", r1cs.display_constraint(row, witness));
+            }
+        }
+
+        write(&triplets, &self.output_path).context("while writing R1CS triplets")?;
+        Ok(())
+    }
+}
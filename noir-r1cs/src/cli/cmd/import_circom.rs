@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use noir_r1cs::{read_circom_r1cs, read_circom_witness, write, R1CSTriplets};
+use tracing::{info, instrument};
+
+use super::Command;
+
+/// Convert a circom R1CS and witness to R1CS triplets for proving
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "import-circom")]
+pub struct Args {
+    /// path to the circom `.r1cs` file
+    #[argh(positional)]
+    r1cs_path: PathBuf,
+
+    /// path to a circom `.wtns` witness file
+    #[argh(option, long = "witness", short = 'w')]
+    witness_path: Option<PathBuf>,
+
+    /// output path for the R1CS triplets
+    #[argh(
+        option,
+        long = "out",
+        short = 'o',
+        default = "PathBuf::from(\"r1cs.r1cst\")"
+    )]
+    output_path: PathBuf,
+}
+
+impl Command for Args {
+    #[instrument(skip_all)]
+    fn run(&self) -> Result<()> {
+        let r1cs = read_circom_r1cs(&self.r1cs_path).context("while reading circom R1CS")?;
+        info!(
+            constraints = r1cs.constraints,
+            witnesses = r1cs.witnesses,
+            public_inputs = r1cs.public_inputs,
+            "Read circom R1CS"
+        );
+
+        let mut triplets = R1CSTriplets::from(&r1cs);
+        if let Some(witness_path) = &self.witness_path {
+            let witness =
+                read_circom_witness(witness_path).context("while reading circom witness")?;
+            r1cs.verify_witness(&witness)
+                .context("while verifying circom witness")?;
+            triplets.witness = witness;
+        }
+
+        write(&triplets, &self.output_path).context("while writing R1CS triplets")?;
+        Ok(())
+    }
+}
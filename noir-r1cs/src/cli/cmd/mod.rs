@@ -1,4 +1,7 @@
 mod circuit_stats;
+mod digest;
+mod export_r1cs;
+mod import_circom;
 // mod generate_gnark_inputs;
 mod prepare;
 mod solve_witness;
@@ -24,6 +27,9 @@ enum Commands {
     Prepare(prepare::Args),
     SolveWitness(solve_witness::Args),
     CircuitStats(circuit_stats::Args),
+    ExportR1CS(export_r1cs::Args),
+    ImportCircom(import_circom::Args),
+    Digest(digest::Args),
 }
 
 impl Command for Args {
@@ -38,6 +44,9 @@ impl Command for Commands {
             Commands::Prepare(args) => args.run(),
             Commands::SolveWitness(args) => args.run(),
             Commands::CircuitStats(args) => args.run(),
+            Commands::ExportR1CS(args) => args.run(),
+            Commands::ImportCircom(args) => args.run(),
+            Commands::Digest(args) => args.run(),
         }
     }
 }
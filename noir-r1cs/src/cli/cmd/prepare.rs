@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
-use noir_r1cs::{write, NoirProofScheme};
+use noir_r1cs::{write, NoirProofScheme, RangeCheckStrategy};
 use tracing::instrument;
 
 use super::Command;
@@ -23,13 +23,31 @@ pub struct Args {
         default = "PathBuf::from(\"noir_proof_scheme.json\")"
     )]
     output_path: PathBuf,
+
+    /// leave out the constraint to source map used in error messages
+    #[argh(switch)]
+    no_source_map: bool,
+
+    /// batch range checks into lookups of digits of this many bits instead
+    /// of decomposing each checked value into bits
+    #[argh(option)]
+    range_check_log_base: Option<u32>,
 }
 
 impl Command for Args {
     #[instrument(skip_all)]
     fn run(&self) -> Result<()> {
-        let scheme = NoirProofScheme::from_file(&self.program_path)
-            .context("while compiling Noir program")?;
+        let range_check_strategy = self
+            .range_check_log_base
+            .map_or(RangeCheckStrategy::Bits, |log_base| {
+                RangeCheckStrategy::Batched { log_base }
+            });
+        let mut scheme =
+            NoirProofScheme::from_file_with_range_checks(&self.program_path, range_check_strategy)
+                .context("while compiling Noir program")?;
+        if self.no_source_map {
+            scheme.source_map = None;
+        }
         write(&scheme, &self.output_path).context("while writing Noir proof scheme")?;
         Ok(())
     }
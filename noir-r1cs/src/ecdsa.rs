@@ -0,0 +1,498 @@
+//! ECDSA signature verification over secp256k1 and secp256r1.
+//!
+//! Coordinates live in the curve's base field and scalars in its group order,
+//! both emulated with [`NonNativeField`]. Points use incomplete affine
+//! formulas. The double scalar multiplication `u1 G + u2 Q` starts from an
+//! offset point `T` of unknown discrete logarithm, so that the accumulator
+//! never meets the point at infinity, and removes the offset at the end.
+
+use std::sync::LazyLock;
+
+use ark_std::{One, Zero};
+use num_bigint::BigUint;
+
+use crate::{
+    bitwise::{self, scale},
+    non_native::{Element, NonNativeField, Term},
+    FieldElement, LinearCombination, R1CSBuilder,
+};
+
+/// Bits of the scalars in the double scalar multiplication.
+const SCALAR_BITS: usize = 256;
+
+/// Affine point on a short Weierstrass curve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Point {
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+/// Short Weierstrass curve `y^2 = x^3 + a x + b` over `F_p` with a prime
+/// order generator.
+#[derive(Debug)]
+pub(crate) struct Curve {
+    pub p: BigUint,
+    pub n: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub generator: Point,
+    /// Offset for the scalar multiplication ladder, the point with the
+    /// smallest positive x coordinate.
+    offset: Point,
+}
+
+pub(crate) static SECP256K1: LazyLock<Curve> = LazyLock::new(|| {
+    Curve::new(
+        "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+        "0",
+        "7",
+        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+    )
+});
+
+pub(crate) static SECP256R1: LazyLock<Curve> = LazyLock::new(|| {
+    Curve::new(
+        "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+        "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+        "ffffffff00000001000000000000000000000000fffffffffffffffffffffffc",
+        "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+        "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+        "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+    )
+});
+
+fn parse_hex(hex: &str) -> BigUint {
+    BigUint::parse_bytes(hex.as_bytes(), 16).expect("Invalid constant")
+}
+
+impl Curve {
+    fn new(p: &str, n: &str, a: &str, b: &str, gx: &str, gy: &str) -> Self {
+        let p = parse_hex(p);
+        let a = parse_hex(a);
+        let b = parse_hex(b);
+        assert_eq!(
+            &p % 4u32,
+            BigUint::from(3u32),
+            "Square roots need p = 3 mod 4"
+        );
+        let offset = (1u32..)
+            .find_map(|x| {
+                let x = BigUint::from(x);
+                let rhs = (x.pow(3) + &a * &x + &b) % &p;
+                let y = rhs.modpow(&((&p + 1u32) >> 2), &p);
+                ((&y * &y) % &p == rhs).then_some(Point { x, y })
+            })
+            .unwrap();
+        let curve = Self {
+            p,
+            n: parse_hex(n),
+            a,
+            b,
+            generator: Point {
+                x: parse_hex(gx),
+                y: parse_hex(gy),
+            },
+            offset,
+        };
+        assert!(curve.is_on_curve(&curve.generator));
+        curve
+    }
+
+    pub fn is_on_curve(&self, point: &Point) -> bool {
+        let rhs = point.x.pow(3) + &self.a * &point.x + &self.b;
+        (&point.y * &point.y) % &self.p == rhs % &self.p
+    }
+
+    fn inverse(&self, value: &BigUint) -> BigUint {
+        value.modpow(&(&self.p - 2u32), &self.p)
+    }
+
+    pub fn neg(&self, point: &Point) -> Point {
+        Point {
+            x: point.x.clone(),
+            y: (&self.p - &point.y) % &self.p,
+        }
+    }
+
+    /// Sum of two points, with `None` the point at infinity.
+    pub fn add(&self, lhs: Option<&Point>, rhs: Option<&Point>) -> Option<Point> {
+        let (lhs, rhs) = match (lhs, rhs) {
+            (None, rhs) => return rhs.cloned(),
+            (lhs, None) => return lhs.cloned(),
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+        };
+        let p = &self.p;
+        let lambda = if lhs.x == rhs.x {
+            if (&lhs.y + &rhs.y) % p == BigUint::zero() {
+                return None;
+            }
+            (&lhs.x * &lhs.x * 3u32 + &self.a) * self.inverse(&(&lhs.y * 2u32)) % p
+        } else {
+            (&rhs.y + p - &lhs.y) * self.inverse(&((&rhs.x + p - &lhs.x) % p)) % p
+        };
+        let x = (&lambda * &lambda + p * 2u32 - &lhs.x - &rhs.x) % p;
+        let y = (&lambda * ((&lhs.x + p - &x) % p) + p - &lhs.y) % p;
+        Some(Point { x, y })
+    }
+
+    /// Scalar multiple of a point, with `None` the point at infinity.
+    pub fn mul(&self, scalar: &BigUint, point: &Point) -> Option<Point> {
+        (0..scalar.bits()).rev().fold(None, |acc, i| {
+            let acc = self.add(acc.as_ref(), acc.as_ref());
+            if scalar.bit(i) {
+                self.add(acc.as_ref(), Some(point))
+            } else {
+                acc
+            }
+        })
+    }
+}
+
+/// In-circuit affine point with non-native coordinates.
+#[derive(Clone)]
+struct PointVar {
+    x: Element,
+    y: Element,
+}
+
+struct EcdsaGadget<'a> {
+    curve: &'a Curve,
+    base: NonNativeField,
+    scalar: NonNativeField,
+}
+
+/// Verify an ECDSA signature over `curve`, returning a boolean that is one
+/// if and only if the signature is valid. Inputs are big-endian bytes that
+/// must be range checked by the caller: 32 bytes for each public key
+/// coordinate and the hashed message, and `r || s` for the signature.
+///
+/// Like the ACVM, a signature is only valid for a canonical public key on the
+/// curve, `r` in `1..n` and a low `s` in `1..=n/2`. When any of these fails,
+/// the generator and `r = s = 1` are verified in their place so that the
+/// constraints stay satisfiable, and the result is zero.
+///
+/// The scalar multiplication uses incomplete formulas, so the constraints are
+/// unsatisfiable instead of returning zero when `u1 G + u2 Q` is the point at
+/// infinity or the public key is a small combination of the offset and the
+/// generator. Neither can happen for a key whose owner follows the protocol.
+pub(crate) fn add_verification(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    curve: &Curve,
+    public_key_x: &[LinearCombination],
+    public_key_y: &[LinearCombination],
+    signature: &[LinearCombination],
+    hashed_message: &[LinearCombination],
+) -> LinearCombination {
+    assert_eq!(signature.len(), 64, "Signatures are 64 bytes");
+    let gadget = EcdsaGadget {
+        curve,
+        base: NonNativeField::new(curve.p.clone(), witness_one),
+        scalar: NonNativeField::new(curve.n.clone(), witness_one),
+    };
+    let base = &gadget.base;
+    let scalar = &gadget.scalar;
+    let one = BigUint::one();
+    let and = |r1cs: &mut R1CSBuilder, a: &LinearCombination, b: &LinearCombination| {
+        bitwise::add_product(r1cs, witness_one, a, b)
+    };
+    let not = |a: &LinearCombination| {
+        let mut result = scale(a, -FieldElement::one());
+        result.push((FieldElement::one(), witness_one));
+        result
+    };
+
+    // Public key
+    let public_key = PointVar {
+        x: base.from_bytes_be(public_key_x),
+        y: base.from_bytes_be(public_key_y),
+    };
+    let x_canonical = base.is_less_than(r1cs, &public_key.x, &curve.p);
+    let y_canonical = base.is_less_than(r1cs, &public_key.y, &curve.p);
+    let on_curve = gadget.is_on_curve(r1cs, &public_key);
+    let key_valid = and(r1cs, &x_canonical, &y_canonical);
+    let key_valid = and(r1cs, &key_valid, &on_curve);
+
+    // Signature
+    let r = scalar.from_bytes_be(&signature[..32]);
+    let s = scalar.from_bytes_be(&signature[32..]);
+    let z = scalar.from_bytes_be(hashed_message);
+    let r_below = scalar.is_less_than(r1cs, &r, &curve.n);
+    let r_zero = scalar.is_zero_integer(r1cs, &r);
+    let s_below = scalar.is_less_than(r1cs, &s, &((&curve.n >> 1) + 1u32));
+    let s_zero = scalar.is_zero_integer(r1cs, &s);
+    let r_valid = and(r1cs, &r_below, &not(&r_zero));
+    let s_valid = and(r1cs, &s_below, &not(&s_zero));
+    let signature_valid = and(r1cs, &r_valid, &s_valid);
+    let well_formed = and(r1cs, &key_valid, &signature_valid);
+
+    // Replace malformed inputs with ones the gadgets accept
+    let unit = scalar.constant(&one);
+    let public_key = gadget.select(
+        r1cs,
+        &well_formed,
+        &public_key,
+        &gadget.constant(&curve.generator),
+    );
+    let r = scalar.select(r1cs, &well_formed, &r, &unit);
+    let s = scalar.select(r1cs, &well_formed, &s, &unit);
+    let s_inverse = scalar.divide(r1cs, &[Term::Constant(&one)], &[(1, &s)]);
+    let u1 = scalar.reduce(r1cs, &[Term::Product(1, &z, &s_inverse)]);
+    let u2 = scalar.reduce(r1cs, &[Term::Product(1, &r, &s_inverse)]);
+
+    // R = u1 G + u2 Q must have x coordinate r modulo n
+    let point = gadget.double_scalar_mul(r1cs, &u1, &u2, &public_key);
+    base.assert_less_than(r1cs, &point.x, &curve.p);
+    let matches = scalar.is_zero(r1cs, &[Term::Linear(1, &point.x), Term::Linear(-1, &r)]);
+    and(r1cs, &well_formed, &matches)
+}
+
+impl EcdsaGadget<'_> {
+    fn constant(&self, point: &Point) -> PointVar {
+        PointVar {
+            x: self.base.constant(&point.x),
+            y: self.base.constant(&point.y),
+        }
+    }
+
+    /// A boolean that is one if and only if `point` is on the curve.
+    fn is_on_curve(&self, r1cs: &mut R1CSBuilder, point: &PointVar) -> LinearCombination {
+        let base = &self.base;
+        let a = base.constant(&self.curve.a);
+        let minus_b = &self.curve.p - &self.curve.b;
+        let x_squared = base.reduce(r1cs, &[Term::Product(1, &point.x, &point.x)]);
+        base.is_zero(
+            r1cs,
+            &[
+                Term::Product(1, &point.y, &point.y),
+                Term::Product(-1, &x_squared, &point.x),
+                Term::Product(-1, &a, &point.x),
+                Term::Constant(&minus_b),
+            ],
+        )
+    }
+
+    /// Incomplete addition, unsatisfiable when the x coordinates are equal.
+    fn add(&self, r1cs: &mut R1CSBuilder, lhs: &PointVar, rhs: &PointVar) -> PointVar {
+        let base = &self.base;
+        base.assert_nonzero(r1cs, &[(1, &rhs.x), (-1, &lhs.x)]);
+        let lambda = base.divide(
+            r1cs,
+            &[Term::Linear(1, &rhs.y), Term::Linear(-1, &lhs.y)],
+            &[(1, &rhs.x), (-1, &lhs.x)],
+        );
+        let x = base.reduce(
+            r1cs,
+            &[
+                Term::Product(1, &lambda, &lambda),
+                Term::Linear(-1, &lhs.x),
+                Term::Linear(-1, &rhs.x),
+            ],
+        );
+        let y = base.reduce(
+            r1cs,
+            &[
+                Term::Product(1, &lambda, &lhs.x),
+                Term::Product(-1, &lambda, &x),
+                Term::Linear(-1, &lhs.y),
+            ],
+        );
+        PointVar { x, y }
+    }
+
+    /// Doubling. The curves have odd order, so `y` is never zero.
+    fn double(&self, r1cs: &mut R1CSBuilder, point: &PointVar) -> PointVar {
+        let base = &self.base;
+        let lambda = base.divide(
+            r1cs,
+            &[
+                Term::Product(3, &point.x, &point.x),
+                Term::Constant(&self.curve.a),
+            ],
+            &[(2, &point.y)],
+        );
+        let x = base.reduce(
+            r1cs,
+            &[
+                Term::Product(1, &lambda, &lambda),
+                Term::Linear(-2, &point.x),
+            ],
+        );
+        let y = base.reduce(
+            r1cs,
+            &[
+                Term::Product(1, &lambda, &point.x),
+                Term::Product(-1, &lambda, &x),
+                Term::Linear(-1, &point.y),
+            ],
+        );
+        PointVar { x, y }
+    }
+
+    fn select(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        bit: &LinearCombination,
+        a: &PointVar,
+        b: &PointVar,
+    ) -> PointVar {
+        PointVar {
+            x: self.base.select(r1cs, bit, &a.x, &b.x),
+            y: self.base.select(r1cs, bit, &a.y, &b.y),
+        }
+    }
+
+    /// `u1 G + u2 Q` by a joint double-and-add ladder. Every step adds `T +
+    /// b1 G + b2 Q` from a four entry table, so the result is offset by
+    /// `(2^(SCALAR_BITS + 1) - 1) T`, which is subtracted at the end.
+    fn double_scalar_mul(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        u1: &Element,
+        u2: &Element,
+        point: &PointVar,
+    ) -> PointVar {
+        let curve = self.curve;
+        let offset = &curve.offset;
+        let generator_offset = curve
+            .add(Some(&curve.generator), Some(offset))
+            .expect("Offset must not be the negated generator");
+
+        // Table indexed by (b1, b2)
+        let t00 = self.constant(offset);
+        let t10 = self.constant(&generator_offset);
+        let t01 = self.add(r1cs, &t00, point);
+        let t11 = self.add(r1cs, &t10, point);
+
+        let u1_bits = self.scalar.to_bits(r1cs, u1);
+        let u2_bits = self.scalar.to_bits(r1cs, u2);
+        let mut accumulator = t00.clone();
+        for i in (0..SCALAR_BITS).rev() {
+            accumulator = self.double(r1cs, &accumulator);
+            let b2_zero = self.select(r1cs, &u1_bits[i], &t10, &t00);
+            let b2_one = self.select(r1cs, &u1_bits[i], &t11, &t01);
+            let entry = self.select(r1cs, &u2_bits[i], &b2_one, &b2_zero);
+            accumulator = self.add(r1cs, &accumulator, &entry);
+        }
+
+        let multiple = (BigUint::one() << (SCALAR_BITS + 1)) - 1u32;
+        let correction = curve
+            .mul(&multiple, offset)
+            .expect("Offset must have order n");
+        let correction = self.constant(&curve.neg(&correction));
+        self.add(r1cs, &accumulator, &correction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FieldElement;
+
+    /// Sign `hash` with private key `d` and nonce `k`, returning the public key
+    /// and a low-s signature.
+    fn sign(curve: &Curve, d: &BigUint, k: &BigUint, hash: &BigUint) -> (Point, BigUint, BigUint) {
+        let n = &curve.n;
+        let public_key = curve.mul(d, &curve.generator).unwrap();
+        let r = curve.mul(k, &curve.generator).unwrap().x % n;
+        let k_inverse = k.modpow(&(n - 2u32), n);
+        let mut s = k_inverse * (hash + &r * d) % n;
+        if s > n >> 1 {
+            s = n - s;
+        }
+        (public_key, r, s)
+    }
+
+    fn to_bytes(value: &BigUint) -> Vec<u8> {
+        let bytes = value.to_bytes_be();
+        let mut padded = vec![0; 32 - bytes.len()];
+        padded.extend(bytes);
+        padded
+    }
+
+    #[test]
+    fn test_curves() {
+        for curve in [&*SECP256K1, &*SECP256R1] {
+            assert!(curve.is_on_curve(&curve.offset));
+            assert_eq!(curve.mul(&curve.n, &curve.generator), None);
+        }
+    }
+
+    /// Verify signatures over `curve` and check the returned validity bit.
+    fn check_verification(curve: &Curve) {
+        let d = BigUint::from(0x1234_5678_9abc_def0u64) << 100u32;
+        let k = BigUint::from(0xfedc_ba98_7654_3210u64) << 120u32;
+        let hash = parse_hex("9834876dcfb05cb167a5c24953eba58c4ac89b1adf57f28f2f9d09af107ee8f0");
+        let (public_key, r, s) = sign(curve, &d, &k, &hash);
+
+        let mut r1cs = R1CSBuilder::new();
+        let one = r1cs.new_witness();
+        let mut new_bytes = |count: usize| -> Vec<usize> {
+            (0..count).map(|_| r1cs.new_witness()).collect::<Vec<_>>()
+        };
+        let inputs = [new_bytes(32), new_bytes(32), new_bytes(64), new_bytes(32)];
+        let lcs = inputs
+            .iter()
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .map(|&byte| vec![(FieldElement::one(), byte)])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let valid = add_verification(&mut r1cs, one, curve, &lcs[0], &lcs[1], &lcs[2], &lcs[3]);
+        let r1cs = r1cs.build();
+
+        let verify = |key: &Point, r: &BigUint, s: &BigUint, hash: &BigUint| {
+            let mut signature = to_bytes(r);
+            signature.extend(to_bytes(s));
+            let values = [
+                to_bytes(&key.x),
+                to_bytes(&key.y),
+                signature,
+                to_bytes(hash),
+            ];
+            let mut witness = vec![None; r1cs.witnesses];
+            witness[one] = Some(FieldElement::one());
+            for (bytes, values) in inputs.iter().zip(values.iter()) {
+                for (&byte, &value) in bytes.iter().zip(values.iter()) {
+                    witness[byte] = Some(FieldElement::from(value));
+                }
+            }
+            r1cs.solve_witness(&mut witness).unwrap();
+            let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+            r1cs.verify_witness(&witness).unwrap();
+            valid
+                .iter()
+                .fold(FieldElement::zero(), |acc, &(c, w)| acc + c * witness[w])
+        };
+        let zero = BigUint::zero();
+        let off_curve = Point {
+            x: public_key.x.clone(),
+            y: &public_key.y + 1u32,
+        };
+        assert_eq!(verify(&public_key, &r, &s, &hash), FieldElement::one());
+        assert_eq!(
+            verify(&public_key, &r, &s, &(&hash + 1u32)),
+            FieldElement::zero()
+        );
+        assert_eq!(
+            verify(&public_key, &r, &(&curve.n - &s), &hash),
+            FieldElement::zero()
+        );
+        assert_eq!(verify(&public_key, &zero, &s, &hash), FieldElement::zero());
+        assert_eq!(verify(&off_curve, &r, &s, &hash), FieldElement::zero());
+    }
+
+    #[test]
+    fn test_verification_secp256k1() {
+        check_verification(&SECP256K1);
+    }
+
+    #[test]
+    fn test_verification_secp256r1() {
+        check_verification(&SECP256R1);
+    }
+}
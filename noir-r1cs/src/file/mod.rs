@@ -15,7 +15,7 @@ use self::{
     counting_writer::CountingWriter,
     json::{read_json, write_json},
 };
-use crate::{noir_proof_scheme::NoirProof, NoirProofScheme};
+use crate::{noir_proof_scheme::NoirProof, NoirProofScheme, R1CSTriplets};
 
 /// Trait for structures that can be serialized to and deserialized from files.
 pub trait FileFormat: Serialize + for<'a> Deserialize<'a> {
@@ -27,7 +27,7 @@ pub trait FileFormat: Serialize + for<'a> Deserialize<'a> {
 impl FileFormat for NoirProofScheme {
     const FORMAT: [u8; 8] = *b"NrProScm";
     const EXTENSION: &'static str = "nps";
-    const VERSION: (u16, u16) = (0, 0);
+    const VERSION: (u16, u16) = (0, 1);
 }
 
 impl FileFormat for NoirProof {
@@ -36,6 +36,12 @@ impl FileFormat for NoirProof {
     const VERSION: (u16, u16) = (0, 0);
 }
 
+impl FileFormat for R1CSTriplets {
+    const FORMAT: [u8; 8] = *b"R1CSTrip";
+    const EXTENSION: &'static str = "r1cst";
+    const VERSION: (u16, u16) = (0, 0);
+}
+
 /// Write a file with format determined from extension.
 #[instrument(skip(value))]
 pub fn write<T: FileFormat>(value: &T, path: &PathBuf) -> Result<()> {
@@ -0,0 +1,293 @@
+//! Grumpkin curve operations for the embedded-curve black boxes.
+//!
+//! Grumpkin is `y^2 = x^3 - 17` over BN254 `Fr`, so coordinates are native
+//! witnesses and a complete addition takes about twenty constraints. Points
+//! carry an explicit infinity flag as in ACIR. The point at infinity is output
+//! as `(0, 0, 1)`.
+
+use ark_std::One;
+
+use crate::{
+    bitwise::{add_inverse, add_is_zero, add_product, scale},
+    range_check, FieldElement, LinearCombination, R1CSBuilder,
+};
+
+/// Bits of each of the two limbs of a scalar.
+const SCALAR_LIMB_BITS: u32 = 128;
+
+/// In-circuit Grumpkin point.
+#[derive(Debug, Clone)]
+pub(crate) struct Point {
+    pub x: LinearCombination,
+    pub y: LinearCombination,
+    pub is_infinite: LinearCombination,
+}
+
+impl Point {
+    fn infinity(witness_one: usize) -> Self {
+        Self {
+            x: vec![],
+            y: vec![],
+            is_infinite: vec![(FieldElement::one(), witness_one)],
+        }
+    }
+}
+
+/// Linear combination `Σ c_i lc_i`.
+fn combine(terms: &[(i64, &LinearCombination)]) -> LinearCombination {
+    terms
+        .iter()
+        .flat_map(|&(c, lc)| scale(lc, FieldElement::from(c)))
+        .collect()
+}
+
+/// Constrain the infinity flag to be boolean and a finite point to be on the
+/// curve.
+pub(crate) fn add_on_curve_check(r1cs: &mut R1CSBuilder, witness_one: usize, point: &Point) {
+    let one = vec![(FieldElement::one(), witness_one)];
+    let flag = &point.is_infinite;
+    let finite = combine(&[(1, &one), (-1, flag)]);
+    r1cs.add_constraint(flag, &finite, &[]);
+
+    let y_squared = add_product(r1cs, witness_one, &point.y, &point.y);
+    let x_squared = add_product(r1cs, witness_one, &point.x, &point.x);
+    let x_cubed = add_product(r1cs, witness_one, &x_squared, &point.x);
+    let equation = combine(&[(1, &y_squared), (-1, &x_cubed), (17, &one)]);
+    r1cs.add_constraint(&finite, &equation, &[]);
+}
+
+/// Complete addition of two points with boolean infinity flags whose finite
+/// points are on the curve.
+pub(crate) fn add_addition(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    lhs: &Point,
+    rhs: &Point,
+) -> Point {
+    let one = vec![(FieldElement::one(), witness_one)];
+    let mul = |r1cs: &mut R1CSBuilder, a: &LinearCombination, b: &LinearCombination| {
+        add_product(r1cs, witness_one, a, b)
+    };
+    let (x1, y1, i1) = (&lhs.x, &lhs.y, &lhs.is_infinite);
+    let (x2, y2, i2) = (&rhs.x, &rhs.y, &rhs.is_infinite);
+
+    // Neither point is infinite
+    let both_infinite = mul(r1cs, i1, i2);
+    let finite = combine(&[(1, &one), (-1, i1), (-1, i2), (1, &both_infinite)]);
+
+    // For finite points on the curve, equal x coordinates mean the points are
+    // equal or opposite.
+    let dx = combine(&[(1, x2), (-1, x1)]);
+    let dy = combine(&[(1, y2), (-1, y1)]);
+    let same_x = add_is_zero(r1cs, witness_one, &dx);
+    let opposite_y = add_is_zero(r1cs, witness_one, &combine(&[(1, y1), (1, y2)]));
+
+    // Slope of the chord, or of the tangent if the x coordinates are equal:
+    // λ = (dy + s (3 x1^2 - dy)) / (dx + 2 s y1). Grumpkin has no points of
+    // order two, so the denominator is non-zero for finite points.
+    let x1_squared = mul(r1cs, x1, x1);
+    let s_y1 = mul(r1cs, &same_x, y1);
+    let denominator = combine(&[(1, &dx), (2, &s_y1)]);
+    let tangent = combine(&[(3, &x1_squared), (-1, &dy)]);
+    let s_tangent = mul(r1cs, &same_x, &tangent);
+    let numerator = combine(&[(1, &dy), (1, &s_tangent)]);
+    // Masking the numerator keeps infinite inputs satisfiable with λ = 0.
+    let numerator = mul(r1cs, &finite, &numerator);
+    let inverse = add_inverse(r1cs, witness_one, &denominator);
+    let lambda = mul(r1cs, &numerator, &inverse);
+    r1cs.add_constraint(&denominator, &lambda, &numerator);
+
+    let lambda_squared = mul(r1cs, &lambda, &lambda);
+    let x3 = combine(&[(1, &lambda_squared), (-1, x1), (-1, x2)]);
+    let lambda_dx = mul(r1cs, &lambda, &combine(&[(1, x1), (-1, &x3)]));
+    let y3 = combine(&[(1, &lambda_dx), (-1, y1)]);
+
+    // Select between the sum, either input and infinity.
+    let cancels = mul(r1cs, &same_x, &opposite_y);
+    let generic = mul(r1cs, &finite, &combine(&[(1, &one), (-1, &cancels)]));
+    let only_lhs_infinite = combine(&[(1, i1), (-1, &both_infinite)]);
+    let only_rhs_infinite = combine(&[(1, i2), (-1, &both_infinite)]);
+    let mut select = |sum: &LinearCombination, a: &LinearCombination, b: &LinearCombination| {
+        let mut output = mul(r1cs, &generic, sum);
+        output.extend(mul(r1cs, &only_lhs_infinite, b));
+        output.extend(mul(r1cs, &only_rhs_infinite, a));
+        output
+    };
+    let x = select(&x3, x1, x2);
+    let y = select(&y3, y1, y2);
+    let is_infinite = combine(&[(1, &both_infinite), (1, &finite), (-1, &generic)]);
+    Point { x, y, is_infinite }
+}
+
+/// `Σ (lo_i + 2^128 hi_i) P_i` for points with boolean infinity flags whose
+/// finite points are on the curve. Scalar limbs are range checked to 128 bits.
+pub(crate) fn add_multi_scalar_mul(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    terms: &[(Point, LinearCombination, LinearCombination)],
+) -> Point {
+    terms
+        .iter()
+        .fold(Point::infinity(witness_one), |sum, (point, lo, hi)| {
+            let mut bits = range_check::decompose(r1cs, witness_one, lo, SCALAR_LIMB_BITS, 1);
+            bits.extend(range_check::decompose(
+                r1cs,
+                witness_one,
+                hi,
+                SCALAR_LIMB_BITS,
+                1,
+            ));
+            let product = add_scalar_mul(r1cs, witness_one, point, &bits);
+            add_addition(r1cs, witness_one, &sum, &product)
+        })
+}
+
+/// Double-and-add over little-endian boolean `bits`.
+fn add_scalar_mul(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    point: &Point,
+    bits: &[usize],
+) -> Point {
+    let one = vec![(FieldElement::one(), witness_one)];
+    let finite = combine(&[(1, &one), (-1, &point.is_infinite)]);
+    bits.iter()
+        .rev()
+        .fold(Point::infinity(witness_one), |accumulator, &bit| {
+            let doubled = add_addition(r1cs, witness_one, &accumulator, &accumulator);
+            // The point if the bit is set, infinity otherwise
+            let bit = vec![(FieldElement::one(), bit)];
+            let selected = add_product(r1cs, witness_one, &bit, &finite);
+            let addend = Point {
+                x: point.x.clone(),
+                y: point.y.clone(),
+                is_infinite: combine(&[(1, &one), (-1, &selected)]),
+            };
+            add_addition(r1cs, witness_one, &doubled, &addend)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::AcirField;
+    use ark_std::Zero;
+    use bn254_blackbox_solver::{embedded_curve_add, multi_scalar_mul};
+
+    use super::*;
+    use crate::{utils::noir_to_native, NoirElement};
+
+    const GENERATOR_Y: &str = "17631683881184975370165255887551781615748388533673675138860";
+
+    /// Allocate witnesses for a point and return them with their values.
+    fn new_point(
+        r1cs: &mut R1CSBuilder,
+        witness: &mut Vec<(usize, NoirElement)>,
+        value: [NoirElement; 3],
+    ) -> Point {
+        let mut coordinate = |value: NoirElement| {
+            let w = r1cs.new_witness();
+            witness.push((w, value));
+            vec![(FieldElement::one(), w)]
+        };
+        Point {
+            x: coordinate(value[0]),
+            y: coordinate(value[1]),
+            is_infinite: coordinate(value[2]),
+        }
+    }
+
+    fn solve(r1cs: R1CSBuilder, assignments: &[(usize, NoirElement)]) -> Vec<FieldElement> {
+        let r1cs = r1cs.build();
+        let mut witness = vec![None; r1cs.witnesses];
+        witness[0] = Some(FieldElement::one());
+        for &(w, value) in assignments {
+            witness[w] = Some(noir_to_native(value));
+        }
+        r1cs.solve_witness(&mut witness).unwrap();
+        let witness = witness
+            .into_iter()
+            .map(|w| w.unwrap_or_default())
+            .collect::<Vec<_>>();
+        r1cs.verify_witness(&witness).unwrap();
+        witness
+    }
+
+    fn evaluate(lc: &LinearCombination, witness: &[FieldElement]) -> FieldElement {
+        lc.iter()
+            .fold(FieldElement::zero(), |acc, &(c, w)| acc + c * witness[w])
+    }
+
+    #[test]
+    fn test_addition() {
+        let generator = [
+            NoirElement::one(),
+            NoirElement::try_from_str(GENERATOR_Y).unwrap(),
+            NoirElement::zero(),
+        ];
+        let doubled = embedded_curve_add(generator, generator).unwrap();
+        let doubled = [doubled.0, doubled.1, doubled.2];
+        let negated = [generator[0], -generator[1], generator[2]];
+        let infinity = [NoirElement::zero(), NoirElement::zero(), NoirElement::one()];
+
+        for (a, b) in [
+            (generator, doubled),
+            (generator, generator),
+            (generator, negated),
+            (generator, infinity),
+            (infinity, doubled),
+            (infinity, infinity),
+        ] {
+            let mut r1cs = R1CSBuilder::new();
+            let one = r1cs.new_witness();
+            let mut assignments = vec![];
+            let lhs = new_point(&mut r1cs, &mut assignments, a);
+            let rhs = new_point(&mut r1cs, &mut assignments, b);
+            add_on_curve_check(&mut r1cs, one, &lhs);
+            add_on_curve_check(&mut r1cs, one, &rhs);
+            let sum = add_addition(&mut r1cs, one, &lhs, &rhs);
+            let witness = solve(r1cs, &assignments);
+
+            let expected = embedded_curve_add(a, b).unwrap();
+            assert_eq!(evaluate(&sum.x, &witness), noir_to_native(expected.0));
+            assert_eq!(evaluate(&sum.y, &witness), noir_to_native(expected.1));
+            assert_eq!(
+                evaluate(&sum.is_infinite, &witness),
+                noir_to_native(expected.2)
+            );
+        }
+    }
+
+    #[test]
+    fn test_multi_scalar_mul() {
+        let generator = [
+            NoirElement::one(),
+            NoirElement::try_from_str(GENERATOR_Y).unwrap(),
+            NoirElement::zero(),
+        ];
+        let scalars = [
+            NoirElement::from(0x1234_5678_9abc_def0_u128 << 60),
+            NoirElement::from(0xfedc_ba98_u128),
+        ];
+
+        let mut r1cs = R1CSBuilder::new();
+        let one = r1cs.new_witness();
+        let mut assignments = vec![];
+        let point = new_point(&mut r1cs, &mut assignments, generator);
+        let [lo, hi] = scalars.map(|scalar| {
+            let w = r1cs.new_witness();
+            assignments.push((w, scalar));
+            vec![(FieldElement::one(), w)]
+        });
+        add_on_curve_check(&mut r1cs, one, &point);
+        let product = add_multi_scalar_mul(&mut r1cs, one, &[(point, lo, hi)]);
+        let witness = solve(r1cs, &assignments);
+
+        let expected = multi_scalar_mul(&generator, &scalars[..1], &scalars[1..]).unwrap();
+        assert_eq!(evaluate(&product.x, &witness), noir_to_native(expected.0));
+        assert_eq!(evaluate(&product.y, &witness), noir_to_native(expected.1));
+        assert_eq!(
+            evaluate(&product.is_infinite, &witness),
+            noir_to_native(expected.2)
+        );
+    }
+}
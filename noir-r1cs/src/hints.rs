@@ -0,0 +1,380 @@
+use anyhow::{anyhow, ensure, Result};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::{One, Zero};
+use num_bigint::{BigInt, BigUint, Sign};
+use serde::{Deserialize, Serialize};
+
+use crate::{poseidon2, utils::serde_ark, FieldElement};
+
+/// A sparse linear combination of witnesses.
+pub type LinearCombination = Vec<(FieldElement, usize)>;
+
+/// Witness computations that can not be derived by solving the constraints
+/// one row at a time, e.g. because they involve a non-linear inverse.
+///
+/// Hints are attached to a constraint row and are executed by the solver right
+/// before that row is solved. The constraints themselves are responsible for
+/// enforcing the hinted values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Hint {
+    /// Sets `outputs[i]` to one if `predicate` is non-zero and `index == i`,
+    /// and to zero otherwise.
+    OneHot {
+        #[serde(with = "serde_ark")]
+        predicate: LinearCombination,
+        #[serde(with = "serde_ark")]
+        index: LinearCombination,
+        outputs: Vec<usize>,
+    },
+
+    /// Sets `outputs` to the little-endian base `2^log_base` digits of `value`.
+    Digits {
+        #[serde(with = "serde_ark")]
+        value: LinearCombination,
+        log_base: u32,
+        outputs: Vec<usize>,
+    },
+
+    /// Sets `outputs[j]` to the number of `values` equal to `j`.
+    Multiplicities {
+        #[serde(with = "serde_ark")]
+        values: Vec<LinearCombination>,
+        outputs: Vec<usize>,
+    },
+
+    /// Sets `output` to the inverse of `value`, or zero if `value` is zero.
+    Inverse {
+        #[serde(with = "serde_ark")]
+        value: LinearCombination,
+        output: usize,
+    },
+
+    /// Divides the integer `Σ value[k] 2^(limb_bits k)` by `modulus`, with
+    /// every position interpreted as a signed integer. Sets `quotient` and
+    /// `remainder` to the limbs of the result. An empty `remainder` requires
+    /// the division to be exact.
+    ModularReduction {
+        #[serde(with = "serde_ark")]
+        value: Vec<LinearCombination>,
+        limb_bits: u32,
+        /// Little-endian 64-bit digits of the modulus.
+        modulus: Vec<u64>,
+        quotient: Vec<usize>,
+        remainder: Vec<usize>,
+    },
+
+    /// Sets `outputs` to the limbs of `numerator / denominator` modulo the
+    /// prime `modulus`, with operands given as in `ModularReduction`.
+    ModularDivision {
+        #[serde(with = "serde_ark")]
+        numerator: Vec<LinearCombination>,
+        #[serde(with = "serde_ark")]
+        denominator: Vec<LinearCombination>,
+        limb_bits: u32,
+        /// Little-endian 64-bit digits of the modulus.
+        modulus: Vec<u64>,
+        outputs: Vec<usize>,
+    },
+
+    /// Runs the Poseidon2 permutation on `inputs`. Sets `trace` to the S-box
+    /// intermediates and `outputs` to the permuted state.
+    Poseidon2 {
+        #[serde(with = "serde_ark")]
+        inputs: Vec<LinearCombination>,
+        trace: Vec<usize>,
+        outputs: Vec<usize>,
+    },
+
+    /// Computes the carries `c[k]` of `Σ value[k] 2^(limb_bits k) = 0`, i.e.
+    /// `value[k] + c[k - 1] = c[k] 2^limb_bits`. Sets `outputs[k]` to `c[k] +
+    /// 2^(carry_bits - 1)`.
+    Carries {
+        #[serde(with = "serde_ark")]
+        value: Vec<LinearCombination>,
+        limb_bits: u32,
+        carry_bits: u32,
+        outputs: Vec<usize>,
+    },
+}
+
+impl Hint {
+    /// The linear combinations the hint reads.
+    pub(crate) fn inputs_mut(&mut self) -> Vec<&mut LinearCombination> {
+        match self {
+            Hint::OneHot {
+                predicate, index, ..
+            } => vec![predicate, index],
+            Hint::Digits { value, .. } | Hint::Inverse { value, .. } => vec![value],
+            Hint::Multiplicities { values: value, .. }
+            | Hint::Poseidon2 { inputs: value, .. }
+            | Hint::ModularReduction { value, .. }
+            | Hint::Carries { value, .. } => value.iter_mut().collect(),
+            Hint::ModularDivision {
+                numerator,
+                denominator,
+                ..
+            } => numerator.iter_mut().chain(denominator.iter_mut()).collect(),
+        }
+    }
+
+    /// The witnesses the hint assigns.
+    pub(crate) fn outputs_mut(&mut self) -> Vec<&mut usize> {
+        match self {
+            Hint::OneHot { outputs, .. }
+            | Hint::Digits { outputs, .. }
+            | Hint::Multiplicities { outputs, .. }
+            | Hint::ModularDivision { outputs, .. }
+            | Hint::Carries { outputs, .. } => outputs.iter_mut().collect(),
+            Hint::Inverse { output, .. } => vec![output],
+            Hint::ModularReduction {
+                quotient,
+                remainder,
+                ..
+            } => quotient.iter_mut().chain(remainder.iter_mut()).collect(),
+            Hint::Poseidon2 { trace, outputs, .. } => {
+                trace.iter_mut().chain(outputs.iter_mut()).collect()
+            }
+        }
+    }
+
+    /// Compute the hinted witness values.
+    pub fn solve(&self, witness: &mut [Option<FieldElement>]) -> Result<()> {
+        match self {
+            Hint::OneHot {
+                predicate,
+                index,
+                outputs,
+            } => {
+                let enabled = !evaluate(predicate, witness)?.is_zero();
+                let index = evaluate(index, witness)?;
+                let selected = if enabled {
+                    let selected =
+                        to_usize(index)
+                            .filter(|&i| i < outputs.len())
+                            .ok_or_else(|| {
+                                anyhow!("Index {index} out of bounds for length {}", outputs.len())
+                            })?;
+                    Some(selected)
+                } else {
+                    None
+                };
+                for (i, &output) in outputs.iter().enumerate() {
+                    let value = if Some(i) == selected {
+                        FieldElement::one()
+                    } else {
+                        FieldElement::zero()
+                    };
+                    assign(witness, output, value)?;
+                }
+            }
+            Hint::Digits {
+                value,
+                log_base,
+                outputs,
+            } => {
+                let value = evaluate(value, witness)?;
+                let bigint = value.into_bigint();
+                let num_bits = log_base * outputs.len() as u32;
+                ensure!(
+                    bigint.num_bits() <= num_bits,
+                    "Value {value} does not fit in {num_bits} bits"
+                );
+                for (i, &output) in outputs.iter().enumerate() {
+                    let digit = (0..*log_base)
+                        .filter(|&bit| bigint.get_bit((i as u32 * log_base + bit) as usize))
+                        .fold(0u64, |digit, bit| digit | (1 << bit));
+                    assign(witness, output, FieldElement::from(digit))?;
+                }
+            }
+            Hint::Multiplicities { values, outputs } => {
+                let mut counts = vec![0u64; outputs.len()];
+                for value in values {
+                    let value = evaluate(value, witness)?;
+                    let entry =
+                        to_usize(value)
+                            .filter(|&j| j < outputs.len())
+                            .ok_or_else(|| {
+                                anyhow!("Value {value} is not in table of size {}", outputs.len())
+                            })?;
+                    counts[entry] += 1;
+                }
+                for (&output, count) in outputs.iter().zip(counts) {
+                    assign(witness, output, FieldElement::from(count))?;
+                }
+            }
+            Hint::Inverse { value, output } => {
+                let value = evaluate(value, witness)?;
+                let inverse = value.inverse().unwrap_or_default();
+                assign(witness, *output, inverse)?;
+            }
+            Hint::ModularReduction {
+                value,
+                limb_bits,
+                modulus,
+                quotient,
+                remainder,
+            } => {
+                let value = evaluate_limbs(value, *limb_bits, witness)?;
+                let modulus = BigInt::from(from_u64_digits(modulus));
+                ensure!(value.sign() != Sign::Minus, "Negative value {value}");
+                let (q, r) = (&value / &modulus, &value % &modulus);
+                if remainder.is_empty() {
+                    ensure!(r.is_zero(), "Value {value} is not divisible by {modulus}");
+                }
+                assign_limbs(witness, quotient, &q, *limb_bits)?;
+                assign_limbs(witness, remainder, &r, *limb_bits)?;
+            }
+            Hint::ModularDivision {
+                numerator,
+                denominator,
+                limb_bits,
+                modulus,
+                outputs,
+            } => {
+                let modulus = BigInt::from(from_u64_digits(modulus));
+                let reduce = |limbs| -> Result<BigInt> {
+                    let value = evaluate_limbs(limbs, *limb_bits, witness)?;
+                    Ok(((value % &modulus) + &modulus) % &modulus)
+                };
+                let numerator = reduce(numerator)?;
+                let denominator = reduce(denominator)?;
+                ensure!(!denominator.is_zero(), "Division by zero modulo {modulus}");
+                let inverse = denominator.modpow(&(&modulus - 2), &modulus);
+                let value = numerator * inverse % &modulus;
+                assign_limbs(witness, outputs, &value, *limb_bits)?;
+            }
+            Hint::Poseidon2 {
+                inputs,
+                trace,
+                outputs,
+            } => {
+                let inputs = inputs
+                    .iter()
+                    .map(|input| evaluate(input, witness))
+                    .collect::<Result<Vec<_>>>()?;
+                let state = inputs
+                    .try_into()
+                    .map_err(|_| anyhow!("Poseidon2 state must have width {}", poseidon2::WIDTH))?;
+                let (values, permuted) = poseidon2::permutation_trace(&state);
+                ensure!(trace.len() == values.len(), "Poseidon2 trace size mismatch");
+                for (&output, value) in trace.iter().zip(values).chain(outputs.iter().zip(permuted))
+                {
+                    assign(witness, output, value)?;
+                }
+            }
+            Hint::Carries {
+                value,
+                limb_bits,
+                carry_bits,
+                outputs,
+            } => {
+                let offset = BigInt::one() << (carry_bits - 1);
+                let mut carry = BigInt::zero();
+                for (k, position) in value.iter().enumerate() {
+                    let total = to_signed(evaluate(position, witness)?) + &carry;
+                    carry = &total >> *limb_bits;
+                    ensure!(
+                        carry.clone() << *limb_bits == total,
+                        "Position {k} is not divisible by 2^{limb_bits}"
+                    );
+                    if let Some(&output) = outputs.get(k) {
+                        assign(witness, output, from_signed(&(&carry + &offset)))?;
+                    }
+                }
+                ensure!(carry.is_zero(), "Non-zero final carry {carry}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate a linear combination on a partial witness.
+fn evaluate(
+    lc: &[(FieldElement, usize)],
+    witness: &[Option<FieldElement>],
+) -> Result<FieldElement> {
+    lc.iter()
+        .try_fold(FieldElement::zero(), |acc, &(coeff, col)| {
+            let value =
+                witness[col].ok_or_else(|| anyhow!("Hint input witness {col} is unsolved"))?;
+            Ok(acc + coeff * value)
+        })
+}
+
+/// Evaluate `Σ limbs[k] 2^(limb_bits k)` with limbs as signed integers.
+fn evaluate_limbs(
+    limbs: &[LinearCombination],
+    limb_bits: u32,
+    witness: &[Option<FieldElement>],
+) -> Result<BigInt> {
+    limbs.iter().rev().try_fold(BigInt::zero(), |acc, limb| {
+        Ok((acc << limb_bits) + to_signed(evaluate(limb, witness)?))
+    })
+}
+
+/// Construct an integer from little-endian 64-bit digits.
+pub(crate) fn from_u64_digits(digits: &[u64]) -> BigUint {
+    digits
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, &digit| (acc << 64u32) + digit)
+}
+
+/// Interpret a field element as a signed integer in `(-p/2, p/2]`.
+fn to_signed(value: FieldElement) -> BigInt {
+    let value = BigUint::from(value);
+    let modulus = BigUint::from(FieldElement::MODULUS);
+    if value > &modulus >> 1 {
+        BigInt::from(value) - BigInt::from(modulus)
+    } else {
+        BigInt::from(value)
+    }
+}
+
+fn from_signed(value: &BigInt) -> FieldElement {
+    let magnitude = FieldElement::from(value.magnitude().clone());
+    if value.sign() == Sign::Minus {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Assign `value` to little-endian limbs of `limb_bits` bits.
+fn assign_limbs(
+    witness: &mut [Option<FieldElement>],
+    outputs: &[usize],
+    value: &BigInt,
+    limb_bits: u32,
+) -> Result<()> {
+    let mask = (BigInt::one() << limb_bits) - 1;
+    let mut rest = value.clone();
+    for &output in outputs {
+        assign(witness, output, from_signed(&(&rest & &mask)))?;
+        rest >>= limb_bits;
+    }
+    ensure!(
+        rest.is_zero(),
+        "Value {value} does not fit in {} limbs",
+        outputs.len()
+    );
+    Ok(())
+}
+
+/// Assign a witness value, checking it against any existing value.
+fn assign(witness: &mut [Option<FieldElement>], col: usize, value: FieldElement) -> Result<()> {
+    if let Some(existing) = witness[col] {
+        ensure!(existing == value, "Hint output witness {col} mismatch");
+    }
+    witness[col] = Some(value);
+    Ok(())
+}
+
+/// Convert a field element to a `usize` if it is small enough.
+fn to_usize(value: FieldElement) -> Option<usize> {
+    let bigint = value.into_bigint();
+    if bigint.num_bits() > usize::BITS {
+        return None;
+    }
+    Some(bigint.as_ref()[0] as usize)
+}
@@ -1,19 +1,18 @@
+use std::{collections::HashMap, io::Read, io::Write};
+
 use ark_ff::Field;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(
-    Debug,
-    Clone,
-    Default,
-    PartialEq,
-    Serialize,
-    Deserialize,
-    CanonicalSerialize,
-    CanonicalDeserialize,
-)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Interner<F: Field> {
     values: Vec<F>,
+    /// Position of each value in `values`, the first occurrence winning. Not
+    /// serialized, built on first use after deserialization.
+    #[serde(skip)]
+    indices: Option<HashMap<F, usize>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -21,19 +20,22 @@ pub struct InternedFieldElement(usize);
 
 impl<F: Field> Interner<F> {
     pub fn new(values: Vec<F>) -> Self {
-        Self { values }
+        Self {
+            indices: Some(build_index(&values)),
+            values,
+        }
     }
 
-    /// Interning is slow in favour of faster lookups.
+    /// Deduplicates through a hash map, so interning is amortized constant
+    /// time.
     pub fn intern(&mut self, value: F) -> InternedFieldElement {
-        // Deduplicate
-        if let Some(index) = self.values.iter().position(|v| *v == value) {
-            return InternedFieldElement(index);
-        }
-
-        // Insert
-        let index = self.values.len();
-        self.values.push(value);
+        let indices = self
+            .indices
+            .get_or_insert_with(|| build_index(&self.values));
+        let index = *indices.entry(value).or_insert_with(|| {
+            self.values.push(value);
+            self.values.len() - 1
+        });
         InternedFieldElement(index)
     }
 
@@ -45,3 +47,49 @@ impl<F: Field> Interner<F> {
         self.values.iter()
     }
 }
+
+/// Map each value to its position. The first occurrence of a value wins.
+fn build_index<F: Field>(values: &[F]) -> HashMap<F, usize> {
+    let mut indices = HashMap::with_capacity(values.len());
+    for (i, &value) in values.iter().enumerate() {
+        indices.entry(value).or_insert(i);
+    }
+    indices
+}
+
+impl<F: Field> PartialEq for Interner<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values
+    }
+}
+
+// Serializes as the bare value list, so the index stays out of the format.
+impl<F: Field> CanonicalSerialize for Interner<F> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.values.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.values.serialized_size(compress)
+    }
+}
+
+impl<F: Field> Valid for Interner<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.values.check()
+    }
+}
+
+impl<F: Field> CanonicalDeserialize for Interner<F> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Vec::deserialize_with_mode(reader, compress, validate).map(Self::new)
+    }
+}
@@ -1,23 +1,48 @@
 #![doc = include_str!("../README.md")]
 #![allow(missing_docs)]
+mod bitwise;
+mod circom;
+mod ecdsa;
 mod file;
 // mod gnark_config;
+mod grumpkin;
+mod hints;
 mod interner;
 mod noir_proof_scheme;
 mod noir_to_r1cs;
 mod noir_witness;
+mod non_native;
+mod optimize;
+mod poseidon2;
 mod r1cs;
+mod range_check;
+mod sha256;
 // mod skyscraper;
+mod source_map;
 mod sparse_matrix;
+mod triplets;
 mod utils;
 // mod whir_r1cs;
 
 pub use {
     crate::{
+        bitwise::{bitwise_cost, BitwiseOp},
+        circom::{read_circom_r1cs, read_circom_witness},
         file::{read, write, FileFormat},
+        hints::{Hint, LinearCombination},
         noir_proof_scheme::{NoirProof, NoirProofScheme},
-        noir_to_r1cs::noir_to_r1cs,
-        r1cs::R1CS,
+        noir_to_r1cs::{
+            noir_program_to_r1cs, noir_program_to_r1cs_with_range_checks,
+            noir_program_to_r1cs_with_source_map, noir_to_r1cs, noir_to_r1cs_with_range_checks,
+            CallWitnessMap,
+        },
+        optimize::optimize_r1cs,
+        poseidon2::{permutation as poseidon2_permutation, poseidon2_cost},
+        r1cs::{r1cs_digest, ConstraintError, Digest, R1CSBuilder, R1CS},
+        range_check::{range_check_cost, RangeCheckStrategy, MAX_LOG_BASE},
+        sha256::sha256_compression_cost,
+        source_map::{OpcodeOrigin, SourceLocation, SourceMap},
+        triplets::{R1CSTriplets, Triplet},
         utils::{human, serde_ark},
     },
     acir::FieldElement as NoirElement,
@@ -26,9 +51,13 @@ pub use {
     // ark_bn254::Fr as FieldElement,
     ark_bn254::Fr as FieldElement,
     interner::Interner,
+    noirc_abi::{
+        input_parser::{Format as InputFormat, InputValue},
+        InputMap,
+    },
     sparse_matrix::HydratedSparseMatrix,
     // whir_r1cs::create_io_pattern,
-    sparse_matrix::SparseMatrix,
+    sparse_matrix::{matrix_vector_products, SparseMatrix, SparseMatrixBuilder},
 };
 
 use crate::{
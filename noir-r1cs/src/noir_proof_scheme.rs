@@ -1,18 +1,22 @@
 use {
     crate::{
-        noir_to_r1cs,
+        noir_program_to_r1cs_with_source_map,
+        optimize_r1cs,
         utils::PrintAbi,
         // whir_r1cs::{WhirR1CSProof, WhirR1CSScheme},
+        ConstraintError,
+        Digest,
         FieldElement,
         NoirWitnessGenerator,
+        RangeCheckStrategy,
+        SourceMap,
         R1CS,
     },
-    anyhow::{anyhow, ensure, Context as _, Result},
-    ark_ff::Field as _,
+    anyhow::{ensure, Context as _, Error, Result},
+    noirc_abi::{input_parser::Format, InputMap},
     noirc_artifacts::program::ProgramArtifact,
-    rand::{thread_rng, Rng as _},
     serde::{Deserialize, Serialize},
-    std::{fs::File, io::Read, path::Path},
+    std::{ffi::OsStr, fs::File, io::Read, path::Path},
     tracing::{info, instrument, span, Level},
 };
 
@@ -20,7 +24,12 @@ use {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NoirProofScheme {
     pub r1cs: R1CS,
+    /// Digest of the R1CS, see [`R1CS::digest`] and [`Self::check_digest`].
+    pub r1cs_digest: Digest,
     pub witness_generator: NoirWitnessGenerator,
+    /// Origin of each constraint, for diagnostics. Optional as it can be large.
+    #[serde(default)]
+    pub source_map: Option<SourceMap>,
     // pub whir:              WhirR1CSScheme,
 }
 
@@ -30,8 +39,16 @@ pub struct NoirProof {
 }
 
 impl NoirProofScheme {
-    #[instrument(fields(size = path.metadata().map(|m| m.len()).ok()))]
     pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_file_with_range_checks(path, RangeCheckStrategy::default())
+    }
+
+    /// Compile a Noir program file using the given strategy for range checks.
+    #[instrument(fields(size = path.metadata().map(|m| m.len()).ok()))]
+    pub fn from_file_with_range_checks(
+        path: &Path,
+        range_check_strategy: RangeCheckStrategy,
+    ) -> Result<Self> {
         let program = {
             let file = File::open(path).context("while opening Noir program")?;
             let _span = span!(
@@ -42,28 +59,37 @@ impl NoirProofScheme {
             .entered();
             serde_json::from_reader(file).context("while reading Noir program")?
         };
-        Self::from_program(&program)
+        Self::from_program_with_range_checks(&program, range_check_strategy)
     }
 
-    #[instrument(skip_all)]
     pub fn from_program(program: &ProgramArtifact) -> Result<Self> {
+        Self::from_program_with_range_checks(program, RangeCheckStrategy::default())
+    }
+
+    /// Compile a Noir program using the given strategy for range checks.
+    #[instrument(skip_all)]
+    pub fn from_program_with_range_checks(
+        program: &ProgramArtifact,
+        range_check_strategy: RangeCheckStrategy,
+    ) -> Result<Self> {
         info!("Program noir version: {}", program.noir_version);
         info!("Program entry point: fn main{};", PrintAbi(&program.abi));
-        ensure!(
-            program.bytecode.functions.len() == 1,
-            "Program must have one entry point."
-        );
 
         // Extract bits from Program Artifact.
-        let main = &program.bytecode.functions[0];
-        info!(
-            "ACIR: {} witnesses, {} opcodes.",
-            main.current_witness_index,
-            main.opcodes.len()
-        );
+        let functions = &program.bytecode.functions;
+        for (i, function) in functions.iter().enumerate() {
+            info!(
+                "ACIR function {i}: {} witnesses, {} opcodes.",
+                function.current_witness_index,
+                function.opcodes.len()
+            );
+        }
 
-        // Compile to R1CS schemes
-        let (r1cs, witness_map) = noir_to_r1cs(&main)?;
+        // Compile to R1CS schemes, inlining calls into the entry point
+        let (r1cs, mut witness_maps, mut source_map) =
+            noir_program_to_r1cs_with_source_map(functions, range_check_strategy)?;
+        let r1cs = optimize_r1cs(&r1cs, &mut witness_maps, Some(&mut source_map));
+        source_map.add_debug_symbols(program);
         info!(
             "R1CS {} constraints, {} witnesses, A {} entries, B {} entries, C {} entries",
             r1cs.constraints,
@@ -74,47 +100,83 @@ impl NoirProofScheme {
         );
 
         // Configure witness generator
-        let witness_generator = NoirWitnessGenerator::new(&program, witness_map, r1cs.witnesses);
+        let witness_generator = NoirWitnessGenerator::new(&program, witness_maps, r1cs.witnesses);
 
         Ok(Self {
+            r1cs_digest: r1cs.digest(),
             r1cs,
             witness_generator,
+            source_map: Some(source_map),
         })
     }
 
+    /// Check the stored digest against the R1CS, e.g. after reading the
+    /// scheme from a file.
+    pub fn check_digest(&self) -> Result<()> {
+        let digest = self.r1cs.digest();
+        ensure!(
+            digest == self.r1cs_digest,
+            "R1CS digest {} does not match the stored digest {}",
+            hex::encode(digest),
+            hex::encode(self.r1cs_digest)
+        );
+        Ok(())
+    }
+
     pub fn size(&self) -> (usize, usize) {
         (self.r1cs.constraints, self.r1cs.witnesses)
     }
 
+    /// Solve the witness for inputs read from a file, in JSON if the extension
+    /// is `.json` and in TOML otherwise.
     #[instrument(skip_all)]
     pub fn solve_witness(&self, input_path: &Path) -> Result<Vec<FieldElement>> {
-        let span = span!(Level::INFO, "generate_witness").entered();
-
         let mut file = File::open(input_path).context("while opening input file")?;
-        let mut input_toml =
+        let mut input_str =
             String::with_capacity(file.metadata().map(|m| m.len() as usize).unwrap_or(0));
-        file.read_to_string(&mut input_toml)
+        file.read_to_string(&mut input_str)
             .context("while reading input file")?;
 
-        // Create partial witness
-        let mut partial_witness = vec![None; self.r1cs.witnesses];
-        partial_witness[0] = Some(FieldElement::ONE);
+        let format = match input_path.extension().and_then(OsStr::to_str) {
+            Some("json") => Format::Json,
+            _ => Format::Toml,
+        };
+        let input = self
+            .witness_generator
+            .parse_input(format, &input_str)
+            .context("while parsing input file")?;
+        self.solve_witness_for_input(input)
+    }
 
-        // Create witness for provided input
+    /// Solve the witness for inputs given as a JSON object.
+    pub fn solve_witness_for_json(&self, json: &str) -> Result<Vec<FieldElement>> {
+        let input = self.witness_generator.parse_input(Format::Json, json)?;
+        self.solve_witness_for_input(input)
+    }
+
+    /// Solve the witness for a map from parameter name to value.
+    #[instrument(skip_all)]
+    pub fn solve_witness_for_input(&self, input: InputMap) -> Result<Vec<FieldElement>> {
+        let span = span!(Level::INFO, "generate_witness").entered();
+
+        // Execute the ACIR program for the provided input
         let input = self
             .witness_generator
-            .input_from_toml(&input_toml)
-            .context("while reading input from toml")?;
-        for (i, value) in input.into_iter().enumerate() {
-            let j = self.witness_generator.witness_map()[i]
-                .ok_or_else(|| anyhow!("ACIR input witness index {i} unmapped"))?
-                .get() as usize;
-            partial_witness[j] = Some(value);
-        }
+            .input_from_map(input)
+            .context("while encoding input")?;
+        let mut partial_witness = self
+            .witness_generator
+            .generate_witness(&input, self.r1cs.witnesses)
+            .context("while executing ACIR program")?;
 
-        // Solve R1CS instance
+        // Solve the R1CS witnesses introduced by the compiler
         self.r1cs
             .solve_witness(&mut partial_witness)
+            .map_err(|error| {
+                self.diagnose(error, |row| {
+                    self.r1cs.display_partial_constraint(row, &partial_witness)
+                })
+            })
             .context("while solving R1CS witness")?;
         let witness = fill_witness(partial_witness).context("while filling witness")?;
         drop(span);
@@ -122,6 +184,33 @@ impl NoirProofScheme {
         Ok(witness)
     }
 
+    /// Check that a witness satisfies the R1CS, explaining a failing constraint.
+    pub fn verify_witness(&self, witness: &[FieldElement]) -> Result<()> {
+        self.r1cs.verify_witness(witness).map_err(|error| {
+            self.diagnose(error, |row| {
+                self.r1cs.display_constraint(row, Some(witness))
+            })
+        })
+    }
+
+    /// Add the failing constraint with its witness values and the opcode and
+    /// source location it came from to a [`ConstraintError`].
+    fn diagnose<D: std::fmt::Display>(
+        &self,
+        error: Error,
+        constraint: impl Fn(usize) -> D,
+    ) -> Error {
+        let Some(row) = error.downcast_ref::<ConstraintError>().map(|e| e.row()) else {
+            return error;
+        };
+        let origin = self.source_map.as_ref().and_then(|map| map.origin(row));
+        let origin = origin.map_or_else(
+            || "no ACIR opcode recorded".to_string(),
+            |origin| origin.to_string(),
+        );
+        error.context(format!("{}\n{origin}", constraint(row)))
+    }
+
     //     // Verify witness (redudant with solve)
     //     #[cfg(test)]
     //     self.r1cs
@@ -145,31 +234,41 @@ impl NoirProofScheme {
     // }
 }
 
-/// Complete a partial witness with random values.
+/// Complete a partial witness, failing if any value is unsolved.
 #[instrument(skip_all, fields(size = witness.len()))]
 fn fill_witness(witness: Vec<Option<FieldElement>>) -> Result<Vec<FieldElement>> {
-    // TODO: Use better entropy source and proper sampling.
-    let mut rng = thread_rng();
-    let mut count = 0;
-    let witness = witness
+    const MAX_LISTED: usize = 20;
+    let unsolved = witness
         .iter()
-        .map(|f| {
-            f.unwrap_or_else(|| {
-                count += 1;
-                FieldElement::from(rng.gen::<u128>())
-            })
-        })
+        .enumerate()
+        .filter_map(|(i, value)| value.is_none().then_some(i))
         .collect::<Vec<_>>();
-    info!("Filled witness with {count} random values");
-    Ok(witness)
+    ensure!(
+        unsolved.is_empty(),
+        "{} R1CS witnesses are unsolved: {:?}{}",
+        unsolved.len(),
+        &unsolved[..unsolved.len().min(MAX_LISTED)],
+        if unsolved.len() > MAX_LISTED {
+            ", ..."
+        } else {
+            ""
+        }
+    );
+    Ok(witness.into_iter().flatten().collect())
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
 
-    use super::NoirProofScheme;
-    use crate::test_serde;
+    use ark_std::One;
+    use noirc_abi::input_parser::Format;
+
+    use super::{fill_witness, NoirProofScheme};
+    use crate::{test_serde, ConstraintError, FieldElement};
 
     #[test]
     fn test_noir_proof_scheme_serde() {
@@ -179,5 +278,81 @@ mod tests {
         .unwrap();
         test_serde(&proof_schema.r1cs);
         test_serde(&proof_schema.witness_generator);
+        proof_schema.check_digest().unwrap();
+    }
+
+    /// Prepare, solve and verify the SHA-256 example, which uses both the
+    /// fixed and the variable size hash.
+    #[test]
+    #[ignore = "requires nargo compile in noir-examples/sha256"]
+    fn test_sha256_end_to_end() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("noir-examples/sha256");
+        let scheme = NoirProofScheme::from_file(&dir.join("target/sha256.json")).unwrap();
+        let input_path = dir.join("Prover.toml");
+        let witness = scheme.solve_witness(&input_path).unwrap();
+        scheme.verify_witness(&witness).unwrap();
+
+        // A wrong digest is rejected
+        let toml = fs::read_to_string(&input_path).unwrap();
+        let wrong = toml.replace("result = [124,", "result = [125,");
+        assert_ne!(wrong, toml);
+        let input = scheme
+            .witness_generator
+            .parse_input(Format::Toml, &wrong)
+            .unwrap();
+        assert!(scheme.solve_witness_for_input(input).is_err());
+    }
+
+    /// A failing constraint is explained with its witness values and the
+    /// opcode it was compiled from.
+    #[test]
+    #[ignore = "requires nargo compile in noir-examples/sha256"]
+    fn test_diagnose_failing_constraint() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("noir-examples/sha256");
+        let scheme = NoirProofScheme::from_file(&dir.join("target/sha256.json")).unwrap();
+        let mut witness = scheme.solve_witness(&dir.join("Prover.toml")).unwrap();
+
+        // Change an output of the first constraint compiled from an opcode
+        let source_map = scheme.source_map.as_ref().unwrap();
+        let r1cs = &scheme.r1cs;
+        let col = (0..r1cs.constraints)
+            .filter(|&row| source_map.origin(row).is_some())
+            .find_map(|row| {
+                let inputs = [r1cs.a(), r1cs.b()]
+                    .iter()
+                    .flat_map(|matrix| matrix.iter_row(row).map(|(col, _)| col))
+                    .collect::<Vec<_>>();
+                r1cs.c()
+                    .iter_row(row)
+                    .map(|(col, _)| col)
+                    .find(|col| !inputs.contains(col))
+            })
+            .unwrap();
+        witness[col] += FieldElement::one();
+
+        let error = r1cs.verify_witness(&witness).unwrap_err();
+        let row = error.downcast_ref::<ConstraintError>().unwrap().row();
+        let partial = witness.iter().copied().map(Some).collect::<Vec<_>>();
+        let error = scheme.diagnose(error, |row| r1cs.display_partial_constraint(row, &partial));
+
+        let constraint = r1cs.display_partial_constraint(row, &partial).to_string();
+        assert!(constraint.starts_with(&format!("{row}: (")));
+        assert!(constraint.ends_with(" ✗"));
+        let origin = source_map.origin(row).map_or_else(
+            || "no ACIR opcode recorded".to_string(),
+            |origin| origin.to_string(),
+        );
+        assert_eq!(
+            format!("{error:#}"),
+            format!("{constraint}\n{origin}: Constraint {row} failed")
+        );
+    }
+
+    #[test]
+    fn test_fill_witness_lists_unsolved() {
+        let one = Some(FieldElement::from(1u64));
+        assert_eq!(fill_witness(vec![one, one]).unwrap().len(), 2);
+        let error = fill_witness(vec![one, None, one, None]).unwrap_err();
+        assert_eq!(error.to_string(), "2 R1CS witnesses are unsolved: [1, 3]");
     }
 }
@@ -1,54 +1,191 @@
-use std::{collections::BTreeMap, num::NonZeroU32, ops::Neg};
+use std::{
+    array,
+    collections::{btree_map::Entry, BTreeMap},
+    num::NonZeroU32,
+    ops::Neg,
+    slice,
+};
 
 use acir::{
-    circuit::{Circuit, Opcode},
+    circuit::{
+        opcodes::{BlackBoxFuncCall, BlockId, ConstantOrWitnessEnum, FunctionInput, MemOp},
+        Circuit, Opcode,
+    },
     native_types::{Expression, Witness},
+    AcirField,
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
+use ark_ff::{BigInteger, PrimeField};
 use ark_std::One;
+use serde::{Deserialize, Serialize};
 
-use crate::{utils::noir_to_native, FieldElement, NoirElement, R1CS};
+use crate::{
+    bitwise::{self, BitwiseOp},
+    ecdsa::{self, Curve},
+    grumpkin, poseidon2,
+    range_check::{self, RangeCheckStrategy},
+    sha256::{self, Word},
+    utils::noir_to_native,
+    FieldElement, Hint, LinearCombination, NoirElement, R1CSBuilder, SourceMap, R1CS,
+};
 
 struct NoirToR1CSCompiler {
-    r1cs: R1CS,
+    r1cs: R1CSBuilder,
     witness_one: usize,
+    /// ACIR witness index to R1CS witness index for the function being
+    /// compiled.
     witness_map: BTreeMap<usize, usize>,
+    /// Current contents of each ACIR memory block of the function being
+    /// compiled.
+    memory_blocks: BTreeMap<u32, Vec<LinearCombination>>,
+    range_check_strategy: RangeCheckStrategy,
+    /// Range checks deferred until finalization, as R1CS witness to bit size.
+    range_checks: Vec<(usize, u32)>,
+    /// ACIR functions currently being inlined, to reject recursion.
+    call_stack: Vec<u32>,
+    /// Witness maps of completed function calls.
+    call_witness_maps: Vec<CallWitnessMap>,
+    /// `(function, opcode index)` of the opcodes being compiled, from the
+    /// entry point down through inlined calls.
+    call_sites: Vec<(u32, usize)>,
+    source_map: SourceMap,
+}
+
+/// The R1CS witnesses of one execution of an ACIR function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallWitnessMap {
+    /// Index of the function in the ACIR program.
+    pub function: u32,
+    /// ACIR witness index to R1CS witness index. Index zero is reserved for
+    /// constant one.
+    pub witness_map: Vec<Option<NonZeroU32>>,
 }
 
 /// Compile a Noir circuit to a R1CS relation, returning the R1CS and a map from
 /// Noir witness indices to R1CS witness indices.
 pub fn noir_to_r1cs(circuit: &Circuit<NoirElement>) -> Result<(R1CS, Vec<Option<NonZeroU32>>)> {
-    let mut compiler = NoirToR1CSCompiler::new();
-    compiler.add_circuit(circuit)?;
-    Ok(compiler.finalize())
+    noir_to_r1cs_with_range_checks(circuit, RangeCheckStrategy::default())
+}
+
+/// Compile a Noir circuit to a R1CS relation using the given strategy for
+/// range checks.
+pub fn noir_to_r1cs_with_range_checks(
+    circuit: &Circuit<NoirElement>,
+    range_check_strategy: RangeCheckStrategy,
+) -> Result<(R1CS, Vec<Option<NonZeroU32>>)> {
+    let (r1cs, mut witness_maps) =
+        noir_program_to_r1cs_with_range_checks(slice::from_ref(circuit), range_check_strategy)?;
+    let main = witness_maps.pop().expect("Entry point witness map");
+    Ok((r1cs, main.witness_map))
+}
+
+/// Compile a multi-function ACIR program to a single R1CS relation. The first
+/// function is the entry point and calls to other functions are inlined.
+///
+/// The public parameters and return values of the entry point are placed at
+/// R1CS witnesses `1..=r1cs.public_inputs`, in that order.
+///
+/// Returns the R1CS and the witness map of every function execution, in the
+/// order the ACVM pushes them to its witness stack: callees before their
+/// callers and the entry point last.
+pub fn noir_program_to_r1cs(
+    functions: &[Circuit<NoirElement>],
+) -> Result<(R1CS, Vec<CallWitnessMap>)> {
+    noir_program_to_r1cs_with_range_checks(functions, RangeCheckStrategy::default())
+}
+
+/// Compile a multi-function ACIR program using the given strategy for range
+/// checks. See [`noir_program_to_r1cs`].
+pub fn noir_program_to_r1cs_with_range_checks(
+    functions: &[Circuit<NoirElement>],
+    range_check_strategy: RangeCheckStrategy,
+) -> Result<(R1CS, Vec<CallWitnessMap>)> {
+    let (r1cs, witness_maps, _) = compile(functions, range_check_strategy)?;
+    Ok((r1cs, witness_maps))
+}
+
+/// Compile a multi-function ACIR program like
+/// [`noir_program_to_r1cs_with_range_checks`], also returning a map from
+/// constraints to the opcodes they came from.
+pub fn noir_program_to_r1cs_with_source_map(
+    functions: &[Circuit<NoirElement>],
+    range_check_strategy: RangeCheckStrategy,
+) -> Result<(R1CS, Vec<CallWitnessMap>, SourceMap)> {
+    compile(functions, range_check_strategy)
+}
+
+fn compile(
+    functions: &[Circuit<NoirElement>],
+    range_check_strategy: RangeCheckStrategy,
+) -> Result<(R1CS, Vec<CallWitnessMap>, SourceMap)> {
+    ensure!(!functions.is_empty(), "Program has no functions");
+    if let RangeCheckStrategy::Batched { log_base } = range_check_strategy {
+        ensure!(
+            (1..=range_check::MAX_LOG_BASE).contains(&log_base),
+            "Range check digits must have between 1 and {} bits",
+            range_check::MAX_LOG_BASE
+        );
+    }
+    let mut compiler = NoirToR1CSCompiler::new(range_check_strategy);
+    compiler.add_public_io(&functions[0]);
+    compiler
+        .source_map
+        .assign_rows(compiler.r1cs.constraints, None);
+    compiler.add_circuit(functions, 0)?;
+    let mut source_map = std::mem::take(&mut compiler.source_map);
+    let (r1cs, witness_maps) = compiler.finalize();
+    source_map.assign_rows(r1cs.constraints, None);
+    Ok((r1cs, witness_maps, source_map))
+}
+
+/// Convert a witness map to a vector indexed by ACIR witness.
+fn to_witness_vec(witness_map: BTreeMap<usize, usize>) -> Vec<Option<NonZeroU32>> {
+    let len = witness_map.keys().max().map_or_else(|| 0, |i| i + 1);
+    let mut map = vec![None; len];
+    for (i, j) in witness_map {
+        map[i] = Some(NonZeroU32::new(j as u32).expect("Index zero is reserved"));
+    }
+    map
 }
 
 impl NoirToR1CSCompiler {
-    fn new() -> Self {
-        let mut r1cs = R1CS::new();
+    fn new(range_check_strategy: RangeCheckStrategy) -> Self {
+        let mut r1cs = R1CSBuilder::new();
         let witness_one = r1cs.new_witness();
         assert_eq!(witness_one, 0, "R1CS requires first witness to be 1");
         Self {
             r1cs,
             witness_one,
             witness_map: BTreeMap::new(),
+            memory_blocks: BTreeMap::new(),
+            range_check_strategy,
+            range_checks: Vec::new(),
+            call_stack: Vec::new(),
+            call_witness_maps: Vec::new(),
+            call_sites: Vec::new(),
+            source_map: SourceMap::default(),
         }
     }
 
-    /// Returns the R1CS and the witness map
-    pub fn finalize(self) -> (R1CS, Vec<Option<NonZeroU32>>) {
-        // Convert witness map to vector
-        let len = self
-            .witness_map
-            .iter()
-            .map(|(i, _)| *i)
-            .max()
-            .map_or_else(|| 0, |i| i + 1);
-        let mut map = vec![None; len];
-        for (i, j) in self.witness_map {
-            map[i] = Some(NonZeroU32::new(j as u32).expect("Index zero is reserved"));
+    /// Returns the R1CS and the witness maps, ending with the entry point
+    pub fn finalize(mut self) -> (R1CS, Vec<CallWitnessMap>) {
+        // Emit deferred range checks against one shared digit table
+        if let RangeCheckStrategy::Batched { log_base } = self.range_check_strategy {
+            let range_checks = std::mem::take(&mut self.range_checks);
+            range_check::add_batched_range_checks(
+                &mut self.r1cs,
+                self.witness_one,
+                range_checks,
+                log_base,
+            );
         }
-        (self.r1cs, map)
+
+        let mut witness_maps = self.call_witness_maps;
+        witness_maps.push(CallWitnessMap {
+            function: 0,
+            witness_map: to_witness_vec(self.witness_map),
+        });
+        (self.r1cs.build(), witness_maps)
     }
 
     /// Index of the constant one witness
@@ -68,6 +205,42 @@ impl NoirToR1CSCompiler {
             })
     }
 
+    /// Allocate the public parameters and return values of the entry point at
+    /// the start of the witness vector. Must be called before any other witness
+    /// is created. A witness that is public more than once gets a slot for
+    /// each occurrence.
+    pub fn add_public_io(&mut self, circuit: &Circuit<NoirElement>) {
+        assert_eq!(
+            self.r1cs.witnesses, 1,
+            "Public values must precede other witnesses"
+        );
+        let public = circuit
+            .public_parameters
+            .0
+            .iter()
+            .chain(circuit.return_values.0.iter());
+        for &witness in public {
+            let index = self.r1cs.new_witness();
+            match self.witness_map.entry(witness.as_usize()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+                Entry::Occupied(entry) => {
+                    let existing = *entry.get();
+                    self.r1cs.add_constraint(
+                        &[],
+                        &[],
+                        &[
+                            (FieldElement::one(), index),
+                            (FieldElement::one().neg(), existing),
+                        ],
+                    );
+                }
+            }
+        }
+        self.r1cs.public_inputs = self.r1cs.witnesses - 1;
+    }
+
     /// Add an ACIR assert zero constraint.
     pub fn add_assert_zero(&mut self, expr: &Expression<NoirElement>) {
         // println!("expr {:?}", expr);
@@ -118,17 +291,988 @@ impl NoirToR1CSCompiler {
         self.r1cs.add_constraint(&a, &b, &linear);
     }
 
-    pub fn add_circuit(&mut self, circuit: &Circuit<NoirElement>) -> Result<()> {
-        for opcode in circuit.opcodes.iter() {
+    /// Convert an ACIR expression to a linear combination, introducing product
+    /// witnesses for its multiplication terms.
+    pub fn add_expression(&mut self, expr: &Expression<NoirElement>) -> LinearCombination {
+        let mut linear = expr
+            .mul_terms
+            .iter()
+            .map(|term| {
+                let a = self.map_witness(term.1);
+                let b = self.map_witness(term.2);
+                let c = self.r1cs.new_witness();
+                self.r1cs.add_constraint(
+                    &[(FieldElement::one(), a)],
+                    &[(FieldElement::one(), b)],
+                    &[(FieldElement::one(), c)],
+                );
+                (noir_to_native(term.0), c)
+            })
+            .collect::<Vec<_>>();
+        linear.extend(
+            expr.linear_combinations
+                .iter()
+                .map(|term| (noir_to_native(term.0), self.map_witness(term.1))),
+        );
+        linear.push((noir_to_native(expr.q_c), self.witness_one()));
+        linear
+    }
+
+    /// Add an ACIR memory block initialization. Blocks are tracked as a list of
+    /// linear combinations and do not produce constraints by themselves.
+    pub fn add_memory_init(&mut self, block_id: BlockId, init: &[Witness]) {
+        let values = init
+            .iter()
+            .map(|witness| vec![(FieldElement::one(), self.map_witness(*witness))])
+            .collect();
+        self.memory_blocks.insert(block_id.0, values);
+    }
+
+    /// Add an ACIR memory read or write.
+    ///
+    /// Accesses with a constant index are resolved at compile time. Dynamic
+    /// accesses use a one-hot selector vector `s` over the block, constrained by
+    /// `s_i * s_i = s_i`, `Σ s_i = predicate` and `Σ i * s_i = predicate *
+    /// index`. A read then costs one product per entry, a write replaces every
+    /// entry with `mem_i + s_i * (value - mem_i)`. A disabled predicate zeroes
+    /// all selectors, so reads return zero and writes leave the block unchanged,
+    /// matching ACVM.
+    pub fn add_memory_op(
+        &mut self,
+        block_id: BlockId,
+        op: &MemOp<NoirElement>,
+        predicate: Option<&Expression<NoirElement>>,
+    ) -> Result<()> {
+        let mut block = self
+            .memory_blocks
+            .remove(&block_id.0)
+            .ok_or_else(|| anyhow!("Memory block {} used before initialization", block_id.0))?;
+        ensure!(
+            op.operation.is_const(),
+            "Memory operation must be a constant"
+        );
+        let is_write = !op.operation.q_c.is_zero();
+        let predicate = predicate.filter(|p| !(p.is_const() && p.q_c.is_one()));
+
+        // Constant index without predicate
+        if predicate.is_none() && op.index.is_const() {
+            let index = op
+                .index
+                .q_c
+                .try_to_u64()
+                .map(|i| i as usize)
+                .filter(|&i| i < block.len())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Memory index {} out of bounds for block {} of length {}",
+                        op.index.q_c,
+                        block_id.0,
+                        block.len()
+                    )
+                })?;
+            let value = self.add_expression(&op.value);
+            if is_write {
+                block[index] = value;
+            } else {
+                let mut linear = block[index].clone();
+                linear.extend(value.into_iter().map(|(c, w)| (c.neg(), w)));
+                self.r1cs.add_constraint(&[], &[], &linear);
+            }
+            self.memory_blocks.insert(block_id.0, block);
+            return Ok(());
+        }
+
+        // One-hot selectors for the accessed position
+        let index = self.add_expression(&op.index);
+        let (predicate, position) = match predicate {
+            Some(predicate) => {
+                let predicate = self.add_expression(predicate);
+                let position = self.r1cs.new_witness();
+                self.r1cs
+                    .add_constraint(&predicate, &index, &[(FieldElement::one(), position)]);
+                (predicate, vec![(FieldElement::one(), position)])
+            }
+            None => (
+                vec![(FieldElement::one(), self.witness_one())],
+                index.clone(),
+            ),
+        };
+        let selectors = (0..block.len())
+            .map(|_| self.r1cs.new_witness())
+            .collect::<Vec<_>>();
+        self.r1cs.add_hint(Hint::OneHot {
+            predicate: predicate.clone(),
+            index,
+            outputs: selectors.clone(),
+        });
+        for &s in &selectors {
+            self.r1cs.add_constraint(
+                &[(FieldElement::one(), s)],
+                &[(FieldElement::one(), s)],
+                &[(FieldElement::one(), s)],
+            );
+        }
+        let mut sum = selectors
+            .iter()
+            .map(|&s| (FieldElement::one(), s))
+            .collect::<Vec<_>>();
+        sum.extend(predicate.into_iter().map(|(c, w)| (c.neg(), w)));
+        self.r1cs.add_constraint(&[], &[], &sum);
+        let mut weighted = selectors
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| (FieldElement::from(i as u64), s))
+            .collect::<Vec<_>>();
+        weighted.extend(position.into_iter().map(|(c, w)| (c.neg(), w)));
+        self.r1cs.add_constraint(&[], &[], &weighted);
+
+        let value = self.add_expression(&op.value);
+        if is_write {
+            for (&s, entry) in selectors.iter().zip(block.iter_mut()) {
+                let updated = self.r1cs.new_witness();
+                let mut delta = value.clone();
+                delta.extend(entry.iter().map(|&(c, w)| (c.neg(), w)));
+                let mut output = vec![(FieldElement::one(), updated)];
+                output.extend(entry.iter().map(|&(c, w)| (c.neg(), w)));
+                self.r1cs
+                    .add_constraint(&[(FieldElement::one(), s)], &delta, &output);
+                *entry = vec![(FieldElement::one(), updated)];
+            }
+        } else {
+            let mut linear = selectors
+                .iter()
+                .zip(block.iter())
+                .map(|(&s, entry)| {
+                    let product = self.r1cs.new_witness();
+                    self.r1cs.add_constraint(
+                        &[(FieldElement::one(), s)],
+                        entry,
+                        &[(FieldElement::one(), product)],
+                    );
+                    (FieldElement::one(), product)
+                })
+                .collect::<Vec<_>>();
+            linear.extend(value.into_iter().map(|(c, w)| (c.neg(), w)));
+            self.r1cs.add_constraint(&[], &[], &linear);
+        }
+        self.memory_blocks.insert(block_id.0, block);
+        Ok(())
+    }
+
+    /// Add an ACIR range check, either directly or deferred to finalization
+    /// depending on the strategy.
+    pub fn add_range_check(&mut self, input: &FunctionInput<NoirElement>) -> Result<()> {
+        let num_bits = input.num_bits();
+        match input.input() {
+            ConstantOrWitnessEnum::Constant(value) => ensure!(
+                value.num_bits() <= num_bits,
+                "Constant {value} does not fit in {num_bits} bits"
+            ),
+            ConstantOrWitnessEnum::Witness(witness) => {
+                let witness = self.map_witness(witness);
+                match self.range_check_strategy {
+                    RangeCheckStrategy::Bits => range_check::add_range_check(
+                        &mut self.r1cs,
+                        self.witness_one,
+                        witness,
+                        num_bits,
+                    ),
+                    RangeCheckStrategy::Batched { .. } => {
+                        self.range_checks.push((witness, num_bits))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert a black-box input to a linear combination.
+    pub fn add_input(&mut self, input: &FunctionInput<NoirElement>) -> LinearCombination {
+        match input.input() {
+            ConstantOrWitnessEnum::Constant(value) => {
+                vec![(noir_to_native(value), self.witness_one)]
+            }
+            ConstantOrWitnessEnum::Witness(witness) => {
+                vec![(FieldElement::one(), self.map_witness(witness))]
+            }
+        }
+    }
+
+    /// Decompose a black-box input into boolean bit linear combinations,
+    /// little-endian. Constant inputs are decomposed at compile time.
+    pub fn add_input_bits(
+        &mut self,
+        input: &FunctionInput<NoirElement>,
+    ) -> Result<Vec<LinearCombination>> {
+        let num_bits = input.num_bits();
+        match input.input() {
+            ConstantOrWitnessEnum::Constant(value) => {
+                ensure!(
+                    value.num_bits() <= num_bits,
+                    "Constant {value} does not fit in {num_bits} bits"
+                );
+                let value = noir_to_native(value).into_bigint();
+                Ok((0..num_bits)
+                    .map(|i| {
+                        let bit = FieldElement::from(value.get_bit(i as usize));
+                        vec![(bit, self.witness_one)]
+                    })
+                    .collect())
+            }
+            ConstantOrWitnessEnum::Witness(witness) => {
+                let witness = self.map_witness(witness);
+                let bits = range_check::decompose(
+                    &mut self.r1cs,
+                    self.witness_one,
+                    &[(FieldElement::one(), witness)],
+                    num_bits,
+                    1,
+                );
+                Ok(bits
+                    .into_iter()
+                    .map(|bit| vec![(FieldElement::one(), bit)])
+                    .collect())
+            }
+        }
+    }
+
+    /// Add an ACIR AND or XOR black-box call.
+    pub fn add_bitwise(
+        &mut self,
+        op: BitwiseOp,
+        lhs: &FunctionInput<NoirElement>,
+        rhs: &FunctionInput<NoirElement>,
+        output: Witness,
+    ) -> Result<()> {
+        ensure!(
+            lhs.num_bits() == rhs.num_bits(),
+            "Bitwise operand sizes differ ({} and {} bits)",
+            lhs.num_bits(),
+            rhs.num_bits()
+        );
+        let lhs = self.add_input_bits(lhs)?;
+        let rhs = self.add_input_bits(rhs)?;
+        let bits = bitwise::add_bitwise(&mut self.r1cs, self.witness_one, op, &lhs, &rhs);
+        let mut linear = range_check::recompose(&bits, 1);
+        linear.push((FieldElement::one().neg(), self.map_witness(output)));
+        self.r1cs.add_constraint(&[], &[], &linear);
+        Ok(())
+    }
+
+    /// Add an ACIR Poseidon2 permutation black-box call.
+    pub fn add_poseidon2(
+        &mut self,
+        inputs: &[FunctionInput<NoirElement>],
+        outputs: &[Witness],
+        len: u32,
+    ) -> Result<()> {
+        ensure!(
+            len as usize == poseidon2::WIDTH
+                && inputs.len() == poseidon2::WIDTH
+                && outputs.len() == poseidon2::WIDTH,
+            "Poseidon2 permutation of width {len} is not supported"
+        );
+        let inputs = array::from_fn(|i| self.add_input(&inputs[i]));
+        let outputs = array::from_fn(|i| self.map_witness(outputs[i]));
+        poseidon2::add_permutation(&mut self.r1cs, self.witness_one, &inputs, &outputs);
+        Ok(())
+    }
+
+    /// Add an ACIR SHA-256 compression black-box call.
+    pub fn add_sha256_compression(
+        &mut self,
+        inputs: &[FunctionInput<NoirElement>; 16],
+        hash_values: &[FunctionInput<NoirElement>; 8],
+        outputs: &[Witness; 8],
+    ) -> Result<()> {
+        let mut add_words = |words: &[FunctionInput<NoirElement>]| {
+            words
+                .iter()
+                .map(|word| {
+                    ensure!(
+                        word.num_bits() == 32,
+                        "SHA-256 words must be 32 bits, got {}",
+                        word.num_bits()
+                    );
+                    self.add_input_bits(word)
+                })
+                .collect::<Result<Vec<Word>>>()
+        };
+        let inputs = add_words(inputs)?.try_into().unwrap();
+        let hash_values = add_words(hash_values)?.try_into().unwrap();
+        let words =
+            sha256::add_compression(&mut self.r1cs, self.witness_one, &inputs, &hash_values);
+        for (mut linear, &output) in words.into_iter().zip(outputs.iter()) {
+            linear.push((FieldElement::one().neg(), self.map_witness(output)));
+            self.r1cs.add_constraint(&[], &[], &linear);
+        }
+        Ok(())
+    }
+
+    /// Add an ACIR ECDSA verification black-box call, constraining `output`
+    /// to whether the signature is valid.
+    pub fn add_ecdsa(
+        &mut self,
+        curve: &Curve,
+        public_key_x: &[FunctionInput<NoirElement>; 32],
+        public_key_y: &[FunctionInput<NoirElement>; 32],
+        signature: &[FunctionInput<NoirElement>; 64],
+        hashed_message: &[FunctionInput<NoirElement>; 32],
+        output: Witness,
+    ) -> Result<()> {
+        let mut add_bytes = |inputs: &[FunctionInput<NoirElement>]| {
+            inputs
+                .iter()
+                .map(|input| {
+                    ensure!(
+                        input.num_bits() == 8,
+                        "ECDSA inputs must be bytes, got {} bits",
+                        input.num_bits()
+                    );
+                    self.add_range_check(input)?;
+                    Ok(self.add_input(input))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let public_key_x = add_bytes(public_key_x)?;
+        let public_key_y = add_bytes(public_key_y)?;
+        let signature = add_bytes(signature)?;
+        let hashed_message = add_bytes(hashed_message)?;
+        let mut valid = ecdsa::add_verification(
+            &mut self.r1cs,
+            self.witness_one,
+            curve,
+            &public_key_x,
+            &public_key_y,
+            &signature,
+            &hashed_message,
+        );
+        valid.push((FieldElement::one().neg(), self.map_witness(output)));
+        self.r1cs.add_constraint(&[], &[], &valid);
+        Ok(())
+    }
+
+    /// Convert a black-box `(x, y, is_infinite)` input to a Grumpkin point,
+    /// constraining it to be on the curve.
+    fn add_grumpkin_point(&mut self, inputs: &[FunctionInput<NoirElement>]) -> grumpkin::Point {
+        let point = grumpkin::Point {
+            x: self.add_input(&inputs[0]),
+            y: self.add_input(&inputs[1]),
+            is_infinite: self.add_input(&inputs[2]),
+        };
+        grumpkin::add_on_curve_check(&mut self.r1cs, self.witness_one, &point);
+        point
+    }
+
+    /// Constrain output witnesses to a Grumpkin point.
+    fn add_grumpkin_output(
+        &mut self,
+        point: grumpkin::Point,
+        outputs: (Witness, Witness, Witness),
+    ) {
+        let outputs = [outputs.0, outputs.1, outputs.2];
+        for (mut linear, output) in [point.x, point.y, point.is_infinite]
+            .into_iter()
+            .zip(outputs)
+        {
+            linear.push((FieldElement::one().neg(), self.map_witness(output)));
+            self.r1cs.add_constraint(&[], &[], &linear);
+        }
+    }
+
+    /// Add an ACIR embedded curve addition black-box call.
+    pub fn add_embedded_curve_add(
+        &mut self,
+        input1: &[FunctionInput<NoirElement>; 3],
+        input2: &[FunctionInput<NoirElement>; 3],
+        outputs: (Witness, Witness, Witness),
+    ) {
+        let lhs = self.add_grumpkin_point(input1);
+        let rhs = self.add_grumpkin_point(input2);
+        let sum = grumpkin::add_addition(&mut self.r1cs, self.witness_one, &lhs, &rhs);
+        self.add_grumpkin_output(sum, outputs);
+    }
+
+    /// Add an ACIR multi-scalar multiplication black-box call. Points are
+    /// `(x, y, is_infinite)` triples and scalars `(lo, hi)` pairs of 128-bit
+    /// limbs.
+    pub fn add_multi_scalar_mul(
+        &mut self,
+        points: &[FunctionInput<NoirElement>],
+        scalars: &[FunctionInput<NoirElement>],
+        outputs: (Witness, Witness, Witness),
+    ) -> Result<()> {
+        ensure!(
+            points.len() % 3 == 0 && points.len() / 3 * 2 == scalars.len(),
+            "Mismatched multi-scalar multiplication inputs: {} points and {} scalars",
+            points.len(),
+            scalars.len()
+        );
+        let terms = points
+            .chunks(3)
+            .zip(scalars.chunks(2))
+            .map(|(point, scalar)| {
+                let point = self.add_grumpkin_point(point);
+                (
+                    point,
+                    self.add_input(&scalar[0]),
+                    self.add_input(&scalar[1]),
+                )
+            })
+            .collect::<Vec<_>>();
+        let sum = grumpkin::add_multi_scalar_mul(&mut self.r1cs, self.witness_one, &terms);
+        self.add_grumpkin_output(sum, outputs);
+        Ok(())
+    }
+
+    /// Inline an ACIR function call. The callee's first witnesses are the call
+    /// inputs and the call outputs are its return values, as in the ACVM.
+    ///
+    /// The predicate must be constant. When it is zero the ACVM skips the call
+    /// and sets the outputs to zero, so only the outputs are constrained.
+    pub fn add_call(
+        &mut self,
+        functions: &[Circuit<NoirElement>],
+        function: u32,
+        inputs: &[Witness],
+        outputs: &[Witness],
+        predicate: Option<&Expression<NoirElement>>,
+    ) -> Result<()> {
+        let callee = functions
+            .get(function as usize)
+            .ok_or_else(|| anyhow!("Call to unknown ACIR function {function}"))?;
+        ensure!(
+            callee.return_values.0.len() == outputs.len(),
+            "Call to ACIR function {function} expects {} outputs, got {}",
+            callee.return_values.0.len(),
+            outputs.len()
+        );
+        if let Some(predicate) = predicate {
+            ensure!(
+                predicate.is_const(),
+                "Call to ACIR function {function} has a predicate that depends on witnesses, \
+                 which can not be compiled to R1CS; only calls with a constant predicate are \
+                 supported"
+            );
+            if predicate.q_c.is_zero() {
+                for &output in outputs {
+                    let output = self.map_witness(output);
+                    self.r1cs
+                        .add_constraint(&[], &[], &[(FieldElement::one(), output)]);
+                }
+                return Ok(());
+            }
+        }
+
+        // Compile the callee in its own scope
+        let arguments = inputs
+            .iter()
+            .map(|&input| self.map_witness(input))
+            .collect::<Vec<_>>();
+        let caller_witness_map = std::mem::take(&mut self.witness_map);
+        let caller_memory_blocks = std::mem::take(&mut self.memory_blocks);
+        self.witness_map.extend(arguments.into_iter().enumerate());
+        self.add_circuit(functions, function)?;
+        let return_values = callee
+            .return_values
+            .0
+            .iter()
+            .map(|&witness| self.map_witness(witness))
+            .collect::<Vec<_>>();
+        let callee_witness_map = std::mem::replace(&mut self.witness_map, caller_witness_map);
+        self.memory_blocks = caller_memory_blocks;
+        self.call_witness_maps.push(CallWitnessMap {
+            function,
+            witness_map: to_witness_vec(callee_witness_map),
+        });
+
+        // Outputs alias the return values unless they are already in use
+        for (output, value) in outputs.iter().zip(return_values) {
+            match self.witness_map.entry(output.as_usize()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+                Entry::Occupied(entry) => {
+                    let output = *entry.get();
+                    self.r1cs.add_constraint(
+                        &[],
+                        &[],
+                        &[
+                            (FieldElement::one(), output),
+                            (FieldElement::one().neg(), value),
+                        ],
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the ACIR function with index `function` of a program.
+    pub fn add_circuit(&mut self, functions: &[Circuit<NoirElement>], function: u32) -> Result<()> {
+        ensure!(
+            !self.call_stack.contains(&function),
+            "Recursive call to ACIR function {function}"
+        );
+        let circuit = functions
+            .get(function as usize)
+            .ok_or_else(|| anyhow!("Unknown ACIR function {function}"))?;
+        self.call_stack.push(function);
+        for (index, opcode) in circuit.opcodes.iter().enumerate() {
+            self.call_sites.push((function, index));
+            let origin = self.source_map.add_opcode(self.call_sites.clone(), opcode);
             match opcode {
                 Opcode::AssertZero(expr) => self.add_assert_zero(expr),
 
+                Opcode::MemoryInit { block_id, init, .. } => self.add_memory_init(*block_id, init),
+
+                Opcode::MemoryOp {
+                    block_id,
+                    op,
+                    predicate,
+                } => self.add_memory_op(*block_id, op, predicate.as_ref())?,
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) => {
+                    self.add_range_check(input)?
+                }
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::AND { lhs, rhs, output }) => {
+                    self.add_bitwise(BitwiseOp::And, lhs, rhs, *output)?
+                }
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::XOR { lhs, rhs, output }) => {
+                    self.add_bitwise(BitwiseOp::Xor, lhs, rhs, *output)?
+                }
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Poseidon2Permutation {
+                    inputs,
+                    outputs,
+                    len,
+                }) => self.add_poseidon2(inputs, outputs, *len)?,
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Sha256Compression {
+                    inputs,
+                    hash_values,
+                    outputs,
+                }) => self.add_sha256_compression(inputs, hash_values, outputs)?,
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::EcdsaSecp256k1 {
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    output,
+                }) => self.add_ecdsa(
+                    &ecdsa::SECP256K1,
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    *output,
+                )?,
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::EcdsaSecp256r1 {
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    output,
+                }) => self.add_ecdsa(
+                    &ecdsa::SECP256R1,
+                    public_key_x,
+                    public_key_y,
+                    signature,
+                    hashed_message,
+                    *output,
+                )?,
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::EmbeddedCurveAdd {
+                    input1,
+                    input2,
+                    outputs,
+                }) => self.add_embedded_curve_add(input1, input2, *outputs),
+
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::MultiScalarMul {
+                    points,
+                    scalars,
+                    outputs,
+                }) => self.add_multi_scalar_mul(points, scalars, *outputs)?,
+
                 // Brillig is only for witness generation and does not produce constraints.
                 Opcode::BrilligCall { .. } => {}
 
+                Opcode::Call {
+                    id,
+                    inputs,
+                    outputs,
+                    predicate,
+                } => self.add_call(functions, id.0, inputs, outputs, predicate.as_ref())?,
+
                 op => bail!("Unsupported Opcode {op}"),
             }
+            // Rows of inlined calls were already attributed to the callee
+            self.source_map
+                .assign_rows(self.r1cs.constraints, Some(origin));
+            self.call_sites.pop();
         }
+        self.call_stack.pop();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, slice};
+
+    use acir::{
+        circuit::{
+            opcodes::{AcirFunctionId, BlackBoxFuncCall, BlockId, BlockType, FunctionInput, MemOp},
+            Circuit, Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        AcirField,
+    };
+
+    use ark_ff::PrimeField;
+
+    use super::{noir_program_to_r1cs, noir_program_to_r1cs_with_source_map};
+    use crate::{FieldElement, NoirElement, RangeCheckStrategy};
+
+    /// Compile a circuit and solve it for the given ACIR witness assignments.
+    /// Returns the values of the ACIR witnesses.
+    fn solve(
+        circuit: &Circuit<NoirElement>,
+        inputs: &[(u32, u64)],
+    ) -> anyhow::Result<Vec<Option<FieldElement>>> {
+        solve_program(slice::from_ref(circuit), inputs)
+    }
+
+    /// Like [`solve`] for a multi-function program, returning the values of
+    /// the entry point witnesses.
+    fn solve_program(
+        functions: &[Circuit<NoirElement>],
+        inputs: &[(u32, u64)],
+    ) -> anyhow::Result<Vec<Option<FieldElement>>> {
+        let (r1cs, mut witness_maps) = noir_program_to_r1cs(functions)?;
+        let witness_map = witness_maps.pop().unwrap().witness_map;
+        let mut witness = vec![None; r1cs.witnesses];
+        witness[0] = Some(FieldElement::from(1u64));
+        for &(i, value) in inputs {
+            let j = witness_map[i as usize].unwrap().get() as usize;
+            witness[j] = Some(FieldElement::from(value));
+        }
+        r1cs.solve_witness(&mut witness)?;
+        let witness = witness
+            .into_iter()
+            .map(|w| w.unwrap_or_default())
+            .collect::<Vec<_>>();
+        r1cs.verify_witness(&witness)?;
+        Ok(witness_map
+            .iter()
+            .map(|j| j.map(|j| witness[j.get() as usize]))
+            .collect())
+    }
+
+    fn assert_equal(a: Witness, b: Witness) -> Opcode<NoirElement> {
+        Opcode::AssertZero(Expression {
+            mul_terms: vec![],
+            linear_combinations: vec![(NoirElement::one(), a), (-NoirElement::one(), b)],
+            q_c: NoirElement::zero(),
+        })
+    }
+
+    #[test]
+    fn test_memory_read_write() {
+        // b = [w0, w1, w2]; b[w3] = w4; w5 = b[w6]; assert w5 == w7
+        let block_id = BlockId(0);
+        let circuit = Circuit {
+            current_witness_index: 7,
+            opcodes: vec![
+                Opcode::MemoryInit {
+                    block_id,
+                    init: vec![Witness(0), Witness(1), Witness(2)],
+                    block_type: BlockType::Memory,
+                },
+                Opcode::MemoryOp {
+                    block_id,
+                    op: MemOp::write_to_mem_index(Witness(3).into(), Witness(4).into()),
+                    predicate: None,
+                },
+                Opcode::MemoryOp {
+                    block_id,
+                    op: MemOp::read_at_mem_index(Witness(6).into(), Witness(5)),
+                    predicate: None,
+                },
+                assert_equal(Witness(5), Witness(7)),
+            ],
+            ..Circuit::default()
+        };
+        let inputs = [(0, 10), (1, 11), (2, 12), (3, 1), (4, 42)];
+        for (read, expected) in [(0, 10), (1, 42), (2, 12)] {
+            let mut inputs = inputs.to_vec();
+            inputs.extend([(6, read), (7, expected)]);
+            solve(&circuit, &inputs).unwrap();
+        }
+        for (read, expected) in [(1, 11), (3, 0)] {
+            let mut inputs = inputs.to_vec();
+            inputs.extend([(6, read), (7, expected)]);
+            assert!(solve(&circuit, &inputs).is_err());
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        // fn f(a, b) -> a * b + 1
+        let callee = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::AssertZero(Expression {
+                mul_terms: vec![(NoirElement::one(), Witness(0), Witness(1))],
+                linear_combinations: vec![(-NoirElement::one(), Witness(2))],
+                q_c: NoirElement::one(),
+            })],
+            private_parameters: BTreeSet::from([Witness(0), Witness(1)]),
+            return_values: PublicInputs(BTreeSet::from([Witness(2)])),
+            ..Circuit::default()
+        };
+        // w3 = f(f(w0, w1), w1); assert w3 == w4
+        let call = |inputs: Vec<Witness>, output: Witness| Opcode::Call {
+            id: AcirFunctionId(1),
+            inputs,
+            outputs: vec![output],
+            predicate: Some(Expression::one()),
+        };
+        let main = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                call(vec![Witness(0), Witness(1)], Witness(2)),
+                call(vec![Witness(2), Witness(1)], Witness(3)),
+                assert_equal(Witness(3), Witness(4)),
+            ],
+            ..Circuit::default()
+        };
+        let functions = [main, callee];
+
+        let (r1cs, witness_maps, source_map) =
+            noir_program_to_r1cs_with_source_map(&functions, RangeCheckStrategy::default())
+                .unwrap();
+        let order = witness_maps.iter().map(|m| m.function).collect::<Vec<_>>();
+        assert_eq!(order, [1, 1, 0]);
+
+        // Constraints are attributed to the inlined opcode that produced them
+        let call_stack = |row| source_map.origin(row).unwrap().call_stack.clone();
+        assert_eq!(call_stack(0), [(0, 0), (1, 0)]);
+        assert_eq!(call_stack(r1cs.constraints - 1), [(0, 2)]);
+
+        let values = solve_program(&functions, &[(0, 3), (1, 5), (4, 81)]).unwrap();
+        assert_eq!(values[2], Some(FieldElement::from(16u64)));
+        assert!(solve_program(&functions, &[(0, 3), (1, 5), (4, 80)]).is_err());
+
+        // Recursion can not be inlined
+        let recursive = Circuit {
+            opcodes: vec![Opcode::Call {
+                id: AcirFunctionId(0),
+                inputs: vec![],
+                outputs: vec![],
+                predicate: None,
+            }],
+            ..Circuit::default()
+        };
+        assert!(noir_program_to_r1cs(&[recursive]).is_err());
+    }
+
+    #[test]
+    fn test_conditional_call() {
+        // fn f(a) -> a + 1
+        let callee = Circuit {
+            current_witness_index: 1,
+            opcodes: vec![Opcode::AssertZero(Expression {
+                mul_terms: vec![],
+                linear_combinations: vec![
+                    (NoirElement::one(), Witness(0)),
+                    (-NoirElement::one(), Witness(1)),
+                ],
+                q_c: NoirElement::one(),
+            })],
+            private_parameters: BTreeSet::from([Witness(0)]),
+            return_values: PublicInputs(BTreeSet::from([Witness(1)])),
+            ..Circuit::default()
+        };
+        // w1 = if predicate { f(w0) } else { 0 }; assert w1 == w2
+        let main = |predicate: Expression<NoirElement>| Circuit {
+            current_witness_index: 2,
+            opcodes: vec![
+                Opcode::Call {
+                    id: AcirFunctionId(1),
+                    inputs: vec![Witness(0)],
+                    outputs: vec![Witness(1)],
+                    predicate: Some(predicate),
+                },
+                assert_equal(Witness(1), Witness(2)),
+            ],
+            ..Circuit::default()
+        };
+
+        // A disabled call outputs zero and does not inline the callee
+        let functions = [main(Expression::zero()), callee.clone()];
+        let (_, witness_maps) = noir_program_to_r1cs(&functions).unwrap();
+        assert_eq!(witness_maps.len(), 1);
+        solve_program(&functions, &[(2, 0)]).unwrap();
+        assert!(solve_program(&functions, &[(2, 8)]).is_err());
+
+        let functions = [main(Expression::one()), callee.clone()];
+        solve_program(&functions, &[(0, 7), (2, 8)]).unwrap();
+
+        // A predicate that depends on a witness is rejected
+        let functions = [main(Witness(3).into()), callee];
+        let error = noir_program_to_r1cs(&functions).unwrap_err();
+        assert!(format!("{error:#}").contains("has a predicate that depends on witnesses"));
+    }
+
+    #[test]
+    fn test_public_io() {
+        // fn main(a, b: pub) -> pub (a * b, b)
+        let circuit = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![Opcode::AssertZero(Expression {
+                mul_terms: vec![(NoirElement::one(), Witness(0), Witness(1))],
+                linear_combinations: vec![(-NoirElement::one(), Witness(2))],
+                q_c: NoirElement::zero(),
+            })],
+            private_parameters: BTreeSet::from([Witness(0)]),
+            public_parameters: PublicInputs(BTreeSet::from([Witness(1)])),
+            return_values: PublicInputs(BTreeSet::from([Witness(1), Witness(2)])),
+            ..Circuit::default()
+        };
+        let (r1cs, mut witness_maps) = noir_program_to_r1cs(slice::from_ref(&circuit)).unwrap();
+        let witness_map = witness_maps.pop().unwrap().witness_map;
+        assert_eq!(r1cs.public_inputs, 3);
+        assert_eq!(witness_map[1].unwrap().get(), 1);
+        assert_eq!(witness_map[2].unwrap().get(), 3);
+
+        let mut witness = vec![None; r1cs.witnesses];
+        witness[0] = Some(FieldElement::from(1u64));
+        for (i, value) in [(0, 3u64), (1, 5), (2, 15)] {
+            witness[witness_map[i].unwrap().get() as usize] = Some(FieldElement::from(value));
+        }
+        r1cs.solve_witness(&mut witness).unwrap();
+        let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        let expected = [5u64, 5, 15].map(FieldElement::from);
+        assert_eq!(r1cs.public_values(&witness), expected);
+    }
+
+    #[test]
+    fn test_range_check() {
+        // w0 < 2^8, w1 < 2^3
+        let circuit = Circuit {
+            current_witness_index: 1,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                    input: FunctionInput::witness(Witness(0), 8),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                    input: FunctionInput::witness(Witness(1), 3),
+                }),
+            ],
+            ..Circuit::default()
+        };
+        solve(&circuit, &[(0, 0), (1, 0)]).unwrap();
+        solve(&circuit, &[(0, 255), (1, 7)]).unwrap();
+        assert!(solve(&circuit, &[(0, 256), (1, 7)]).is_err());
+        assert!(solve(&circuit, &[(0, 255), (1, 8)]).is_err());
+    }
+
+    #[test]
+    fn test_bitwise() {
+        // w2 = w0 & w1; w3 = w0 ^ w1; assert w2 == w4, w3 == w5
+        let lhs = FunctionInput::witness(Witness(0), 8);
+        let rhs = FunctionInput::witness(Witness(1), 8);
+        let circuit = Circuit {
+            current_witness_index: 5,
+            opcodes: vec![
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::AND {
+                    lhs,
+                    rhs,
+                    output: Witness(2),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::XOR {
+                    lhs,
+                    rhs,
+                    output: Witness(3),
+                }),
+                assert_equal(Witness(2), Witness(4)),
+                assert_equal(Witness(3), Witness(5)),
+            ],
+            ..Circuit::default()
+        };
+        for (a, b) in [(0, 0), (0b1100_1010, 0b1010_0110), (255, 170)] {
+            solve(&circuit, &[(0, a), (1, b), (4, a & b), (5, a ^ b)]).unwrap();
+            assert!(solve(&circuit, &[(0, a), (1, b), (4, a | b), (5, a ^ b ^ 1)]).is_err());
+        }
+        assert!(solve(&circuit, &[(0, 256), (1, 0), (4, 0), (5, 256)]).is_err());
+    }
+
+    #[test]
+    fn test_sha256_compression() {
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let expected = "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1";
+        let initial_state = [
+            0x6a09e667u32,
+            0xbb67ae85,
+            0x3c6ef372,
+            0xa54ff53a,
+            0x510e527f,
+            0x9b05688c,
+            0x1f83d9ab,
+            0x5be0cd19,
+        ];
+
+        // Pad the message into 512 bit blocks
+        let mut padded = message.to_vec();
+        padded.push(0x80);
+        while padded.len() % 64 != 56 {
+            padded.push(0);
+        }
+        padded.extend((message.len() as u64 * 8).to_be_bytes());
+        let words = padded
+            .chunks(4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        // Chain compressions, starting from the constant initial state
+        let mut opcodes = vec![];
+        let mut inputs = vec![];
+        let mut next_witness = 0;
+        let mut state = initial_state
+            .map(|word| FunctionInput::constant(NoirElement::from(word as u128), 32).unwrap());
+        for block in words.chunks(16) {
+            let block_inputs: [_; 16] = std::array::from_fn(|i| {
+                inputs.push((next_witness + i as u32, block[i] as u64));
+                FunctionInput::witness(Witness(next_witness + i as u32), 32)
+            });
+            let outputs: [_; 8] = std::array::from_fn(|i| Witness(next_witness + 16 + i as u32));
+            next_witness += 24;
+            opcodes.push(Opcode::BlackBoxFuncCall(
+                BlackBoxFuncCall::Sha256Compression {
+                    inputs: Box::new(block_inputs),
+                    hash_values: Box::new(state),
+                    outputs: Box::new(outputs),
+                },
+            ));
+            state = outputs.map(|output| FunctionInput::witness(output, 32));
+        }
+        let circuit = Circuit {
+            current_witness_index: next_witness - 1,
+            opcodes,
+            ..Circuit::default()
+        };
+
+        let values = solve(&circuit, &inputs).unwrap();
+        let digest = (next_witness - 8..next_witness)
+            .map(|i| {
+                let word = values[i as usize].unwrap();
+                format!("{:08x}", word.into_bigint().as_ref()[0])
+            })
+            .collect::<String>();
+        assert_eq!(digest, expected);
+    }
+}
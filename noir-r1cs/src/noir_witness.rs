@@ -1,22 +1,29 @@
-use std::num::NonZeroU32;
+use std::{collections::BTreeMap, iter, num::NonZeroU32};
 
+use acir::{
+    brillig::ForeignCallResult,
+    circuit::Program,
+    native_types::{Witness, WitnessMap},
+};
+use acvm::pwg::{ACVMStatus, ACVM};
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use ark_ff::PrimeField;
+use ark_ff::{Field, PrimeField};
+use ark_std::{One, Zero};
+use bn254_blackbox_solver::Bn254BlackBoxSolver;
 use noirc_abi::{
     input_parser::{Format, InputValue},
-    Abi, AbiType,
+    Abi, AbiType, InputMap, Sign,
 };
 use noirc_artifacts::program::ProgramArtifact;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::{
-    utils::{noir_to_native, serde_jsonify},
-    FieldElement,
+    utils::{native_to_noir, noir_to_native, serde_decimal::signed, serde_jsonify},
+    CallWitnessMap, FieldElement, NoirElement,
 };
 
-// TODO: Handling of the return value for the verifier.
-
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NoirWitnessGenerator {
     // Note: Abi uses an [internally tagged] enum format in Serde, which is not compatible
@@ -25,41 +32,194 @@ pub struct NoirWitnessGenerator {
     #[serde(with = "serde_jsonify")]
     abi: Abi,
 
-    /// ACIR witness index to R1CS witness index
+    /// The ACIR program, executed by the ACVM to compute the ACIR witnesses.
+    #[serde(
+        serialize_with = "Program::serialize_program_base64",
+        deserialize_with = "Program::deserialize_program_base64"
+    )]
+    program: Program<NoirElement>,
+
+    /// ACIR witness index to R1CS witness index for the entry point. Noir
+    /// inputs are at the start.
     /// Index zero is reserved for constant one, so we can use NonZeroU32
     witness_map: Vec<Option<NonZeroU32>>,
+
+    /// Witness maps of the inlined ACIR function calls, in ACVM witness stack
+    /// order. The entry point is not included.
+    #[serde(default)]
+    call_witness_maps: Vec<CallWitnessMap>,
 }
 
 impl NoirWitnessGenerator {
+    /// Create a witness generator from the witness maps returned by
+    /// [`crate::noir_program_to_r1cs`], which end with the entry point.
     pub fn new(
         program: &ProgramArtifact,
-        mut witness_map: Vec<Option<NonZeroU32>>,
+        mut call_witness_maps: Vec<CallWitnessMap>,
         r1cs_witnesses: usize,
     ) -> Self {
         let abi = program.abi.clone();
-        assert!(witness_map
+        assert!(call_witness_maps
             .iter()
+            .flat_map(|call| call.witness_map.iter())
             .filter_map(|n| *n)
             .all(|n| (n.get() as usize) < r1cs_witnesses));
-
-        // Take only the prefix of witness map relevant for Noir inputs
-        let num_inputs = abi.field_count() as usize;
-        witness_map.truncate(num_inputs);
-        Self { abi, witness_map }
+        let witness_map = call_witness_maps
+            .pop()
+            .expect("Missing entry point witness map")
+            .witness_map;
+        Self {
+            abi,
+            program: program.bytecode.clone(),
+            witness_map,
+            call_witness_maps,
+        }
     }
 
     pub fn witness_map(&self) -> &[Option<NonZeroU32>] {
         &self.witness_map
     }
 
-    /// Noir inputs are in order at the start of the witness vector
-    #[instrument(skip_all, fields(size = toml.len()))]
+    pub fn call_witness_maps(&self) -> &[CallWitnessMap] {
+        &self.call_witness_maps
+    }
+
+    /// Execute the ACIR program on Noir inputs with the ACVM, including
+    /// Brillig calls, and map the ACIR witnesses to a partial R1CS witness.
+    /// R1CS witnesses introduced by the compiler are left unsolved.
+    #[instrument(skip_all)]
+    pub fn generate_witness(
+        &self,
+        inputs: &[FieldElement],
+        r1cs_witnesses: usize,
+    ) -> Result<Vec<Option<FieldElement>>> {
+        let initial_witness = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (Witness(i as u32), native_to_noir(value)))
+            .collect::<BTreeMap<_, _>>();
+        let mut witness_stack = Vec::new();
+        self.execute(0, WitnessMap::from(initial_witness), &mut witness_stack)?;
+        ensure!(
+            witness_stack.len() == self.call_witness_maps.len() + 1,
+            "ACVM executed {} functions, expected {}",
+            witness_stack.len(),
+            self.call_witness_maps.len() + 1
+        );
+
+        let mut witness = vec![None; r1cs_witnesses];
+        witness[0] = Some(FieldElement::ONE);
+        let maps = self
+            .call_witness_maps
+            .iter()
+            .map(|call| (call.function, &call.witness_map))
+            .chain(iter::once((0, &self.witness_map)));
+        for ((function, acir_witness), (expected, witness_map)) in
+            witness_stack.into_iter().zip(maps)
+        {
+            ensure!(
+                function == expected,
+                "ACVM executed function {function}, expected {expected}"
+            );
+            for (acir_index, value) in acir_witness {
+                let Some(&Some(index)) = witness_map.get(acir_index.as_usize()) else {
+                    continue;
+                };
+                let value = noir_to_native(value);
+                let entry = &mut witness[index.get() as usize];
+                ensure!(
+                    entry.is_none_or(|existing| existing == value),
+                    "Conflicting values for R1CS witness {index}"
+                );
+                *entry = Some(value);
+            }
+        }
+        Ok(witness)
+    }
+
+    /// Solve an ACIR function with the ACVM, recursively solving calls. Pushes
+    /// the solved witnesses of every function to `witness_stack` in the order
+    /// the functions complete.
+    fn execute(
+        &self,
+        function: u32,
+        initial_witness: WitnessMap<NoirElement>,
+        witness_stack: &mut Vec<(u32, WitnessMap<NoirElement>)>,
+    ) -> Result<WitnessMap<NoirElement>> {
+        let circuit = self
+            .program
+            .functions
+            .get(function as usize)
+            .ok_or_else(|| anyhow!("Unknown ACIR function {function}"))?;
+        let solver = Bn254BlackBoxSolver(false);
+        let mut acvm = ACVM::new(
+            &solver,
+            &circuit.opcodes,
+            initial_witness,
+            &self.program.unconstrained_functions,
+            &circuit.assert_messages,
+        );
+        loop {
+            match acvm.solve() {
+                ACVMStatus::Solved => break,
+                ACVMStatus::InProgress => unreachable!("ACVM returned while in progress"),
+                ACVMStatus::Failure(error) => {
+                    bail!("ACVM failed in ACIR function {function}: {error}")
+                }
+                ACVMStatus::RequiresForeignCall(call) => {
+                    // Printing is the only oracle without a result
+                    ensure!(
+                        call.function == "print",
+                        "Unsupported foreign call {}",
+                        call.function
+                    );
+                    acvm.resolve_pending_foreign_call(ForeignCallResult::default());
+                }
+                ACVMStatus::RequiresAcirCall(call) => {
+                    let callee = call.id.0;
+                    let witness = self.execute(callee, call.initial_witness, witness_stack)?;
+                    let return_values = self.program.functions[callee as usize]
+                        .return_values
+                        .0
+                        .iter()
+                        .map(|w| {
+                            witness.get(w).copied().ok_or_else(|| {
+                                anyhow!("Return value {w} of ACIR function {callee} is unsolved")
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    acvm.resolve_pending_acir_call(return_values);
+                }
+            }
+        }
+        let witness = acvm.finalize();
+        witness_stack.push((function, witness.clone()));
+        Ok(witness)
+    }
+
+    /// Parse inputs in TOML, as in a `Prover.toml` file, or JSON into a map
+    /// from parameter name to value, typed by the program ABI.
+    #[instrument(skip_all, fields(size = input.len()))]
+    pub fn parse_input(&self, format: Format, input: &str) -> Result<InputMap> {
+        format
+            .parse(input, &self.abi)
+            .with_context(|| format!("while parsing input {}", format.ext()))
+    }
+
+    /// Encode inputs given as TOML.
     pub fn input_from_toml(&self, toml: &str) -> Result<Vec<FieldElement>> {
-        // Parse toml to name -> value map
-        let mut input = Format::Toml
-            .parse(toml, &self.abi)
-            .context("while parsing input toml")?;
+        self.input_from_map(self.parse_input(Format::Toml, toml)?)
+    }
+
+    /// Encode inputs given as a JSON object from parameter name to value.
+    pub fn input_from_json(&self, json: &str) -> Result<Vec<FieldElement>> {
+        self.input_from_map(self.parse_input(Format::Json, json)?)
+    }
 
+    /// Encode a map from parameter name to value as the witness values of
+    /// the program inputs. Noir inputs are in order at the start of the
+    /// witness vector.
+    pub fn input_from_map(&self, mut input: InputMap) -> Result<Vec<FieldElement>> {
         // Prepare witness vector
         let num_inputs = self.abi.field_count() as usize;
         let mut inputs = Vec::with_capacity(num_inputs);
@@ -68,8 +228,7 @@ impl NoirWitnessGenerator {
         for param in self.abi.parameters.iter() {
             let value = input
                 .remove(&param.name)
-                .ok_or_else(|| anyhow!("Missing input {}", &param.name))?
-                .clone();
+                .ok_or_else(|| anyhow!("Missing input {}", &param.name))?;
             encode_input(&mut inputs, value, &param.typ)
                 .with_context(|| format!("while encoding input for {}", &param.name))?;
         }
@@ -84,7 +243,9 @@ impl NoirWitnessGenerator {
 impl PartialEq for NoirWitnessGenerator {
     fn eq(&self, other: &Self) -> bool {
         format!("{:?}", self.abi) == format!("{:?}", other.abi)
+            && self.program == other.program
             && self.witness_map == other.witness_map
+            && self.call_witness_maps == other.call_witness_maps
     }
 }
 
@@ -97,12 +258,35 @@ fn encode_input(
 ) -> Result<()> {
     match (value, abi_type) {
         (InputValue::Field(elem), AbiType::Field) => input.push(noir_to_native(elem)),
-        (InputValue::Vec(vec_elements), AbiType::Array { typ, .. }) => {
+        (InputValue::Field(elem), AbiType::Integer { sign, width }) => {
+            input.push(encode_integer(noir_to_native(elem), *sign, *width)?)
+        }
+        (InputValue::Field(elem), AbiType::Boolean) => {
+            let value = noir_to_native(elem);
+            ensure!(
+                value.is_zero() || value.is_one(),
+                "Boolean input must be 0 or 1, got {}",
+                signed(value)
+            );
+            input.push(value);
+        }
+        (InputValue::Vec(vec_elements), AbiType::Array { length, typ }) => {
+            ensure!(
+                vec_elements.len() == *length as usize,
+                "Array length {} does not match expected length {length}",
+                vec_elements.len()
+            );
             for elem in vec_elements {
                 encode_input(input, elem, typ)?;
             }
         }
         (InputValue::Vec(vec_elements), AbiType::Tuple { fields }) => {
+            ensure!(
+                vec_elements.len() == fields.len(),
+                "Tuple length {} does not match expected length {}",
+                vec_elements.len(),
+                fields.len()
+            );
             for (value, typ) in vec_elements.into_iter().zip(fields) {
                 encode_input(input, value, typ)?;
             }
@@ -134,3 +318,180 @@ fn encode_input(
     }
     Ok(())
 }
+
+/// Check that an integer input fits its type and encode it the way Noir does.
+/// Signed integers are stored in two's complement. The ABI parsers already
+/// produce that encoding, but a negative field element `-v` with
+/// `v <= 2^(width-1)` is converted as well.
+fn encode_integer(value: FieldElement, sign: Sign, width: u32) -> Result<FieldElement> {
+    let bound = BigUint::from(1u8) << width;
+    let unsigned = BigUint::from(value.into_bigint());
+    match sign {
+        Sign::Unsigned => {
+            ensure!(
+                unsigned < bound,
+                "Input {} does not fit in u{width}",
+                signed(value)
+            );
+            Ok(value)
+        }
+        Sign::Signed => {
+            if unsigned < bound {
+                return Ok(value);
+            }
+            let magnitude = BigUint::from((-value).into_bigint());
+            ensure!(
+                magnitude <= bound >> 1,
+                "Input {} does not fit in i{width}",
+                signed(value)
+            );
+            Ok(FieldElement::from(bound - magnitude))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::circuit::Program;
+    use noirc_abi::{input_parser::InputValue, InputMap, Sign};
+    use serde_json::json;
+
+    use super::{encode_integer, NoirWitnessGenerator};
+    use crate::{FieldElement, NoirElement};
+
+    /// A witness generator for a program without opcodes taking the given
+    /// ABI parameters, enough to encode inputs.
+    fn generator(parameters: serde_json::Value) -> NoirWitnessGenerator {
+        let abi = json!({ "parameters": parameters, "return_type": null, "error_types": {} });
+        NoirWitnessGenerator {
+            abi: serde_json::from_value(abi).unwrap(),
+            program: Program {
+                functions: Vec::new(),
+                unconstrained_functions: Vec::new(),
+            },
+            witness_map: Vec::new(),
+            call_witness_maps: Vec::new(),
+        }
+    }
+
+    fn parameter(name: &str, typ: serde_json::Value) -> serde_json::Value {
+        json!({ "name": name, "type": typ, "visibility": "private" })
+    }
+
+    fn field(value: u128) -> InputValue {
+        InputValue::Field(NoirElement::from(value))
+    }
+
+    #[test]
+    fn test_encode_integer() {
+        let int = |value: i64| FieldElement::from(value);
+        assert_eq!(
+            encode_integer(int(255), Sign::Unsigned, 8).unwrap(),
+            int(255)
+        );
+        assert!(encode_integer(int(256), Sign::Unsigned, 8).is_err());
+        assert!(encode_integer(int(-1), Sign::Unsigned, 8).is_err());
+
+        // Negative values are encoded in two's complement
+        assert_eq!(encode_integer(int(-1), Sign::Signed, 8).unwrap(), int(255));
+        assert_eq!(
+            encode_integer(int(-128), Sign::Signed, 8).unwrap(),
+            int(128)
+        );
+        assert_eq!(encode_integer(int(127), Sign::Signed, 8).unwrap(), int(127));
+        assert!(encode_integer(int(-129), Sign::Signed, 8).is_err());
+
+        // Values already in two's complement are kept
+        assert_eq!(encode_integer(int(200), Sign::Signed, 8).unwrap(), int(200));
+        assert!(encode_integer(int(256), Sign::Signed, 8).is_err());
+    }
+
+    #[test]
+    fn test_input_from_json() {
+        let generator = generator(json!([
+            parameter("x", json!({ "kind": "field" })),
+            parameter(
+                "ys",
+                json!({ "kind": "array", "length": 2, "type": { "kind": "field" } })
+            ),
+            parameter("flag", json!({ "kind": "boolean" })),
+        ]));
+        let input = generator
+            .input_from_json(r#"{ "x": "3", "ys": ["1", "2"], "flag": true }"#)
+            .unwrap();
+        assert_eq!(input, [3u64, 1, 2, 1].map(FieldElement::from));
+
+        assert!(generator.input_from_json(r#"{ "x": "3" }"#).is_err());
+    }
+
+    #[test]
+    fn test_negative_input() {
+        let generator = generator(json!([parameter(
+            "x",
+            json!({ "kind": "integer", "sign": "signed", "width": 8 })
+        )]));
+        let minus_one = [FieldElement::from(255u64)];
+        assert_eq!(generator.input_from_toml(r#"x = "-1""#).unwrap(), minus_one);
+        assert_eq!(
+            generator.input_from_json(r#"{ "x": "-1" }"#).unwrap(),
+            minus_one
+        );
+        assert_eq!(
+            generator.input_from_toml(r#"x = "-128""#).unwrap(),
+            [FieldElement::from(128u64)]
+        );
+    }
+
+    #[test]
+    fn test_input_from_map() {
+        let generator = generator(json!([
+            parameter(
+                "ys",
+                json!({ "kind": "array", "length": 2, "type": { "kind": "field" } })
+            ),
+            parameter(
+                "pair",
+                json!({
+                    "kind": "tuple",
+                    "fields": [{ "kind": "boolean" }, { "kind": "integer", "sign": "unsigned", "width": 8 }],
+                })
+            ),
+        ]));
+        let input = |ys: Vec<InputValue>, pair: Vec<InputValue>| {
+            let mut map = InputMap::new();
+            map.insert("ys".to_string(), InputValue::Vec(ys));
+            map.insert("pair".to_string(), InputValue::Vec(pair));
+            map
+        };
+        assert_eq!(
+            generator
+                .input_from_map(input(vec![field(4), field(5)], vec![field(1), field(255)]))
+                .unwrap(),
+            [4u64, 5, 1, 255].map(FieldElement::from)
+        );
+
+        // Booleans must be 0 or 1
+        let error = generator
+            .input_from_map(input(vec![field(4), field(5)], vec![field(2), field(255)]))
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("Boolean input must be 0 or 1, got 2"));
+
+        // Array and tuple lengths must match the ABI
+        let error = generator
+            .input_from_map(input(vec![field(4)], vec![field(1), field(255)]))
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("Array length 1 does not match expected length 2"));
+        let error = generator
+            .input_from_map(input(vec![field(4), field(5)], vec![field(1)]))
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("Tuple length 1 does not match expected length 2"));
+
+        // Missing and extra inputs
+        let mut map = input(vec![field(4), field(5)], vec![field(1), field(255)]);
+        map.insert("z".to_string(), field(0));
+        assert!(generator.input_from_map(map.clone()).is_err());
+        map.remove("ys");
+        map.remove("z");
+        assert!(generator.input_from_map(map).is_err());
+    }
+}
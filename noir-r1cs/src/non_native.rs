@@ -0,0 +1,493 @@
+//! Arithmetic over fields other than BN254 `Fr` using 64-bit limbs.
+//!
+//! An [`Element`] is the integer `Σ limbs[k] 2^(64 k)` with every limb range
+//! checked to 64 bits. Elements are kept below `2^256` but are not necessarily
+//! reduced. A relation `Σ terms ≡ r (mod m)` is proven by hinting the quotient
+//! `q`, range checking it, and showing that the integer `Σ terms - q m - r` is
+//! zero. The integer is laid out in signed positions of 64 bits, and a chain
+//! of range checked carries shows that it vanishes without wrapping around the
+//! native modulus.
+
+use std::iter;
+
+use ark_std::{One, Zero};
+use num_bigint::BigUint;
+
+use crate::{
+    bitwise::{self, add_is_zero, scale},
+    range_check::{self, power_of_two},
+    FieldElement, Hint, LinearCombination, R1CSBuilder,
+};
+
+/// Number of limbs in an element.
+pub(crate) const LIMBS: usize = 4;
+
+/// Bits per limb.
+pub(crate) const LIMB_BITS: u32 = 64;
+
+/// Carries are range checked to this many bits after adding `2^(CARRY_BITS -
+/// 1)`. Positions are below `2^136`, so carries are below `2^72`.
+const CARRY_BITS: u32 = 80;
+
+/// Digit size used to range check limbs and carries.
+const LOG_BASE: u32 = 2;
+
+/// Little-endian 64-bit limbs of a non-native field element.
+pub(crate) type Element = [LinearCombination; LIMBS];
+
+/// A summand of a non-native relation.
+pub(crate) enum Term<'a> {
+    /// `c · a · b`
+    Product(i64, &'a Element, &'a Element),
+    /// `c · a`
+    Linear(i64, &'a Element),
+    /// A non-negative constant.
+    Constant(&'a BigUint),
+}
+
+/// Arithmetic modulo a fixed `modulus` below `2^256`.
+pub(crate) struct NonNativeField {
+    modulus: BigUint,
+    witness_one: usize,
+}
+
+/// A signed integer `Σ limbs[k] 2^(64 k)` bounded by `-negative ≤ value ≤
+/// positive`.
+#[derive(Clone, Default)]
+struct Positions {
+    limbs: Vec<LinearCombination>,
+    positive: BigUint,
+    negative: BigUint,
+}
+
+impl Positions {
+    fn add(&mut self, k: usize, lc: impl IntoIterator<Item = (FieldElement, usize)>) {
+        if self.limbs.len() <= k {
+            self.limbs.resize(k + 1, vec![]);
+        }
+        self.limbs[k].extend(lc);
+    }
+
+    fn add_bound(&mut self, coefficient: i64, bound: BigUint) {
+        let bound = bound * coefficient.unsigned_abs();
+        if coefficient < 0 {
+            self.negative += bound;
+        } else {
+            self.positive += bound;
+        }
+    }
+
+    fn add_constant(&mut self, value: &BigUint, witness_one: usize) {
+        for (k, limb) in value.to_u64_digits().into_iter().enumerate() {
+            self.add(k, [(FieldElement::from(limb), witness_one)]);
+        }
+        self.positive += value;
+    }
+
+    fn append(&mut self, other: Positions) {
+        for (k, limb) in other.limbs.into_iter().enumerate() {
+            self.add(k, limb);
+        }
+        self.positive += other.positive;
+        self.negative += other.negative;
+    }
+
+    fn neg(self) -> Positions {
+        Positions {
+            limbs: self
+                .limbs
+                .iter()
+                .map(|limb| scale(limb, -FieldElement::one()))
+                .collect(),
+            positive: self.negative,
+            negative: self.positive,
+        }
+    }
+}
+
+impl NonNativeField {
+    pub fn new(modulus: BigUint, witness_one: usize) -> Self {
+        assert!(modulus.bits() <= 256, "Modulus must fit in 256 bits");
+        Self {
+            modulus,
+            witness_one,
+        }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    /// A constant element, reduced modulo the modulus.
+    pub fn constant(&self, value: &BigUint) -> Element {
+        let digits = (value % &self.modulus).to_u64_digits();
+        std::array::from_fn(|k| {
+            let limb = digits.get(k).copied().unwrap_or_default();
+            vec![(FieldElement::from(limb), self.witness_one)]
+        })
+    }
+
+    /// An element from 32 big-endian bytes. The bytes must be range checked by
+    /// the caller.
+    pub fn from_bytes_be(&self, bytes: &[LinearCombination]) -> Element {
+        assert_eq!(bytes.len(), 32, "Elements are 32 bytes");
+        std::array::from_fn(|k| {
+            let limb = (0..8)
+                .map(|i| bytes[31 - 8 * k - i].clone())
+                .collect::<Vec<_>>();
+            range_check::recompose(&limb, 8)
+        })
+    }
+
+    /// Little-endian bits of an element.
+    pub fn to_bits(&self, r1cs: &mut R1CSBuilder, element: &Element) -> Vec<LinearCombination> {
+        element
+            .iter()
+            .flat_map(|limb| {
+                range_check::decompose(r1cs, self.witness_one, limb, LIMB_BITS, 1)
+                    .into_iter()
+                    .map(|bit| vec![(FieldElement::one(), bit)])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// `a` if the boolean `bit` is one and `b` otherwise.
+    pub fn select(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        bit: &LinearCombination,
+        a: &Element,
+        b: &Element,
+    ) -> Element {
+        std::array::from_fn(|k| {
+            let mut difference = a[k].clone();
+            difference.extend(scale(&b[k], -FieldElement::one()));
+            let mut limb = bitwise::add_product(r1cs, self.witness_one, bit, &difference);
+            limb.extend(b[k].iter().copied());
+            limb
+        })
+    }
+
+    /// A new element congruent to `Σ terms`.
+    pub fn reduce(&self, r1cs: &mut R1CSBuilder, terms: &[Term]) -> Element {
+        let positions = self.positions(r1cs, terms);
+        self.add_congruence(r1cs, positions, None)
+    }
+
+    /// A new element congruent to `numerator / Σ c_i e_i` for the linear
+    /// `denominator` terms `(c_i, e_i)`. The modulus must be prime. A zero
+    /// denominator makes the constraints unsatisfiable unless the numerator is
+    /// zero too, in which case the result is unconstrained.
+    pub fn divide(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        numerator: &[Term],
+        denominator: &[(i64, &Element)],
+    ) -> Element {
+        let numerator = self.positions(r1cs, numerator);
+        let linear = denominator
+            .iter()
+            .map(|&(c, e)| Term::Linear(c, e))
+            .collect::<Vec<_>>();
+        let denominator_positions = self.positions(r1cs, &linear);
+
+        let outputs = self.new_limbs(r1cs, LIMBS);
+        r1cs.add_hint(Hint::ModularDivision {
+            numerator: numerator.limbs.clone(),
+            denominator: denominator_positions.limbs,
+            limb_bits: LIMB_BITS,
+            modulus: self.modulus.to_u64_digits(),
+            outputs: outputs.clone(),
+        });
+        let quotient = self.range_checked(r1cs, &outputs);
+
+        let products = denominator
+            .iter()
+            .map(|&(c, e)| Term::Product(c, &quotient, e))
+            .collect::<Vec<_>>();
+        let mut positions = self.positions(r1cs, &products);
+        positions.append(numerator.neg());
+        let zero = self.constant(&BigUint::zero());
+        self.add_congruence(r1cs, positions, Some(&zero));
+        quotient
+    }
+
+    /// Constrain `Σ c_i e_i ≢ 0` for linear terms `(c_i, e_i)`. The modulus
+    /// must be prime.
+    pub fn assert_nonzero(&self, r1cs: &mut R1CSBuilder, terms: &[(i64, &Element)]) {
+        let one = BigUint::one();
+        self.divide(r1cs, &[Term::Constant(&one)], terms);
+    }
+
+    /// Constrain the integer value of `element` to be less than `bound`.
+    pub fn assert_less_than(&self, r1cs: &mut R1CSBuilder, element: &Element, bound: &BigUint) {
+        assert!(!bound.is_zero(), "Bound must be positive");
+        let max = bound - 1u32;
+        let mut positions = self.positions(r1cs, &[Term::Linear(-1, element)]);
+        positions.add_constant(&max, self.witness_one);
+
+        // The difference `max - element` is non-negative and fits in 256 bits.
+        let difference = self.new_limbs(r1cs, LIMBS);
+        r1cs.add_hint(Hint::ModularReduction {
+            value: positions.limbs.clone(),
+            limb_bits: LIMB_BITS,
+            modulus: (BigUint::one() << 256u32).to_u64_digits(),
+            quotient: vec![],
+            remainder: difference.clone(),
+        });
+        let difference = self.range_checked(r1cs, &difference);
+        positions.append(self.positions(r1cs, &[Term::Linear(-1, &difference)]));
+        self.add_identity(r1cs, positions.limbs);
+    }
+
+    /// A boolean that is one if and only if the integer value of `element` is
+    /// less than `bound`.
+    pub fn is_less_than(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        element: &Element,
+        bound: &BigUint,
+    ) -> LinearCombination {
+        let one = FieldElement::one();
+        let limit = BigUint::one() << 256u32;
+        assert!(bound <= &limit, "Bound must fit in 256 bits");
+
+        // `element - bound + 2^256 = 2^256 geq + difference` with a boolean
+        // `geq` and a difference that fits in 256 bits.
+        let mut positions = self.positions(r1cs, &[Term::Linear(1, element)]);
+        positions.add_constant(&(&limit - bound), self.witness_one);
+        let geq = r1cs.new_witness();
+        let difference = self.new_limbs(r1cs, LIMBS);
+        r1cs.add_hint(Hint::ModularReduction {
+            value: positions.limbs.clone(),
+            limb_bits: LIMB_BITS,
+            modulus: limit.to_u64_digits(),
+            quotient: vec![geq],
+            remainder: difference.clone(),
+        });
+        r1cs.add_constraint(&[(one, geq)], &[(one, geq)], &[(one, geq)]);
+        let difference = self.range_checked(r1cs, &difference);
+        positions.append(self.positions(r1cs, &[Term::Linear(-1, &difference)]));
+        positions.add(LIMBS, [(-one, geq)]);
+        self.add_identity(r1cs, positions.limbs);
+        vec![(one, self.witness_one), (-one, geq)]
+    }
+
+    /// A boolean that is one if and only if the integer value of `element` is
+    /// zero.
+    pub fn is_zero_integer(&self, r1cs: &mut R1CSBuilder, element: &Element) -> LinearCombination {
+        // The limbs are non-negative, so their sum is zero exactly when all
+        // of them are, and it is far too small to wrap around.
+        add_is_zero(r1cs, self.witness_one, &element.concat())
+    }
+
+    /// A boolean that is one if and only if `Σ terms ≡ 0`.
+    pub fn is_zero(&self, r1cs: &mut R1CSBuilder, terms: &[Term]) -> LinearCombination {
+        let reduced = self.reduce(r1cs, terms);
+        self.assert_less_than(r1cs, &reduced, &self.modulus);
+        self.is_zero_integer(r1cs, &reduced)
+    }
+
+    /// Lay out `Σ terms` in signed positions, adding constraints for products.
+    fn positions(&self, r1cs: &mut R1CSBuilder, terms: &[Term]) -> Positions {
+        let limb_bound = BigUint::one() << 256u32;
+        let mut positions = Positions::default();
+        for term in terms {
+            match *term {
+                Term::Product(c, a, b) => {
+                    let square = std::ptr::eq(a, b);
+                    for i in 0..LIMBS {
+                        for j in 0..LIMBS {
+                            // Squares reuse the symmetric products.
+                            let weight = match (square, i.cmp(&j)) {
+                                (true, std::cmp::Ordering::Greater) => continue,
+                                (true, std::cmp::Ordering::Less) => 2 * c,
+                                _ => c,
+                            };
+                            let product =
+                                bitwise::add_product(r1cs, self.witness_one, &a[i], &b[j]);
+                            positions.add(i + j, scale(&product, FieldElement::from(weight)));
+                        }
+                    }
+                    positions.add_bound(c, &limb_bound * &limb_bound);
+                }
+                Term::Linear(c, a) => {
+                    for (k, limb) in a.iter().enumerate() {
+                        positions.add(k, scale(limb, FieldElement::from(c)));
+                    }
+                    positions.add_bound(c, limb_bound.clone());
+                }
+                Term::Constant(value) => positions.add_constant(value, self.witness_one),
+            }
+        }
+        positions
+    }
+
+    /// Constrain `positions ≡ remainder` for a given remainder, or for a new
+    /// hinted remainder which is returned.
+    fn add_congruence(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        mut positions: Positions,
+        remainder: Option<&Element>,
+    ) -> Element {
+        if let Some(remainder) = remainder {
+            positions.append(self.positions(r1cs, &[Term::Linear(-1, remainder)]));
+        }
+
+        // Offset by a multiple of the modulus to make the value non-negative.
+        if !positions.negative.is_zero() {
+            let multiple = (&positions.negative + &self.modulus - 1u32) / &self.modulus;
+            positions.add_constant(&(multiple * &self.modulus), self.witness_one);
+        }
+        let quotient_bits = (&positions.positive / &self.modulus).bits() as usize;
+        let quotient_limbs = quotient_bits.div_ceil(LIMB_BITS as usize).max(1);
+
+        let quotient = self.new_limbs(r1cs, quotient_limbs);
+        let hinted = match remainder {
+            Some(_) => vec![],
+            None => self.new_limbs(r1cs, LIMBS),
+        };
+        r1cs.add_hint(Hint::ModularReduction {
+            value: positions.limbs.clone(),
+            limb_bits: LIMB_BITS,
+            modulus: self.modulus.to_u64_digits(),
+            quotient: quotient.clone(),
+            remainder: hinted.clone(),
+        });
+        for &limb in &quotient {
+            self.add_limb_check(r1cs, &[(FieldElement::one(), limb)], LIMB_BITS);
+        }
+        let remainder = match remainder {
+            Some(remainder) => remainder.clone(),
+            None => {
+                let remainder = self.range_checked(r1cs, &hinted);
+                positions.append(self.positions(r1cs, &[Term::Linear(-1, &remainder)]));
+                remainder
+            }
+        };
+
+        // Subtract `quotient · modulus`
+        for (j, digit) in self.modulus.to_u64_digits().into_iter().enumerate() {
+            for (i, &limb) in quotient.iter().enumerate() {
+                positions.add(i + j, [(-FieldElement::from(digit), limb)]);
+            }
+        }
+        self.add_identity(r1cs, positions.limbs);
+        remainder
+    }
+
+    /// Constrain the integer `Σ positions[k] 2^(64 k)` to be zero through a
+    /// chain of carries `positions[k] + c[k - 1] = c[k] 2^64`.
+    fn add_identity(&self, r1cs: &mut R1CSBuilder, positions: Vec<LinearCombination>) {
+        let carries = (1..positions.len())
+            .map(|_| r1cs.new_witness())
+            .collect::<Vec<_>>();
+        r1cs.add_hint(Hint::Carries {
+            value: positions.clone(),
+            limb_bits: LIMB_BITS,
+            carry_bits: CARRY_BITS,
+            outputs: carries.clone(),
+        });
+        let offset = power_of_two(CARRY_BITS - 1);
+        let carry = |k: usize| -> LinearCombination {
+            match carries.get(k) {
+                Some(&carry) => vec![(FieldElement::one(), carry), (-offset, self.witness_one)],
+                None => vec![],
+            }
+        };
+        for &witness in &carries {
+            self.add_limb_check(r1cs, &[(FieldElement::one(), witness)], CARRY_BITS);
+        }
+        for (k, position) in positions.into_iter().enumerate() {
+            let mut linear = position;
+            if k > 0 {
+                linear.extend(carry(k - 1));
+            }
+            linear.extend(scale(&carry(k), -power_of_two(LIMB_BITS)));
+            r1cs.add_constraint(&[], &[], &linear);
+        }
+    }
+
+    fn new_limbs(&self, r1cs: &mut R1CSBuilder, count: usize) -> Vec<usize> {
+        iter::repeat_with(|| r1cs.new_witness())
+            .take(count)
+            .collect()
+    }
+
+    /// Range check hinted limbs and collect them into an element.
+    fn range_checked(&self, r1cs: &mut R1CSBuilder, limbs: &[usize]) -> Element {
+        std::array::from_fn(|k| {
+            let limb = vec![(FieldElement::one(), limbs[k])];
+            self.add_limb_check(r1cs, &limb, LIMB_BITS);
+            limb
+        })
+    }
+
+    fn add_limb_check(
+        &self,
+        r1cs: &mut R1CSBuilder,
+        value: &[(FieldElement, usize)],
+        num_bits: u32,
+    ) {
+        range_check::decompose(r1cs, self.witness_one, value, num_bits, LOG_BASE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiply_and_divide() {
+        // secp256k1 base field
+        let modulus = BigUint::parse_bytes(
+            b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            16,
+        )
+        .unwrap();
+        let mut r1cs = R1CSBuilder::new();
+        let one = r1cs.new_witness();
+        let field = NonNativeField::new(modulus.clone(), one);
+        let a_limbs = field.new_limbs(&mut r1cs, LIMBS);
+        let b_limbs = field.new_limbs(&mut r1cs, LIMBS);
+        let a = field.range_checked(&mut r1cs, &a_limbs);
+        let b = field.range_checked(&mut r1cs, &b_limbs);
+        let product = field.reduce(&mut r1cs, &[Term::Product(1, &a, &b), Term::Linear(-3, &a)]);
+        let quotient = field.divide(&mut r1cs, &[Term::Linear(1, &a)], &[(1, &b), (-1, &a)]);
+        field.assert_less_than(&mut r1cs, &product, &modulus);
+        let r1cs = r1cs.build();
+
+        let a_value = &modulus - 5u32;
+        let b_value = (BigUint::one() << 255u32) + 12345u32;
+        let mut witness = vec![None; r1cs.witnesses];
+        witness[one] = Some(FieldElement::one());
+        for (limbs, value) in [(&a_limbs, &a_value), (&b_limbs, &b_value)] {
+            let digits = value.to_u64_digits();
+            for (&limb, &digit) in limbs.iter().zip(digits.iter()) {
+                witness[limb] = Some(FieldElement::from(digit));
+            }
+        }
+        r1cs.solve_witness(&mut witness).unwrap();
+        let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        r1cs.verify_witness(&witness).unwrap();
+
+        let evaluate = |element: &Element| {
+            element.iter().rev().fold(BigUint::zero(), |acc, limb| {
+                let value = limb
+                    .iter()
+                    .fold(FieldElement::zero(), |acc, &(c, w)| acc + c * witness[w]);
+                (acc << 64u32) + BigUint::from(value)
+            })
+        };
+        let three_a = (&a_value * 3u32) % &modulus;
+        let expected = (&a_value * &b_value + &modulus - three_a) % &modulus;
+        assert_eq!(evaluate(&product), expected);
+        let denominator = (&b_value + &modulus - &a_value) % &modulus;
+        assert_eq!(
+            (evaluate(&quotient) * denominator) % &modulus,
+            &a_value % &modulus
+        );
+    }
+}
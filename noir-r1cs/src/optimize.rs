@@ -0,0 +1,473 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem,
+    num::NonZeroU32,
+};
+
+use ark_ff::Field;
+use ark_std::{One, Zero};
+use tracing::{info, instrument};
+
+use crate::{
+    r1cs::merge_terms, CallWitnessMap, FieldElement, Hint, LinearCombination, R1CSBuilder,
+    SourceMap, R1CS,
+};
+
+/// Shrink an R1CS instance without changing its solutions.
+///
+/// * Rows that are linear are removed by substituting one of their witnesses
+///   into the remaining rows.
+/// * Rows that compute the same product as an earlier row are removed and
+///   their output is replaced by the earlier one.
+/// * Witnesses that no longer occur are dropped and the rest renumbered.
+///
+/// Witnesses are only eliminated where the row-by-row solver can still solve
+/// the result, given the values the ACVM provides through `witness_maps`. The
+/// witness maps are rewritten to the new numbering. The constant one and the
+/// public values keep their positions. A source map, if given, is restricted
+/// to the remaining rows.
+#[instrument(skip_all, fields(constraints = r1cs.constraints, witnesses = r1cs.witnesses))]
+pub fn optimize_r1cs(
+    r1cs: &R1CS,
+    witness_maps: &mut [CallWitnessMap],
+    source_map: Option<&mut SourceMap>,
+) -> R1CS {
+    let mut optimizer = Optimizer::new(r1cs, witness_maps);
+    optimizer.deduplicate_products();
+    optimizer.eliminate_linear();
+    if let Some(source_map) = source_map {
+        let rows = (0..optimizer.rows.len())
+            .filter(|&row| optimizer.rows[row].is_some())
+            .collect::<Vec<_>>();
+        source_map.select_rows(&rows);
+    }
+    let result = optimizer.finalize(witness_maps);
+    info!(
+        "Optimized R1CS to {} constraints, {} witnesses",
+        result.constraints, result.witnesses
+    );
+    result
+}
+
+struct Row {
+    a: LinearCombination,
+    b: LinearCombination,
+    c: LinearCombination,
+}
+
+struct Optimizer {
+    public_inputs: usize,
+    witnesses: usize,
+    /// Constraint rows, `None` once removed.
+    rows: Vec<Option<Row>>,
+    hints: Vec<(usize, Hint)>,
+    /// When the solver learns each witness: `2 row` for hints run before
+    /// `row`, `2 row + 1` for solving `row` itself and zero for the witnesses
+    /// provided by the ACVM. `None` if the solver never learns it.
+    known_at: Vec<Option<usize>>,
+    /// Rows and hints that may contain each witness, possibly stale or
+    /// repeated.
+    row_uses: Vec<Vec<usize>>,
+    hint_uses: Vec<Vec<usize>>,
+}
+
+impl Optimizer {
+    fn new(r1cs: &R1CS, witness_maps: &[CallWitnessMap]) -> Self {
+        let lc = |row: &mut dyn Iterator<Item = (usize, FieldElement)>| {
+            row.map(|(col, coeff)| (coeff, col)).collect::<Vec<_>>()
+        };
+        let rows = (0..r1cs.constraints)
+            .map(|row| {
+                Some(Row {
+                    a: lc(&mut r1cs.a().iter_row(row)),
+                    b: lc(&mut r1cs.b().iter_row(row)),
+                    c: lc(&mut r1cs.c().iter_row(row)),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut row_uses = vec![Vec::new(); r1cs.witnesses];
+        for (i, row) in rows.iter().enumerate() {
+            let row = row.as_ref().unwrap();
+            for &(_, col) in row.a.iter().chain(&row.b).chain(&row.c) {
+                if row_uses[col].last() != Some(&i) {
+                    row_uses[col].push(i);
+                }
+            }
+        }
+        let mut hints = r1cs.hints.clone();
+        let mut hint_uses = vec![Vec::new(); r1cs.witnesses];
+        for (i, (_, hint)) in hints.iter_mut().enumerate() {
+            for lc in hint.inputs_mut() {
+                for &(_, col) in lc.iter() {
+                    hint_uses[col].push(i);
+                }
+            }
+        }
+
+        let mut optimizer = Self {
+            public_inputs: r1cs.public_inputs,
+            witnesses: r1cs.witnesses,
+            rows,
+            hints,
+            known_at: vec![None; r1cs.witnesses],
+            row_uses,
+            hint_uses,
+        };
+        optimizer.simulate_solver(witness_maps);
+        optimizer
+    }
+
+    /// Determine when [`R1CS::solve_witness`] learns each witness.
+    fn simulate_solver(&mut self, witness_maps: &[CallWitnessMap]) {
+        let known_at = &mut self.known_at;
+        known_at[0] = Some(0);
+        for index in witness_maps
+            .iter()
+            .flat_map(|map| map.witness_map.iter())
+            .flatten()
+        {
+            known_at[index.get() as usize] = Some(0);
+        }
+
+        let mut hints = self.hints.iter_mut().peekable();
+        for (row, constraint) in self.rows.iter().enumerate() {
+            while let Some((_, hint)) = hints.next_if(|(hint_row, _)| *hint_row <= row) {
+                for output in hint.outputs_mut() {
+                    known_at[*output].get_or_insert(2 * row);
+                }
+            }
+            let constraint = constraint.as_ref().unwrap();
+            let unknown = |lc: &LinearCombination| {
+                lc.iter()
+                    .filter(|(_, col)| known_at[*col].is_none())
+                    .map(|&(_, col)| col)
+                    .collect::<Vec<_>>()
+            };
+            let unknown = [
+                unknown(&constraint.a),
+                unknown(&constraint.b),
+                unknown(&constraint.c),
+            ];
+            if let [col] = unknown.concat()[..] {
+                known_at[col] = Some(2 * row + 1);
+            }
+        }
+    }
+
+    fn is_public(&self, col: usize) -> bool {
+        col <= self.public_inputs
+    }
+
+    /// Remove rows computing the same product `A·z * B·z / γ` as an earlier row.
+    fn deduplicate_products(&mut self) {
+        let mut products =
+            HashMap::<(LinearCombination, LinearCombination, FieldElement), usize>::new();
+        for row in 0..self.rows.len() {
+            let Some(constraint) = &self.rows[row] else {
+                continue;
+            };
+            let &[(gamma, output)] = &constraint.c[..] else {
+                continue;
+            };
+            if self.is_public(output) || self.known_at[output] != Some(2 * row + 1) {
+                continue;
+            }
+            let (a, b) = (constraint.a.clone(), constraint.b.clone());
+            let key = if a <= b { (a, b, gamma) } else { (b, a, gamma) };
+            match products.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(output);
+                }
+                Entry::Occupied(entry) => {
+                    let existing = *entry.get();
+                    self.rows[row] = None;
+                    self.substitute(output, &[(FieldElement::one(), existing)]);
+                }
+            }
+        }
+    }
+
+    /// Remove linear rows by solving them for one of their witnesses.
+    fn eliminate_linear(&mut self) {
+        let mut pending = (0..self.rows.len()).rev().collect::<Vec<_>>();
+        while let Some(row) = pending.pop() {
+            let Some(lc) = self.rows[row].as_ref().and_then(linear_form) else {
+                continue;
+            };
+            if lc.is_empty() {
+                // Trivially satisfied
+                self.rows[row] = None;
+                continue;
+            }
+            let Some(col) = self.elimination_candidate(row, &lc) else {
+                continue;
+            };
+            let coeff = lc.iter().find(|(_, c)| *c == col).unwrap().0;
+            let scale = -coeff.inverse().unwrap();
+            let expr = lc
+                .iter()
+                .filter(|(_, c)| *c != col)
+                .map(|&(v, c)| (v * scale, c))
+                .collect::<Vec<_>>();
+            self.rows[row] = None;
+            let changed = self.substitute(col, &expr);
+            pending.extend(changed.into_iter().rev());
+        }
+    }
+
+    /// Pick the witness of a linear row to eliminate, if any can be eliminated
+    /// without growing the matrices or breaking the solver.
+    ///
+    /// Either the row solves for the witness and the others are known before
+    /// it, or all witnesses of the row are provided by the ACVM.
+    fn elimination_candidate(&mut self, row: usize, lc: &LinearCombination) -> Option<usize> {
+        let known_before = |col: usize| self.known_at[col].is_some_and(|t| t <= 2 * row);
+        let provided = |col: usize| self.known_at[col] == Some(0);
+        let candidates = lc
+            .iter()
+            .map(|&(_, col)| col)
+            .filter(|&col| !self.is_public(col))
+            .filter(|&col| {
+                let mut others = lc.iter().map(|&(_, c)| c).filter(|&c| c != col);
+                if self.known_at[col] == Some(2 * row + 1) {
+                    others.all(|c| known_before(c))
+                } else {
+                    provided(col) && others.all(|c| provided(c))
+                }
+            })
+            .collect::<Vec<_>>();
+        let (occurrences, col) = candidates
+            .into_iter()
+            .map(|col| (self.occurrences(col), col))
+            .min()?;
+        // Each occurrence trades one entry for `lc.len() - 1` entries, while
+        // removing the row saves `lc.len()` entries.
+        (occurrences * lc.len().saturating_sub(2) <= lc.len()).then_some(col)
+    }
+
+    /// Number of live rows containing the witness.
+    fn occurrences(&mut self, col: usize) -> usize {
+        let uses = &mut self.row_uses[col];
+        uses.sort_unstable();
+        uses.dedup();
+        uses.iter()
+            .filter_map(|&row| self.rows[row].as_ref())
+            .filter(|row| row_contains(row, col))
+            .count()
+    }
+
+    /// Replace a witness by a linear combination everywhere. Returns the rows
+    /// that changed.
+    fn substitute(&mut self, col: usize, expr: &[(FieldElement, usize)]) -> Vec<usize> {
+        let mut rows = mem::take(&mut self.row_uses[col]);
+        rows.sort_unstable();
+        rows.dedup();
+        rows.retain(|&row| {
+            let Some(constraint) = &mut self.rows[row] else {
+                return false;
+            };
+            let a = substitute_lc(&mut constraint.a, col, expr);
+            let b = substitute_lc(&mut constraint.b, col, expr);
+            let c = substitute_lc(&mut constraint.c, col, expr);
+            a || b || c
+        });
+        let mut hints = mem::take(&mut self.hint_uses[col]);
+        hints.sort_unstable();
+        hints.dedup();
+        for &hint in &hints {
+            for lc in self.hints[hint].1.inputs_mut() {
+                substitute_lc(lc, col, expr);
+            }
+        }
+        for &(_, other) in expr {
+            self.row_uses[other].extend(&rows);
+            self.hint_uses[other].extend(&hints);
+        }
+        rows
+    }
+
+    /// Build the optimized R1CS, dropping unused witnesses.
+    fn finalize(mut self, witness_maps: &mut [CallWitnessMap]) -> R1CS {
+        let mut used = vec![false; self.witnesses];
+        used[..=self.public_inputs].fill(true);
+        for row in self.rows.iter().flatten() {
+            for &(_, col) in row.a.iter().chain(&row.b).chain(&row.c) {
+                used[col] = true;
+            }
+        }
+        for (_, hint) in &mut self.hints {
+            for lc in hint.inputs_mut() {
+                for &(_, col) in lc.iter() {
+                    used[col] = true;
+                }
+            }
+            for output in hint.outputs_mut() {
+                used[*output] = true;
+            }
+        }
+
+        let mut r1cs = R1CSBuilder::new();
+        let remap = used
+            .iter()
+            .map(|&used| used.then(|| r1cs.new_witness()))
+            .collect::<Vec<_>>();
+        r1cs.public_inputs = self.public_inputs;
+        let remap_lc = |lc: &LinearCombination| {
+            lc.iter()
+                .map(|&(coeff, col)| (coeff, remap[col].unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut hints = self.hints.into_iter().peekable();
+        for (row, constraint) in self.rows.iter().enumerate() {
+            while let Some((_, mut hint)) = hints.next_if(|(hint_row, _)| *hint_row <= row) {
+                for lc in hint.inputs_mut() {
+                    *lc = remap_lc(lc);
+                }
+                for output in hint.outputs_mut() {
+                    *output = remap[*output].unwrap();
+                }
+                r1cs.add_hint(hint);
+            }
+            if let Some(constraint) = constraint {
+                r1cs.add_constraint(
+                    &remap_lc(&constraint.a),
+                    &remap_lc(&constraint.b),
+                    &remap_lc(&constraint.c),
+                );
+            }
+        }
+        for (_, mut hint) in hints {
+            for lc in hint.inputs_mut() {
+                *lc = remap_lc(lc);
+            }
+            for output in hint.outputs_mut() {
+                *output = remap[*output].unwrap();
+            }
+            r1cs.add_hint(hint);
+        }
+
+        for map in witness_maps {
+            for entry in &mut map.witness_map {
+                *entry = entry.and_then(|index| {
+                    remap[index.get() as usize].map(|i| NonZeroU32::new(i as u32).unwrap())
+                });
+            }
+        }
+        r1cs.build()
+    }
+}
+
+/// The linear combination that must vanish if the row is linear, i.e. if `A`
+/// or `B` is constant.
+fn linear_form(row: &Row) -> Option<LinearCombination> {
+    let constant = |lc: &LinearCombination| match lc[..] {
+        [] => Some(FieldElement::zero()),
+        [(coeff, 0)] => Some(coeff),
+        _ => None,
+    };
+    let (k, other) = if let Some(k) = constant(&row.a) {
+        (k, &row.b)
+    } else {
+        (constant(&row.b)?, &row.a)
+    };
+    let terms = other
+        .iter()
+        .map(|&(coeff, col)| (k * coeff, col))
+        .chain(row.c.iter().map(|&(coeff, col)| (-coeff, col)))
+        .collect::<Vec<_>>();
+    Some(to_lc(&terms))
+}
+
+fn row_contains(row: &Row, col: usize) -> bool {
+    [&row.a, &row.b, &row.c]
+        .into_iter()
+        .any(|lc| lc.iter().any(|&(_, c)| c == col))
+}
+
+/// Replace `col` by `expr` in a linear combination. Returns whether it occurred.
+fn substitute_lc(lc: &mut LinearCombination, col: usize, expr: &[(FieldElement, usize)]) -> bool {
+    let Some(i) = lc.iter().position(|&(_, c)| c == col) else {
+        return false;
+    };
+    let (coeff, _) = lc.remove(i);
+    lc.extend(expr.iter().map(|&(v, c)| (coeff * v, c)));
+    *lc = to_lc(lc);
+    true
+}
+
+fn to_lc(terms: &[(FieldElement, usize)]) -> LinearCombination {
+    merge_terms(terms)
+        .into_iter()
+        .map(|(col, coeff)| (coeff, col))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, slice};
+
+    use acir::{
+        circuit::{Circuit, Opcode, PublicInputs},
+        native_types::{Expression, Witness},
+        AcirField,
+    };
+
+    use super::optimize_r1cs;
+    use crate::{noir_program_to_r1cs, FieldElement, NoirElement};
+
+    #[test]
+    fn test_optimize() {
+        // w2 = x * y + x * x; w3 = x * y + y * y; return w2 + w3
+        let term = |a, b| (NoirElement::one(), Witness(a), Witness(b));
+        let assert_zero = |mul_terms, linear: &[(i128, u32)]| {
+            Opcode::AssertZero(Expression {
+                mul_terms,
+                linear_combinations: linear
+                    .iter()
+                    .map(|&(coeff, w)| (NoirElement::from(coeff), Witness(w)))
+                    .collect(),
+                q_c: NoirElement::zero(),
+            })
+        };
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                assert_zero(vec![term(0, 1), term(0, 0)], &[(-1, 2)]),
+                assert_zero(vec![term(0, 1), term(1, 1)], &[(-1, 3)]),
+                assert_zero(vec![], &[(1, 2), (1, 3), (-1, 4)]),
+            ],
+            private_parameters: BTreeSet::from([Witness(0), Witness(1)]),
+            return_values: PublicInputs(BTreeSet::from([Witness(4)])),
+            ..Circuit::default()
+        };
+        let (r1cs, mut witness_maps) = noir_program_to_r1cs(slice::from_ref(&circuit)).unwrap();
+        let optimized = optimize_r1cs(&r1cs, &mut witness_maps, None);
+        assert_eq!((r1cs.constraints, r1cs.witnesses), (5, 8));
+        assert_eq!((optimized.constraints, optimized.witnesses), (3, 6));
+        assert_eq!(optimized.public_inputs, 1);
+
+        // Solve from the ACIR witnesses as the ACVM would provide them
+        let witness_map = &witness_maps[0].witness_map;
+        let mut witness = vec![None; optimized.witnesses];
+        witness[0] = Some(FieldElement::from(1u64));
+        for (i, value) in [(0, 3u64), (1, 5), (2, 24), (3, 40), (4, 64)] {
+            if let Some(index) = witness_map[i] {
+                witness[index.get() as usize] = Some(FieldElement::from(value));
+            }
+        }
+        optimized.solve_witness(&mut witness).unwrap();
+        let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        optimized.verify_witness(&witness).unwrap();
+        assert_eq!(
+            optimized.public_values(&witness),
+            [FieldElement::from(64u64)]
+        );
+
+        // The optimized instance still rejects a wrong result
+        let mut wrong = witness.clone();
+        wrong[1] += FieldElement::from(1u64);
+        assert!(optimized.verify_witness(&wrong).is_err());
+    }
+}
@@ -1,14 +1,55 @@
-use anyhow::{bail, ensure, Result};
-use ark_ff::One;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use ark_ff::{Field, One, PrimeField};
+use ark_serialize::CanonicalSerialize;
 use ark_std::Zero;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 use tracing::instrument;
 
-use crate::{utils::serde_ark, FieldElement, HydratedSparseMatrix, Interner, SparseMatrix};
+use crate::{
+    hints::Hint,
+    sparse_matrix::matrix_vector_products,
+    utils::{serde_ark, serde_decimal::signed},
+    FieldElement, HydratedSparseMatrix, Interner, SparseMatrix, SparseMatrixBuilder,
+};
+
+/// A constraint that does not hold or that [`R1CS::solve_witness`] can not
+/// solve. Recover it from an error with [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintError {
+    Failed(usize),
+    Unsolvable(usize),
+}
+
+impl ConstraintError {
+    pub fn row(&self) -> usize {
+        match *self {
+            Self::Failed(row) | Self::Unsolvable(row) => row,
+        }
+    }
+}
+
+impl Display for ConstraintError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Failed(row) => write!(f, "Constraint {row} failed"),
+            Self::Unsolvable(row) => write!(f, "Can not solve constraint {row}"),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
 
 /// Represents a R1CS constraint system.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct R1CS {
+    /// Number of public values, stored in witnesses `1..=public_inputs` right
+    /// after the constant one.
     pub public_inputs: usize,
     pub witnesses: usize,
     pub constraints: usize,
@@ -17,21 +58,13 @@ pub struct R1CS {
     pub a: SparseMatrix,
     pub b: SparseMatrix,
     pub c: SparseMatrix,
+    /// Witness hints as (row, hint) pairs sorted by row. Each hint is executed
+    /// before its row is solved.
+    #[serde(default)]
+    pub hints: Vec<(usize, Hint)>,
 }
 
 impl R1CS {
-    pub fn new() -> Self {
-        Self {
-            public_inputs: 0,
-            witnesses: 0,
-            constraints: 0,
-            interner: Interner::default(),
-            a: SparseMatrix::new(0, 0),
-            b: SparseMatrix::new(0, 0),
-            c: SparseMatrix::new(0, 0),
-        }
-    }
-
     pub fn a(&self) -> HydratedSparseMatrix<'_, FieldElement> {
         self.a.hydrate(&self.interner)
     }
@@ -44,37 +77,46 @@ impl R1CS {
         self.c.hydrate(&self.interner)
     }
 
-    /// Create a new witness variable
-    pub fn new_witness(&mut self) -> usize {
-        let value = self.witnesses;
-        self.witnesses += 1;
-        self.a.grow(self.constraints, self.witnesses);
-        self.b.grow(self.constraints, self.witnesses);
-        self.c.grow(self.constraints, self.witnesses);
-        value
+    /// The public values of a witness, excluding the constant one.
+    pub fn public_values<'a>(&self, witness: &'a [FieldElement]) -> &'a [FieldElement] {
+        &witness[1..=self.public_inputs]
     }
 
-    /// Add an R1CS constraint.
-    pub fn add_constraint(
-        &mut self,
-        a: &[(FieldElement, usize)],
-        b: &[(FieldElement, usize)],
-        c: &[(FieldElement, usize)],
-    ) {
-        let row = self.constraints;
-        self.constraints += 1;
-        self.a.grow(self.constraints, self.witnesses);
-        self.b.grow(self.constraints, self.witnesses);
-        self.c.grow(self.constraints, self.witnesses);
-        for (c, col) in a.iter().copied() {
-            self.a.set(row, col, self.interner.intern(c))
-        }
-        for (c, col) in b.iter().copied() {
-            self.b.set(row, col, self.interner.intern(c))
-        }
-        for (c, col) in c.iter().copied() {
-            self.c.set(row, col, self.interner.intern(c))
-        }
+    /// Compute `A·z`, `B·z` and `C·z` for a witness `z`.
+    pub fn products(&self, witness: &[FieldElement]) -> [Vec<FieldElement>; 3] {
+        matrix_vector_products([self.a(), self.b(), self.c()], witness)
+    }
+
+    /// Canonical digest of the constraint system, see [`r1cs_digest`].
+    pub fn digest(&self) -> Digest {
+        r1cs_digest(
+            self.public_inputs,
+            self.witnesses,
+            self.constraints,
+            [self.a(), self.b(), self.c()],
+        )
+    }
+
+    /// Display a constraint as `row: (A) · (B) = (C)` in terms of witnesses
+    /// `wᵢ`. Given a witness, a second line substitutes the witness values and
+    /// shows whether the constraint holds.
+    pub fn display_constraint<'a>(
+        &'a self,
+        row: usize,
+        witness: Option<&'a [FieldElement]>,
+    ) -> impl Display + 'a {
+        let values = witness.map_or(Values::None, Values::Full);
+        Constraint(self, row, values)
+    }
+
+    /// Display a constraint like [`Self::display_constraint`] for a partially
+    /// solved witness. Unknown values are shown as `?`.
+    pub fn display_partial_constraint<'a>(
+        &'a self,
+        row: usize,
+        witness: &'a [Option<FieldElement>],
+    ) -> impl Display + 'a {
+        Constraint(self, row, Values::Partial(witness))
     }
 
     /// Take a partially solved witness and try to complete it using the R1CS
@@ -90,27 +132,33 @@ impl R1CS {
 
         // Solve constraints in order
         // (this is how Noir expects it to be done, judging from ACVM)
+        let mut hints = self.hints.iter().peekable();
         for row in 0..self.constraints {
+            while let Some((_, hint)) = hints.next_if(|(hint_row, _)| *hint_row <= row) {
+                hint.solve(witness)
+                    .with_context(|| format!("while solving hint for constraint {row}"))?;
+            }
             let a = sparse_dot(self.a().iter_row(row), &witness);
             let b = sparse_dot(self.b().iter_row(row), &witness);
             let c = sparse_dot(self.c().iter_row(row), &witness);
             let (val, mat) = match (a, b, c) {
                 (Some(a), Some(b), Some(c)) => {
-                    ensure!(a * b == c, "Constraint {row} failed");
+                    ensure!(a * b == c, ConstraintError::Failed(row));
                     continue;
                 }
                 (Some(a), Some(b), None) => (a * b, self.c()),
                 (Some(a), None, Some(c)) => (c / a, self.b()),
                 (None, Some(b), Some(c)) => (c / b, self.a()),
-                _ => {
-                    bail!("Can not solve constraint {row}.")
-                }
+                _ => bail!(ConstraintError::Unsolvable(row)),
             };
             let Some((col, val)) = solve_dot(mat.iter_row(row), &witness, val) else {
-                bail!("Could not solve constraint {row}.")
+                bail!(ConstraintError::Unsolvable(row))
             };
             witness[col] = Some(val);
         }
+        for (_, hint) in hints {
+            hint.solve(witness).context("while solving trailing hint")?;
+        }
         Ok(())
     }
 
@@ -122,21 +170,227 @@ impl R1CS {
         );
 
         // Verify
-        let a = self.a() * witness;
-        let b = self.b() * witness;
-        let c = self.c() * witness;
+        let [a, b, c] = self.products(witness);
         for (row, ((a, b), c)) in a
             .into_iter()
             .zip(b.into_iter())
             .zip(c.into_iter())
             .enumerate()
         {
-            ensure!(a * b == c, "Constraint {row} failed");
+            ensure!(a * b == c, ConstraintError::Failed(row));
         }
         Ok(())
     }
 }
 
+/// A R1CS constraint system under construction. Constraints are collected as
+/// matrix triplets and compressed once by [`R1CSBuilder::build`].
+#[derive(Debug, Clone, Default)]
+pub struct R1CSBuilder {
+    /// See [`R1CS::public_inputs`].
+    pub public_inputs: usize,
+    pub witnesses: usize,
+    pub constraints: usize,
+    interner: Interner<FieldElement>,
+    a: SparseMatrixBuilder,
+    b: SparseMatrixBuilder,
+    c: SparseMatrixBuilder,
+    hints: Vec<(usize, Hint)>,
+}
+
+impl R1CSBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new witness variable
+    pub fn new_witness(&mut self) -> usize {
+        let value = self.witnesses;
+        self.witnesses += 1;
+        self.a.grow(self.constraints, self.witnesses);
+        self.b.grow(self.constraints, self.witnesses);
+        self.c.grow(self.constraints, self.witnesses);
+        value
+    }
+
+    /// Add a witness hint to be executed before the next constraint is solved.
+    pub fn add_hint(&mut self, hint: Hint) {
+        self.hints.push((self.constraints, hint));
+    }
+
+    /// Add an R1CS constraint.
+    pub fn add_constraint(
+        &mut self,
+        a: &[(FieldElement, usize)],
+        b: &[(FieldElement, usize)],
+        c: &[(FieldElement, usize)],
+    ) {
+        let row = self.constraints;
+        self.constraints += 1;
+        let interner = &mut self.interner;
+        for (matrix, terms) in [(&mut self.a, a), (&mut self.b, b), (&mut self.c, c)] {
+            matrix.grow(self.constraints, self.witnesses);
+            for (col, coeff) in merge_terms(terms) {
+                matrix.set(row, col, interner.intern(coeff));
+            }
+        }
+    }
+
+    /// Compress the collected constraints into a [`R1CS`].
+    #[instrument(skip_all, fields(constraints = self.constraints, witnesses = self.witnesses))]
+    pub fn build(self) -> R1CS {
+        R1CS {
+            public_inputs: self.public_inputs,
+            witnesses: self.witnesses,
+            constraints: self.constraints,
+            interner: self.interner,
+            a: self.a.build(),
+            b: self.b.build(),
+            c: self.c.build(),
+            hints: self.hints,
+        }
+    }
+}
+
+/// A SHA-256 digest.
+pub type Digest = [u8; 32];
+
+const DIGEST_DOMAIN: &[u8] = b"noir-r1cs R1CS digest v1";
+
+/// Canonical SHA-256 digest of an R1CS instance, over its dimensions and the
+/// entries of the three matrices in row-major order.
+///
+/// The interner enters through the values of the matrix entries, so the
+/// digest does not depend on the order values were interned in. Importing
+/// [`R1CSTriplets`](crate::R1CSTriplets) gives the same digest as the
+/// exported instance. Hints only help to solve the witness and are not
+/// included.
+pub fn r1cs_digest<F: Field>(
+    public_inputs: usize,
+    witnesses: usize,
+    constraints: usize,
+    matrices: [HydratedSparseMatrix<'_, F>; 3],
+) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(DIGEST_DOMAIN);
+    for size in [public_inputs, witnesses, constraints] {
+        hasher.update((size as u64).to_le_bytes());
+    }
+    for matrix in matrices {
+        hasher.update((matrix.num_entries() as u64).to_le_bytes());
+        for ((row, col), value) in matrix.iter() {
+            hasher.update((row as u64).to_le_bytes());
+            hasher.update((col as u64).to_le_bytes());
+            value
+                .serialize_compressed(&mut hasher)
+                .expect("Writing to a hasher can not fail");
+        }
+    }
+    hasher.finalize().into()
+}
+
+// Combine terms with the same column and drop zero coefficients.
+pub(crate) fn merge_terms(terms: &[(FieldElement, usize)]) -> BTreeMap<usize, FieldElement> {
+    let mut merged = BTreeMap::new();
+    for &(coeff, col) in terms {
+        *merged.entry(col).or_insert_with(FieldElement::zero) += coeff;
+    }
+    merged.retain(|_, coeff| !coeff.is_zero());
+    merged
+}
+
+#[derive(Clone, Copy)]
+enum Values<'a> {
+    None,
+    Full(&'a [FieldElement]),
+    Partial(&'a [Option<FieldElement>]),
+}
+
+impl Values<'_> {
+    fn get(self, col: usize) -> Option<FieldElement> {
+        match self {
+            Values::None => None,
+            Values::Full(witness) => witness.get(col).copied(),
+            Values::Partial(witness) => witness.get(col).copied().flatten(),
+        }
+    }
+}
+
+struct Constraint<'a>(&'a R1CS, usize, Values<'a>);
+
+impl Display for Constraint<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let Self(r1cs, row, values) = *self;
+        let matrices = [r1cs.a(), r1cs.b(), r1cs.c()];
+        let terms = |var: &dyn Fn(usize) -> String| {
+            matrices.map(|matrix| format_terms(matrix.iter_row(row), var))
+        };
+        let [a, b, c] = terms(&|col| format!("w{col}"));
+        write!(f, "{row}: ({a}) · ({b}) = ({c})")?;
+        if matches!(values, Values::None) {
+            return Ok(());
+        }
+        let value = |value: Option<FieldElement>| {
+            value.map_or_else(|| "?".to_string(), |v| signed(v).to_string())
+        };
+        let [a, b, c] = terms(&|col| value(values.get(col)));
+        let [za, zb, zc] = matrices.map(|matrix| {
+            matrix
+                .iter_row(row)
+                .map(|(col, coeff)| Some(coeff * values.get(col)?))
+                .sum::<Option<FieldElement>>()
+        });
+        let status = match (za, zb, zc) {
+            (Some(za), Some(zb), Some(zc)) if za * zb == zc => " ✓",
+            (Some(_), Some(_), Some(_)) => " ✗",
+            _ => "",
+        };
+        write!(
+            f,
+            "\n{:width$}  ({a}) · ({b}) = ({c})  →  {} · {} = {}{status}",
+            "",
+            value(za),
+            value(zb),
+            value(zc),
+            width = row.to_string().len()
+        )
+    }
+}
+
+// Format a linear combination, writing `var(col)` for each witness. Column
+// zero is the constant one.
+fn format_terms(
+    terms: impl Iterator<Item = (usize, FieldElement)>,
+    var: &dyn Fn(usize) -> String,
+) -> String {
+    let mut result = String::new();
+    for (col, coeff) in terms {
+        let (sign, coeff) = if coeff.into_bigint() > FieldElement::MODULUS_MINUS_ONE_DIV_TWO {
+            ("-", -coeff)
+        } else {
+            ("+", coeff)
+        };
+        match (result.is_empty(), sign) {
+            (true, "-") => result.push('-'),
+            (true, _) => {}
+            (false, _) => {
+                result.push(' ');
+                result.push_str(sign);
+                result.push(' ');
+            }
+        }
+        result.push_str(&match (col, coeff.is_one()) {
+            (0, _) => coeff.to_string(),
+            (_, true) => var(col),
+            (_, false) => format!("{coeff}·{}", var(col)),
+        });
+    }
+    if result.is_empty() {
+        result.push('0');
+    }
+    result
+}
+
 // Sparse dot product. `a` is assumed zero. `b` is assumed missing.
 fn sparse_dot<'a>(
     a: impl Iterator<Item = (usize, FieldElement)>,
@@ -176,3 +430,34 @@ fn solve_dot<'a>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_partial_constraint() {
+        let mut r1cs = R1CSBuilder::new();
+        let [one, x, y] = [(); 3].map(|_| r1cs.new_witness());
+        let f = |v: u64| FieldElement::from(v);
+        r1cs.add_constraint(&[(f(1), x)], &[(f(1), x)], &[(f(1), y)]);
+        r1cs.add_constraint(&[(f(1), y), (-f(1), x)], &[(f(1), one)], &[(f(3), one)]);
+        let r1cs = r1cs.build();
+
+        let mut witness = vec![Some(f(1)), Some(f(3)), None];
+        let error = r1cs.solve_witness(&mut witness).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ConstraintError>(),
+            Some(&ConstraintError::Failed(1))
+        );
+        assert_eq!(
+            r1cs.display_partial_constraint(1, &witness).to_string(),
+            "1: (-w1 + w2) · (1) = (3)\n   (-3 + 9) · (1) = (3)  →  6 · 1 = 3 ✗"
+        );
+        witness[2] = None;
+        assert_eq!(
+            r1cs.display_partial_constraint(0, &witness).to_string(),
+            "0: (w1) · (w1) = (w2)\n   (3) · (3) = (?)  →  3 · 3 = ?"
+        );
+    }
+}
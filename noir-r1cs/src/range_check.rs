@@ -0,0 +1,406 @@
+//! Lowering of range checks to R1CS through digit decomposition.
+
+use std::{array, collections::BTreeMap};
+
+use ark_ff::{Field, PrimeField};
+use ark_std::One;
+
+use crate::{
+    poseidon2::{self, poseidon2_cost},
+    FieldElement, Hint, LinearCombination, R1CSBuilder,
+};
+
+/// Largest supported digit size of [`RangeCheckStrategy::Batched`].
+pub const MAX_LOG_BASE: u32 = 20;
+
+/// Number of state elements absorbed per Poseidon2 permutation when deriving
+/// the lookup challenge.
+const RATE: usize = poseidon2::WIDTH - 1;
+
+/// Strategy for lowering ACIR `RANGE` black-box calls.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RangeCheckStrategy {
+    /// Decompose every checked value into boolean constrained bits as soon as
+    /// the check is encountered.
+    #[default]
+    Bits,
+
+    /// Collect all checks, keep only the tightest bound per witness, and
+    /// decompose each witness once into base `2^log_base` digits. All digits
+    /// are looked up in one shared table `0..2^log_base` with a logarithmic
+    /// derivative argument
+    ///
+    /// `Σ_i 1 / (X - d_i) = Σ_j m_j / (X - j)`
+    ///
+    /// where `m_j` counts the digits equal to `j`. The challenge `X` is a
+    /// Poseidon2 hash of all digits and multiplicities computed in the
+    /// circuit. Each digit costs one constraint plus its share of the hash,
+    /// and the table adds a fixed cost of `2^log_base` constraints.
+    Batched { log_base: u32 },
+}
+
+/// Number of R1CS constraints produced by lowering the given `(witness,
+/// num_bits)` range checks with a strategy.
+pub fn range_check_cost(strategy: RangeCheckStrategy, checks: &[(usize, u32)]) -> usize {
+    match strategy {
+        RangeCheckStrategy::Bits => checks
+            .iter()
+            .map(|&(_, num_bits)| decomposition_cost(num_bits, 1))
+            .sum(),
+        RangeCheckStrategy::Batched { log_base } => {
+            let bounds = tightest_bounds(checks.iter().copied());
+            let mut digits = 0;
+            let mut lookups = 0;
+            for &num_bits in bounds.values().filter(|&&num_bits| !is_trivial(num_bits)) {
+                let widths = digit_widths(num_bits, log_base);
+                digits += widths.len();
+                lookups += widths.len() + usize::from(num_bits % log_base != 0);
+            }
+            if digits == 0 {
+                return 0;
+            }
+            let recompositions = bounds
+                .values()
+                .filter(|&&num_bits| !is_trivial(num_bits))
+                .count();
+            let table = 1usize << log_base;
+            let challenge = (digits + table).div_ceil(RATE) * poseidon2_cost();
+            recompositions + challenge + lookups + table + 1
+        }
+    }
+}
+
+/// Keep only the smallest bit size per witness.
+pub(crate) fn tightest_bounds(
+    checks: impl IntoIterator<Item = (usize, u32)>,
+) -> BTreeMap<usize, u32> {
+    let mut bounds = BTreeMap::new();
+    for (witness, num_bits) in checks {
+        bounds
+            .entry(witness)
+            .and_modify(|bits: &mut u32| *bits = (*bits).min(num_bits))
+            .or_insert(num_bits);
+    }
+    bounds
+}
+
+/// Whether a range check is implied by the field size.
+pub(crate) fn is_trivial(num_bits: u32) -> bool {
+    num_bits >= FieldElement::MODULUS_BIT_SIZE
+}
+
+/// Constrain `value < 2^num_bits` through its bit decomposition.
+pub(crate) fn add_range_check(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    value: usize,
+    num_bits: u32,
+) {
+    if is_trivial(num_bits) {
+        return;
+    }
+    decompose(
+        r1cs,
+        witness_one,
+        &[(FieldElement::one(), value)],
+        num_bits,
+        1,
+    );
+}
+
+/// Constrain all `(witness, num_bits)` checks against one shared table of
+/// base `2^log_base` digits, see [`RangeCheckStrategy::Batched`].
+///
+/// A most significant digit `d` of width `w < log_base` is looked up twice,
+/// as `d` and as `d + 2^log_base - 2^w`, which both lie in the table exactly
+/// when `d < 2^w`.
+pub(crate) fn add_batched_range_checks(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    checks: impl IntoIterator<Item = (usize, u32)>,
+    log_base: u32,
+) {
+    assert!(
+        (1..=MAX_LOG_BASE).contains(&log_base),
+        "Digits must have between 1 and {MAX_LOG_BASE} bits"
+    );
+    let one = FieldElement::one();
+    let table_size = 1u64 << log_base;
+
+    // Decompose every value into unconstrained digits
+    let mut digits = Vec::new();
+    let mut lookups: Vec<LinearCombination> = Vec::new();
+    for (value, num_bits) in tightest_bounds(checks) {
+        if is_trivial(num_bits) {
+            continue;
+        }
+        let widths = digit_widths(num_bits, log_base);
+        let outputs = widths
+            .iter()
+            .map(|_| r1cs.new_witness())
+            .collect::<Vec<_>>();
+        r1cs.add_hint(Hint::Digits {
+            value: vec![(one, value)],
+            log_base,
+            outputs: outputs.clone(),
+        });
+        let digit_lcs = outputs
+            .iter()
+            .map(|&digit| vec![(one, digit)])
+            .collect::<Vec<_>>();
+        let mut linear = recompose(&digit_lcs, log_base);
+        linear.push((-one, value));
+        r1cs.add_constraint(&[], &[], &linear);
+
+        for (&digit, &width) in outputs.iter().zip(widths.iter()) {
+            lookups.push(vec![(one, digit)]);
+            if width < log_base {
+                let shift = FieldElement::from(table_size - (1 << width));
+                lookups.push(vec![(one, digit), (shift, witness_one)]);
+            }
+        }
+        digits.extend(outputs);
+    }
+    if digits.is_empty() {
+        return;
+    }
+
+    // Count how often every table entry is looked up
+    let multiplicities = (0..table_size)
+        .map(|_| r1cs.new_witness())
+        .collect::<Vec<_>>();
+    r1cs.add_hint(Hint::Multiplicities {
+        values: lookups.clone(),
+        outputs: multiplicities.clone(),
+    });
+
+    // The challenge binds the digits and multiplicities
+    let hashed = digits
+        .iter()
+        .chain(multiplicities.iter())
+        .copied()
+        .collect::<Vec<_>>();
+    let challenge = add_challenge(r1cs, witness_one, &hashed);
+
+    // Σ 1 / (X - d_i) - Σ m_j / (X - j) = 0
+    let mut sum = LinearCombination::new();
+    for lookup in &lookups {
+        let mut difference = vec![(one, challenge)];
+        difference.extend(lookup.iter().map(|&(c, w)| (-c, w)));
+        let inverse = r1cs.new_witness();
+        r1cs.add_constraint(&difference, &[(one, inverse)], &[(one, witness_one)]);
+        sum.push((one, inverse));
+    }
+    for (entry, &multiplicity) in multiplicities.iter().enumerate() {
+        let difference = [
+            (one, challenge),
+            (-FieldElement::from(entry as u64), witness_one),
+        ];
+        let quotient = r1cs.new_witness();
+        r1cs.add_constraint(&difference, &[(one, quotient)], &[(one, multiplicity)]);
+        sum.push((-one, quotient));
+    }
+    r1cs.add_constraint(&[], &[], &sum);
+}
+
+/// Hash `inputs` with a Poseidon2 sponge whose capacity element is
+/// initialized to the number of inputs. Returns the witness holding the first
+/// state element after the last permutation.
+fn add_challenge(r1cs: &mut R1CSBuilder, witness_one: usize, inputs: &[usize]) -> usize {
+    let one = FieldElement::one();
+    let mut state: [LinearCombination; poseidon2::WIDTH] = array::from_fn(|_| vec![]);
+    state[RATE].push((FieldElement::from(inputs.len() as u64), witness_one));
+    for chunk in inputs.chunks(RATE) {
+        for (element, &input) in state.iter_mut().zip(chunk) {
+            element.push((one, input));
+        }
+        let outputs = array::from_fn(|_| r1cs.new_witness());
+        poseidon2::add_permutation(r1cs, witness_one, &state, &outputs);
+        state = outputs.map(|output| vec![(one, output)]);
+    }
+    state[0][0].1
+}
+
+/// Decompose `value` into little-endian base `2^log_base` digits covering
+/// `num_bits`, constraining each digit to its range and the digits to
+/// recompose to `value`. The most significant digit is narrowed when
+/// `num_bits` is not a multiple of `log_base`. Returns the digit witnesses.
+pub(crate) fn decompose(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    value: &[(FieldElement, usize)],
+    num_bits: u32,
+    log_base: u32,
+) -> Vec<usize> {
+    assert!(log_base >= 1, "Digits must have at least one bit");
+    let widths = digit_widths(num_bits, log_base);
+    let digits = widths
+        .iter()
+        .map(|_| r1cs.new_witness())
+        .collect::<Vec<_>>();
+    r1cs.add_hint(Hint::Digits {
+        value: value.to_vec(),
+        log_base,
+        outputs: digits.clone(),
+    });
+    for (&digit, &width) in digits.iter().zip(widths.iter()) {
+        add_digit_check(r1cs, witness_one, digit, width);
+    }
+
+    // Recompose
+    let digit_lcs = digits
+        .iter()
+        .map(|&digit| vec![(FieldElement::one(), digit)])
+        .collect::<Vec<_>>();
+    let mut linear = recompose(&digit_lcs, log_base);
+    linear.extend(value.iter().map(|&(c, w)| (-c, w)));
+    r1cs.add_constraint(&[], &[], &linear);
+    digits
+}
+
+/// Linear combination `Σ 2^(i * log_base) digits[i]` of little-endian digits.
+pub(crate) fn recompose(digits: &[LinearCombination], log_base: u32) -> LinearCombination {
+    digits
+        .iter()
+        .enumerate()
+        .flat_map(|(i, digit)| {
+            let weight = power_of_two(i as u32 * log_base);
+            digit.iter().map(move |&(c, w)| (c * weight, w))
+        })
+        .collect()
+}
+
+/// Constrain `digit` to `0..B` with `B = 2^num_bits` through the vanishing
+/// polynomial `∏ (d - j)`. With `u = d (d - m)` and `m = B - 1`, the factors
+/// pair up as `(d - j)(d - m + j) = u + j (m - j)`, so the check takes `B / 2`
+/// constraints.
+fn add_digit_check(r1cs: &mut R1CSBuilder, witness_one: usize, digit: usize, num_bits: u32) {
+    let one = FieldElement::one();
+    if num_bits == 1 {
+        r1cs.add_constraint(&[(one, digit)], &[(one, digit)], &[(one, digit)]);
+        return;
+    }
+    let max = (1u64 << num_bits) - 1;
+    let u = r1cs.new_witness();
+    r1cs.add_constraint(
+        &[(one, digit)],
+        &[(one, digit), (-FieldElement::from(max), witness_one)],
+        &[(one, u)],
+    );
+    let factor = |j: u64| vec![(one, u), (FieldElement::from(j * (max - j)), witness_one)];
+    let mut accumulator = vec![(one, u)];
+    for j in 1..(max + 1) / 2 {
+        let output = if j + 1 == (max + 1) / 2 {
+            vec![]
+        } else {
+            vec![(one, r1cs.new_witness())]
+        };
+        r1cs.add_constraint(&accumulator, &factor(j), &output);
+        accumulator = output;
+    }
+}
+
+/// Bit widths of the digits covering `num_bits`.
+fn digit_widths(num_bits: u32, log_base: u32) -> Vec<u32> {
+    let mut widths = vec![log_base; (num_bits / log_base) as usize];
+    if num_bits % log_base != 0 {
+        widths.push(num_bits % log_base);
+    }
+    widths
+}
+
+/// Number of constraints of [`decompose`].
+fn decomposition_cost(num_bits: u32, log_base: u32) -> usize {
+    if is_trivial(num_bits) {
+        return 0;
+    }
+    let digits = digit_widths(num_bits, log_base)
+        .into_iter()
+        .map(|width| 1usize << (width - 1))
+        .sum::<usize>();
+    digits + 1
+}
+
+pub(crate) fn power_of_two(exponent: u32) -> FieldElement {
+    FieldElement::from(2u64).pow([exponent as u64])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decomposition_cost_matches_constraints() {
+        for log_base in 1..=4 {
+            for num_bits in [0, 1, 3, 8, 13, 32] {
+                let mut r1cs = R1CSBuilder::new();
+                let one = r1cs.new_witness();
+                let value = r1cs.new_witness();
+                decompose(
+                    &mut r1cs,
+                    one,
+                    &[(FieldElement::one(), value)],
+                    num_bits,
+                    log_base,
+                );
+                assert_eq!(r1cs.constraints, decomposition_cost(num_bits, log_base));
+                let r1cs = r1cs.build();
+
+                // Solve and verify for the largest value in range
+                let mut witness = vec![None; r1cs.witnesses];
+                witness[one] = Some(FieldElement::one());
+                witness[value] = Some(power_of_two(num_bits) - FieldElement::one());
+                r1cs.solve_witness(&mut witness).unwrap();
+                let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+                r1cs.verify_witness(&witness).unwrap();
+
+                // Out of range
+                let mut witness = vec![None; r1cs.witnesses];
+                witness[one] = Some(FieldElement::one());
+                witness[value] = Some(power_of_two(num_bits));
+                assert!(r1cs.solve_witness(&mut witness).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_range_checks() {
+        for log_base in [1, 3, 4] {
+            let bounds = [8, 13, 1, 3];
+            let mut r1cs = R1CSBuilder::new();
+            let one = r1cs.new_witness();
+            let values = bounds.map(|_| r1cs.new_witness());
+            let checks = values
+                .iter()
+                .copied()
+                .zip(bounds)
+                // Looser repeated bound and a trivial check are dropped
+                .chain([(values[0], 16), (values[1], 254)])
+                .collect::<Vec<_>>();
+            add_batched_range_checks(&mut r1cs, one, checks.iter().copied(), log_base);
+            assert_eq!(
+                r1cs.constraints,
+                range_check_cost(RangeCheckStrategy::Batched { log_base }, &checks)
+            );
+            let r1cs = r1cs.build();
+
+            let solve = |assignment: [u64; 4]| -> anyhow::Result<()> {
+                let mut witness = vec![None; r1cs.witnesses];
+                witness[one] = Some(FieldElement::one());
+                for (&value, assigned) in values.iter().zip(assignment) {
+                    witness[value] = Some(FieldElement::from(assigned));
+                }
+                r1cs.solve_witness(&mut witness)?;
+                let witness = witness.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+                r1cs.verify_witness(&witness)
+            };
+            solve([0, 0, 0, 0]).unwrap();
+            solve([255, 8191, 1, 7]).unwrap();
+            solve([17, 4096, 0, 5]).unwrap();
+            assert!(solve([256, 0, 0, 0]).is_err());
+            assert!(solve([0, 8192, 0, 0]).is_err());
+            assert!(solve([0, 0, 2, 0]).is_err());
+            assert!(solve([0, 0, 0, 8]).is_err());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! SHA-256 compression function gadget.
+//!
+//! Words are represented as 32 little-endian bits, each a boolean linear
+//! combination. Rotations and shifts are free rewirings of the bits, the
+//! boolean functions reuse the bit products from [`crate::bitwise`], and modular
+//! additions are decomposed with [`crate::range_check::decompose`] after which
+//! the carry bits are dropped.
+
+use std::array;
+
+use ark_std::One;
+
+use crate::{
+    bitwise::{add_bitwise, add_product, scale, BitwiseOp},
+    range_check::{decompose, recompose},
+    FieldElement, LinearCombination, R1CSBuilder,
+};
+
+/// A 32-bit word as little-endian boolean linear combinations.
+pub(crate) type Word = Vec<LinearCombination>;
+
+const WORD_BITS: u32 = 32;
+
+/// Round constants.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Number of R1CS constraints of [`add_compression`] on witness inputs,
+/// including the decomposition of inputs and outputs into bits.
+pub fn sha256_compression_cost() -> usize {
+    let mut r1cs = R1CSBuilder::new();
+    let one = r1cs.new_witness();
+    let add_word = |r1cs: &mut R1CSBuilder| {
+        let witness = r1cs.new_witness();
+        decompose(r1cs, one, &[(FieldElement::one(), witness)], WORD_BITS, 1)
+            .into_iter()
+            .map(|bit| vec![(FieldElement::one(), bit)])
+            .collect::<Word>()
+    };
+    let inputs = array::from_fn(|_| add_word(&mut r1cs));
+    let hash_values = array::from_fn(|_| add_word(&mut r1cs));
+    let outputs = add_compression(&mut r1cs, one, &inputs, &hash_values);
+    r1cs.constraints + outputs.len()
+}
+
+/// Add the SHA-256 compression of the message block `inputs` into the state
+/// `hash_values`. Returns the eight output words as linear combinations.
+///
+/// All operand bits must already be constrained boolean.
+pub(crate) fn add_compression(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    inputs: &[Word; 16],
+    hash_values: &[Word; 8],
+) -> [LinearCombination; 8] {
+    // Message schedule
+    let mut w = inputs.to_vec();
+    for t in 16..64 {
+        let s0 = xor3(
+            r1cs,
+            witness_one,
+            [
+                rotr(&w[t - 15], 7),
+                rotr(&w[t - 15], 18),
+                shr(&w[t - 15], 3),
+            ],
+        );
+        let s1 = xor3(
+            r1cs,
+            witness_one,
+            [rotr(&w[t - 2], 17), rotr(&w[t - 2], 19), shr(&w[t - 2], 10)],
+        );
+        let word = add_words(r1cs, witness_one, &[&s1, &w[t - 7], &s0, &w[t - 16]], 0);
+        w.push(word);
+    }
+
+    // Rounds
+    let mut state = hash_values.clone();
+    for t in 0..64 {
+        let [a, b, c, d, e, f, g, h] = &state;
+        let s1 = xor3(r1cs, witness_one, [rotr(e, 6), rotr(e, 11), rotr(e, 25)]);
+        let ch = ch(r1cs, witness_one, e, f, g);
+        let s0 = xor3(r1cs, witness_one, [rotr(a, 2), rotr(a, 13), rotr(a, 22)]);
+        let maj = maj(r1cs, witness_one, a, b, c);
+        let new_a = add_words(r1cs, witness_one, &[h, &s1, &ch, &w[t], &s0, &maj], K[t]);
+        let new_e = add_words(r1cs, witness_one, &[d, h, &s1, &ch, &w[t]], K[t]);
+        state = [
+            new_a,
+            a.clone(),
+            b.clone(),
+            c.clone(),
+            new_e,
+            e.clone(),
+            f.clone(),
+            g.clone(),
+        ];
+    }
+
+    // Add the compressed chunk to the current hash value
+    array::from_fn(|i| {
+        let word = add_words(r1cs, witness_one, &[&hash_values[i], &state[i]], 0);
+        recompose(&word, 1)
+    })
+}
+
+fn rotr(word: &[LinearCombination], n: usize) -> Word {
+    (0..word.len())
+        .map(|i| word[(i + n) % word.len()].clone())
+        .collect()
+}
+
+fn shr(word: &[LinearCombination], n: usize) -> Word {
+    (0..word.len())
+        .map(|i| word.get(i + n).cloned().unwrap_or_default())
+        .collect()
+}
+
+fn xor3(r1cs: &mut R1CSBuilder, witness_one: usize, [a, b, c]: [Word; 3]) -> Word {
+    let ab = add_bitwise(r1cs, witness_one, BitwiseOp::Xor, &a, &b);
+    add_bitwise(r1cs, witness_one, BitwiseOp::Xor, &ab, &c)
+}
+
+/// `(e & f) ^ (!e & g) = g + e * (f - g)`
+fn ch(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    e: &[LinearCombination],
+    f: &[LinearCombination],
+    g: &[LinearCombination],
+) -> Word {
+    e.iter()
+        .zip(f.iter().zip(g.iter()))
+        .map(|(e, (f, g))| {
+            let mut difference = f.clone();
+            difference.extend(scale(g, -FieldElement::one()));
+            let mut output = add_product(r1cs, witness_one, e, &difference);
+            output.extend(g.iter().copied());
+            output
+        })
+        .collect()
+}
+
+/// `(a & b) ^ (a & c) ^ (b & c) = ab + c * (a + b - 2ab)`
+fn maj(
+    r1cs: &mut R1CSBuilder,
+    witness_one: usize,
+    a: &[LinearCombination],
+    b: &[LinearCombination],
+    c: &[LinearCombination],
+) -> Word {
+    a.iter()
+        .zip(b.iter().zip(c.iter()))
+        .map(|(a, (b, c))| {
+            let ab = add_product(r1cs, witness_one, a, b);
+            let mut sum = a.clone();
+            sum.extend(b.iter().copied());
+            sum.extend(scale(&ab, -FieldElement::from(2u64)));
+            let mut output = add_product(r1cs, witness_one, c, &sum);
+            output.extend(ab);
+            output
+        })
+        .collect()
+}
+
+/// Sum words and a constant modulo `2^32`, returning the bits of the result.
+fn add_words(r1cs: &mut R1CSBuilder, witness_one: usize, words: &[&Word], constant: u32) -> Word {
+    let mut sum = words
+        .iter()
+        .flat_map(|word| recompose(word, 1))
+        .collect::<LinearCombination>();
+    sum.push((FieldElement::from(constant), witness_one));
+    let terms = words.len() as u32 + u32::from(constant != 0);
+    let carry_bits = u32::BITS - (terms - 1).leading_zeros();
+    let bits = decompose(r1cs, witness_one, &sum, WORD_BITS + carry_bits, 1);
+    bits.into_iter()
+        .take(WORD_BITS as usize)
+        .map(|bit| vec![(FieldElement::one(), bit)])
+        .collect()
+}
@@ -0,0 +1,152 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use acir::circuit::{Opcode, OpcodeLocation};
+use noirc_artifacts::program::ProgramArtifact;
+use serde::{Deserialize, Serialize};
+
+use crate::NoirElement;
+
+/// Maximum length of the printed ACIR opcode kept per origin.
+const MAX_OPCODE_LEN: usize = 200;
+
+/// Maps R1CS constraints back to the ACIR opcodes, and through the debug
+/// symbols to the Noir source, they were compiled from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Origin of each constraint as an index into `origins`. `None` for
+    /// constraints not tied to a single opcode, such as the public value
+    /// equalities and the deferred range checks.
+    rows: Vec<Option<u32>>,
+    origins: Vec<OpcodeOrigin>,
+}
+
+/// An ACIR opcode in the inlined program.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpcodeOrigin {
+    /// `(function, opcode index)` of the inlined `Call` opcodes from the entry
+    /// point down, ending with the opcode itself.
+    pub call_stack: Vec<(u32, usize)>,
+    /// The opcode as printed by ACIR, truncated.
+    pub opcode: String,
+    /// Noir call stack of the opcode, outermost first. Empty without debug
+    /// symbols.
+    pub locations: Vec<SourceLocation>,
+}
+
+/// A span of Noir source code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub path: String,
+    /// One-based line and column of the start of the span.
+    pub line: usize,
+    pub column: usize,
+    /// The source text of the span.
+    pub text: String,
+}
+
+impl SourceMap {
+    /// Origin of a constraint, if known.
+    pub fn origin(&self, row: usize) -> Option<&OpcodeOrigin> {
+        let index = (*self.rows.get(row)?)?;
+        self.origins.get(index as usize)
+    }
+
+    /// Register an opcode, returning its index for [`Self::assign_rows`].
+    pub(crate) fn add_opcode(
+        &mut self,
+        call_stack: Vec<(u32, usize)>,
+        opcode: &Opcode<NoirElement>,
+    ) -> u32 {
+        let mut opcode = opcode.to_string();
+        if let Some((end, _)) = opcode.char_indices().nth(MAX_OPCODE_LEN) {
+            opcode.truncate(end);
+            opcode.push('…');
+        }
+        self.origins.push(OpcodeOrigin {
+            call_stack,
+            opcode,
+            locations: Vec::new(),
+        });
+        (self.origins.len() - 1) as u32
+    }
+
+    /// Attribute the constraints added since the last call, up to
+    /// `constraints`, to an opcode.
+    pub(crate) fn assign_rows(&mut self, constraints: usize, origin: Option<u32>) {
+        if constraints > self.rows.len() {
+            self.rows.resize(constraints, origin);
+        }
+    }
+
+    /// Keep only the given rows, in the given order.
+    pub(crate) fn select_rows(&mut self, rows: &[usize]) {
+        self.rows = rows.iter().map(|&row| self.rows[row]).collect();
+    }
+
+    /// Resolve the Noir source locations of all opcodes from the program's
+    /// debug symbols.
+    pub fn add_debug_symbols(&mut self, program: &ProgramArtifact) {
+        for origin in &mut self.origins {
+            origin.locations = origin
+                .call_stack
+                .iter()
+                .flat_map(|&(function, opcode)| source_locations(program, function, opcode))
+                .collect();
+        }
+    }
+}
+
+fn source_locations(
+    program: &ProgramArtifact,
+    function: u32,
+    opcode: usize,
+) -> Vec<SourceLocation> {
+    let Some(debug_info) = program.debug_symbols.debug_infos.get(function as usize) else {
+        return Vec::new();
+    };
+    debug_info
+        .opcode_location(&OpcodeLocation::Acir(opcode))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|location| {
+            let file = program.file_map.get(&location.file)?;
+            let start = location.span.start() as usize;
+            let end = location.span.end() as usize;
+            let before = file.source.get(..start)?;
+            Some(SourceLocation {
+                path: file.path.display().to_string(),
+                line: before.matches('\n').count() + 1,
+                column: before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1,
+                text: file.source.get(start..end)?.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl Display for OpcodeOrigin {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let (function, opcode) = self.call_stack.last().copied().unwrap_or_default();
+        write!(
+            f,
+            "ACIR function {function} opcode {opcode}: {}",
+            self.opcode
+        )?;
+        for &(function, opcode) in self.call_stack.iter().rev().skip(1) {
+            write!(f, "\n  inlined from function {function} opcode {opcode}")?;
+        }
+        for location in self.locations.iter().rev() {
+            write!(f, "\n  at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path, self.line, self.column, self.text
+        )
+    }
+}
@@ -1,15 +1,17 @@
 use std::{
     fmt::Debug,
     ops::{Mul, Range},
+    sync::OnceLock,
 };
 
 use ark_ff::Field;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{InternedFieldElement, Interner};
 
 /// A sparse matrix with interned field elements
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SparseMatrix {
     /// The number of rows in the matrix.
     pub rows: usize,
@@ -25,6 +27,21 @@ pub struct SparseMatrix {
 
     // List of values
     values: Vec<InternedFieldElement>,
+
+    /// The transpose, computed on first use by left multiplication and reset
+    /// by any modification.
+    #[serde(skip)]
+    transposed: OnceLock<Box<SparseMatrix>>,
+}
+
+/// A sparse matrix under construction, as unordered `(row, col, value)`
+/// triplets. Converted to the compressed row format of [`SparseMatrix`] once,
+/// by [`SparseMatrixBuilder::build`].
+#[derive(Debug, Clone, Default)]
+pub struct SparseMatrixBuilder {
+    rows: usize,
+    cols: usize,
+    entries: Vec<(u32, u32, InternedFieldElement)>,
 }
 
 /// A hydrated sparse matrix with uninterned field elements
@@ -42,6 +59,7 @@ impl SparseMatrix {
             row_indices: vec![0; rows],
             col_indices: Vec::new(),
             values: Vec::new(),
+            transposed: OnceLock::new(),
         }
     }
 
@@ -66,12 +84,14 @@ impl SparseMatrix {
         self.rows = rows;
         self.cols = cols;
         self.row_indices.resize(rows, self.values.len() as u32);
+        self.transposed.take();
     }
 
     /// Set the value at the given row and column.
     pub fn set(&mut self, row: usize, col: usize, value: InternedFieldElement) {
         assert!(row < self.rows, "row index out of bounds");
         assert!(col < self.cols, "column index out of bounds");
+        self.transposed.take();
 
         // Find the row
         let row_range = self.row_range(row);
@@ -95,6 +115,24 @@ impl SparseMatrix {
         }
     }
 
+    /// Append a row with entries sorted by column, in time linear in the
+    /// number of entries.
+    pub fn push_row(&mut self, entries: impl IntoIterator<Item = (usize, InternedFieldElement)>) {
+        self.rows += 1;
+        self.row_indices.push(self.values.len() as u32);
+        self.transposed.take();
+        let start = self.col_indices.len();
+        for (col, value) in entries {
+            assert!(col < self.cols, "column index out of bounds");
+            self.col_indices.push(col as u32);
+            self.values.push(value);
+        }
+        debug_assert!(
+            self.col_indices[start..].windows(2).all(|w| w[0] < w[1]),
+            "row entries must be sorted by column"
+        );
+    }
+
     /// Iterate over the non-default entries of a row of the matrix.
     pub fn iter_row(
         &self,
@@ -114,6 +152,38 @@ impl SparseMatrix {
         })
     }
 
+    /// The transposed matrix, i.e. this matrix in compressed column format.
+    /// Cached after the first call.
+    pub fn transposed(&self) -> &SparseMatrix {
+        self.transposed.get_or_init(|| {
+            let mut row_indices = vec![0u32; self.cols + 1];
+            for &col in &self.col_indices {
+                row_indices[col as usize + 1] += 1;
+            }
+            for col in 0..self.cols {
+                row_indices[col + 1] += row_indices[col];
+            }
+            let mut next = row_indices.clone();
+            let mut col_indices = vec![0u32; self.values.len()];
+            let mut values = self.values.clone();
+            for ((row, col), value) in self.iter() {
+                let i = &mut next[col];
+                col_indices[*i as usize] = row as u32;
+                values[*i as usize] = value;
+                *i += 1;
+            }
+            row_indices.pop();
+            Box::new(SparseMatrix {
+                rows: self.cols,
+                cols: self.rows,
+                row_indices,
+                col_indices,
+                values,
+                transposed: OnceLock::new(),
+            })
+        })
+    }
+
     fn row_range(&self, row: usize) -> Range<usize> {
         let start = *self.row_indices.get(row).expect("Row index out of bounds") as usize;
         let end = self
@@ -125,7 +195,70 @@ impl SparseMatrix {
     }
 }
 
+impl SparseMatrixBuilder {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn grow(&mut self, rows: usize, cols: usize) {
+        assert!(rows >= self.rows);
+        assert!(cols >= self.cols);
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    /// Set the value at the given row and column. Later values overwrite
+    /// earlier ones.
+    pub fn set(&mut self, row: usize, col: usize, value: InternedFieldElement) {
+        assert!(row < self.rows, "row index out of bounds");
+        assert!(col < self.cols, "column index out of bounds");
+        self.entries.push((row as u32, col as u32, value));
+    }
+
+    /// Sort the triplets into a compressed row matrix.
+    pub fn build(mut self) -> SparseMatrix {
+        // Stable, so the last write to an entry ends up last among equals
+        self.entries.sort_by_key(|&(row, col, _)| (row, col));
+        let mut matrix = SparseMatrix::new(0, self.cols);
+        let mut entries = self.entries.into_iter().peekable();
+        for row in 0..self.rows as u32 {
+            let mut cols = Vec::new();
+            while let Some((_, col, value)) = entries.next_if(|&(r, ..)| r == row) {
+                if entries
+                    .peek()
+                    .is_some_and(|&(r, c, _)| (r, c) == (row, col))
+                {
+                    continue;
+                }
+                cols.push((col as usize, value));
+            }
+            matrix.push_row(cols);
+        }
+        matrix
+    }
+}
+
 impl<'a, F: Field> HydratedSparseMatrix<'a, F> {
+    /// The transposed matrix. See [`SparseMatrix::transposed`].
+    pub fn transposed(&self) -> HydratedSparseMatrix<'a, F> {
+        self.matrix.transposed().hydrate(self.interner)
+    }
+
+    pub fn num_entries(&self) -> usize {
+        self.matrix.num_entries()
+    }
+
+    /// Inner product of a row with a vector.
+    fn dot_row(&self, row: usize, rhs: &[F]) -> F {
+        self.iter_row(row)
+            .map(|(col, value)| value * rhs[col])
+            .sum()
+    }
+
     /// Iterate over the non-default entries of a row of the matrix.
     pub fn iter_row(&self, row: usize) -> impl Iterator<Item = (usize, F)> + use<'_, F> {
         self.matrix.iter_row(row).map(|(col, value)| {
@@ -147,8 +280,48 @@ impl<'a, F: Field> HydratedSparseMatrix<'a, F> {
     }
 }
 
-/// Right multiplication by vector
-// OPT: Paralelize
+impl PartialEq for SparseMatrix {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self.row_indices == other.row_indices
+            && self.col_indices == other.col_indices
+            && self.values == other.values
+    }
+}
+
+/// Compute `A·z`, `B·z` and `C·z` in a single parallel pass over the rows.
+pub fn matrix_vector_products<F: Field>(
+    matrices: [HydratedSparseMatrix<'_, F>; 3],
+    z: &[F],
+) -> [Vec<F>; 3] {
+    let rows = matrices[0].matrix.rows;
+    for matrix in &matrices {
+        assert_eq!(
+            matrix.matrix.rows, rows,
+            "Matrices differ in number of rows."
+        );
+        assert_eq!(
+            matrix.matrix.cols,
+            z.len(),
+            "Vector length does not match number of columns."
+        );
+    }
+    let mut result = [(); 3].map(|_| vec![F::zero(); rows]);
+    let [a, b, c] = &mut result;
+    a.par_iter_mut()
+        .zip(b.par_iter_mut())
+        .zip(c.par_iter_mut())
+        .enumerate()
+        .for_each(|(row, ((a, b), c))| {
+            *a = matrices[0].dot_row(row, z);
+            *b = matrices[1].dot_row(row, z);
+            *c = matrices[2].dot_row(row, z);
+        });
+    result
+}
+
+/// Right multiplication by vector, parallel over the rows.
 impl<F: Field> Mul<&[F]> for HydratedSparseMatrix<'_, F> {
     type Output = Vec<F>;
 
@@ -158,16 +331,15 @@ impl<F: Field> Mul<&[F]> for HydratedSparseMatrix<'_, F> {
             rhs.len(),
             "Vector length does not match number of columns."
         );
-        let mut result = vec![F::zero(); self.matrix.rows];
-        for ((i, j), value) in self.iter() {
-            result[i] += value * &rhs[j];
-        }
-        result
+        (0..self.matrix.rows)
+            .into_par_iter()
+            .map(|row| self.dot_row(row, rhs))
+            .collect()
     }
 }
 
-/// Left multiplication by vector
-// OPT: Paralelize
+/// Left multiplication by vector, as right multiplication by the cached
+/// transpose.
 impl<F: Field> Mul<HydratedSparseMatrix<'_, F>> for &[F] {
     type Output = Vec<F>;
 
@@ -177,11 +349,7 @@ impl<F: Field> Mul<HydratedSparseMatrix<'_, F>> for &[F] {
             rhs.matrix.rows,
             "Vector length does not match number of rows."
         );
-        let mut result = vec![F::zero(); rhs.matrix.cols];
-        for ((i, j), value) in rhs.iter() {
-            result[j] += value * &self[i];
-        }
-        result
+        rhs.transposed() * self
     }
 }
 
@@ -195,4 +363,42 @@ mod tests {
         let cols = 100;
         let mut matrix = SparseMatrix::new(rows, cols);
     }
+
+    #[test]
+    fn test_builder() {
+        let mut interner = Interner::<ark_bn254::Fr>::default();
+        let values = [1u64, 2, 3, 4].map(|v| interner.intern(v.into()));
+        let entries = [(2, 1, 0), (0, 3, 1), (2, 0, 2), (0, 3, 3), (1, 2, 1)];
+
+        let mut expected = SparseMatrix::new(4, 4);
+        let mut builder = SparseMatrixBuilder::new(4, 4);
+        for (row, col, value) in entries {
+            expected.set(row, col, values[value]);
+            builder.set(row, col, values[value]);
+        }
+        assert_eq!(builder.build(), expected);
+    }
+
+    #[test]
+    fn test_products() {
+        use ark_bn254::Fr;
+
+        let mut interner = Interner::<Fr>::default();
+        let mut matrix = SparseMatrix::new(3, 4);
+        for (row, col, value) in [(0, 1, 2u64), (0, 3, 3), (2, 0, 4), (2, 3, 5)] {
+            matrix.set(row, col, interner.intern(value.into()));
+        }
+        let matrix = matrix.hydrate(&interner);
+        let z = [1u64, 2, 3, 4].map(Fr::from);
+        let y = [1u64, 2, 3].map(Fr::from);
+
+        let expected = [16u64, 0, 24].map(Fr::from);
+        assert_eq!(matrix * &z[..], expected);
+        let [a, b, c] = matrix_vector_products([matrix; 3], &z);
+        assert!(a == expected && b == expected && c == expected);
+
+        let expected = [12u64, 2, 0, 18].map(Fr::from);
+        assert_eq!(&y[..] * matrix, expected);
+        assert_eq!(matrix.transposed().transposed().matrix, matrix.matrix);
+    }
 }
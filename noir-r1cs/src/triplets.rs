@@ -0,0 +1,161 @@
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    utils::serde_decimal, FieldElement, HydratedSparseMatrix, Interner, SparseMatrixBuilder, R1CS,
+};
+
+/// An R1CS instance as explicit `(row, col, value)` triplets, with values as
+/// signed decimal strings.
+///
+/// This is the interchange format for R1CS instances that do not come from a
+/// Noir program. Witness `0` is the constant one and witnesses
+/// `1..=public_inputs` are the public values. Witness hints are not part of
+/// the format, so a full `witness` has to be provided to prove an instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct R1CSTriplets {
+    pub public_inputs: usize,
+    pub witnesses: usize,
+    pub constraints: usize,
+    pub a: Vec<Triplet>,
+    pub b: Vec<Triplet>,
+    pub c: Vec<Triplet>,
+    /// A satisfying witness, if known.
+    #[serde(
+        default,
+        with = "serde_decimal::vec",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub witness: Vec<FieldElement>,
+}
+
+/// A matrix entry as `(row, col, value)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Triplet(
+    pub usize,
+    pub usize,
+    #[serde(with = "serde_decimal")] pub FieldElement,
+);
+
+impl From<&R1CS> for R1CSTriplets {
+    fn from(r1cs: &R1CS) -> Self {
+        let triplets = |matrix: HydratedSparseMatrix<'_, FieldElement>| {
+            matrix
+                .iter()
+                .map(|((row, col), value)| Triplet(row, col, value))
+                .collect()
+        };
+        Self {
+            public_inputs: r1cs.public_inputs,
+            witnesses: r1cs.witnesses,
+            constraints: r1cs.constraints,
+            a: triplets(r1cs.a()),
+            b: triplets(r1cs.b()),
+            c: triplets(r1cs.c()),
+            witness: Vec::new(),
+        }
+    }
+}
+
+impl TryFrom<&R1CSTriplets> for R1CS {
+    type Error = anyhow::Error;
+
+    /// Rebuild an [`R1CS`] from triplets. Repeated entries are summed and
+    /// zeros are dropped, matching
+    /// [`R1CSBuilder::add_constraint`](crate::R1CSBuilder::add_constraint).
+    fn try_from(triplets: &R1CSTriplets) -> Result<Self> {
+        ensure!(triplets.witnesses > 0, "Missing the constant one witness");
+        ensure!(
+            triplets.public_inputs < triplets.witnesses,
+            "{} public inputs do not fit in {} witnesses",
+            triplets.public_inputs,
+            triplets.witnesses
+        );
+        let mut interner = Interner::default();
+        let mut build = |name: &str, entries: &[Triplet]| -> Result<_> {
+            let mut rows = vec![Vec::new(); triplets.constraints];
+            for &Triplet(row, col, value) in entries {
+                ensure!(
+                    row < triplets.constraints && col < triplets.witnesses,
+                    "Entry ({row}, {col}) of {name} is out of bounds"
+                );
+                rows[row].push((value, col));
+            }
+            let mut matrix = SparseMatrixBuilder::new(triplets.constraints, triplets.witnesses);
+            for (row, terms) in rows.iter().enumerate() {
+                for (col, value) in crate::r1cs::merge_terms(terms) {
+                    matrix.set(row, col, interner.intern(value));
+                }
+            }
+            Ok(matrix.build())
+        };
+        let a = build("A", &triplets.a)?;
+        let b = build("B", &triplets.b)?;
+        let c = build("C", &triplets.c)?;
+        Ok(R1CS {
+            public_inputs: triplets.public_inputs,
+            witnesses: triplets.witnesses,
+            constraints: triplets.constraints,
+            interner,
+            a,
+            b,
+            c,
+            hints: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_serde, R1CSBuilder};
+
+    #[test]
+    fn test_roundtrip() {
+        // x · x = y, with y public
+        let mut r1cs = R1CSBuilder::new();
+        r1cs.public_inputs = 1;
+        for _ in 0..3 {
+            r1cs.new_witness();
+        }
+        let one = FieldElement::from(1);
+        r1cs.add_constraint(&[(one, 2)], &[(one, 2)], &[(one, 1)]);
+        r1cs.add_constraint(
+            &[(-one, 0), (one, 2)],
+            &[(one, 0)],
+            &[(FieldElement::from(2), 0)],
+        );
+        let r1cs = r1cs.build();
+
+        let mut triplets = R1CSTriplets::from(&r1cs);
+        assert_eq!(triplets.a[1], Triplet(1, 0, -one));
+        triplets.witness = vec![one, FieldElement::from(9), FieldElement::from(3)];
+        test_serde(&triplets);
+
+        let json = serde_json::to_value(&triplets).unwrap();
+        assert_eq!(json["a"][1], serde_json::json!([1, 0, "-1"]));
+
+        let imported = R1CS::try_from(&triplets).unwrap();
+        assert_eq!(
+            imported.products(&triplets.witness),
+            r1cs.products(&triplets.witness)
+        );
+        imported.verify_witness(&triplets.witness).unwrap();
+        // The interner differs, the digest does not
+        assert_eq!(imported.digest(), r1cs.digest());
+
+        assert_eq!(
+            imported.display_constraint(1, None).to_string(),
+            "1: (-1 + w2) · (1) = (2)"
+        );
+        assert_eq!(
+            imported
+                .display_constraint(1, Some(&triplets.witness))
+                .to_string(),
+            "1: (-1 + w2) · (1) = (2)\n   (-1 + 3) · (1) = (2)  →  2 · 1 = 2 ✓"
+        );
+
+        triplets.c.push(Triplet(2, 0, one));
+        assert!(R1CS::try_from(&triplets).is_err());
+    }
+}
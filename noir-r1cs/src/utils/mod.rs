@@ -1,5 +1,6 @@
 mod print_abi;
 pub mod serde_ark;
+pub mod serde_decimal;
 // pub mod serde_hex;
 pub mod serde_jsonify;
 // pub mod sumcheck;
@@ -7,6 +8,7 @@ pub mod serde_jsonify;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use acir::AcirField;
+use ark_ff::{BigInteger, PrimeField};
 use num_bigint::BigUint;
 
 pub use self::print_abi::PrintAbi;
@@ -19,6 +21,12 @@ pub fn noir_to_native(n: NoirElement) -> FieldElement {
     FieldElement::from(number)
 }
 
+/// Convert a native FieldElement to a Noir field element
+#[inline(always)]
+pub fn native_to_noir(n: FieldElement) -> NoirElement {
+    NoirElement::from_be_bytes_reduce(&n.into_bigint().to_bytes_be())
+}
+
 /// Pretty print a float using SI-prefixes.
 pub fn human(value: f64) -> impl Display {
     struct Human(f64);
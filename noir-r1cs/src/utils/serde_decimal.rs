@@ -0,0 +1,102 @@
+//! Serde encoding of field elements as signed decimal strings, for formats
+//! meant to be read and written by hand.
+//!
+//! Elements in the upper half of the field are written as negative numbers.
+//! Parsing also accepts `0x` prefixed hexadecimal and reduces modulo the
+//! field size.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use anyhow::{ensure, Context as _, Result};
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use serde::{de::Error as _, Deserialize as _, Deserializer, Serializer};
+
+use crate::FieldElement;
+
+/// Display a field element as a signed decimal.
+pub fn signed(value: FieldElement) -> impl Display {
+    struct Signed(FieldElement);
+    impl Display for Signed {
+        fn fmt(&self, f: &mut Formatter) -> FmtResult {
+            if self.0.into_bigint() > FieldElement::MODULUS_MINUS_ONE_DIV_TWO {
+                write!(f, "-{}", -self.0)
+            } else {
+                write!(f, "{}", self.0)
+            }
+        }
+    }
+    Signed(value)
+}
+
+/// Parse a signed decimal or `0x` prefixed hexadecimal field element.
+pub fn parse(str: &str) -> Result<FieldElement> {
+    let str = str.trim();
+    let (negative, digits) = match str.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, str),
+    };
+    let number = match digits.strip_prefix("0x") {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::parse_bytes(digits.as_bytes(), 10),
+    };
+    let number = number.with_context(|| format!("invalid field element {str:?}"))?;
+    ensure!(
+        number < BigUint::from(FieldElement::MODULUS),
+        "field element {str:?} exceeds the field size"
+    );
+    let value = FieldElement::from(number);
+    Ok(if negative { -value } else { value })
+}
+
+pub fn serialize<S: Serializer>(value: &FieldElement, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&signed(*value))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FieldElement, D::Error> {
+    let str = String::deserialize(deserializer)?;
+    parse(&str).map_err(D::Error::custom)
+}
+
+/// The same encoding for vectors of field elements.
+pub mod vec {
+    use serde::{de::Error as _, ser::SerializeSeq as _, Deserialize as _};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        values: &[FieldElement],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&signed(*value).to_string())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<FieldElement>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|str| parse(str).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed() {
+        for value in [0, 1, 12345, -1, -12345] {
+            let value = FieldElement::from(value as i64);
+            assert_eq!(parse(&signed(value).to_string()).unwrap(), value);
+        }
+        assert_eq!(signed(-FieldElement::from(7)).to_string(), "-7");
+        assert_eq!(parse("0x10").unwrap(), FieldElement::from(16));
+        assert!(parse("12a").is_err());
+    }
+}
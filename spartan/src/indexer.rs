@@ -18,6 +18,8 @@ use ark_poly_commit::{
 };
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use itertools::Itertools;
+use noir_r1cs::Digest;
+use sha2::{Digest as _, Sha256};
 
 use super::zk::{ZKMLCommit, ZKMLCommitterKey, ZKMLVerifierKey, SRS};
 use crate::{
@@ -25,6 +27,8 @@ use crate::{
     utils::{normalized_multiplicities, pad_with_first_term},
 };
 
+const VK_DIGEST_DOMAIN: &[u8] = b"spartan IndexVerifierKey digest v1";
+
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct IndexProverKey<E: Pairing> {
     pub rows: Vec<usize>,
@@ -54,14 +58,35 @@ pub struct IndexVerifierKey<E: Pairing> {
     pub val_a_oracle: Commitment<E>,
     pub val_b_oracle: Commitment<E>,
     pub val_c_oracle: Commitment<E>,
+    /// Commitments to the row and column addresses of the non-zero entries,
+    /// padded with the first entry.
+    pub row_oracle: Commitment<E>,
+    pub col_oracle: Commitment<E>,
+    /// Commitments to the multiplicities of the row and column addresses.
+    pub freq_r_oracle: Commitment<E>,
+    pub freq_c_oracle: Commitment<E>,
     pub real_len_val: usize,
     pub num_variables_val: usize,
     pub padded_num_var: usize,
+    /// Number of public values following the constant one in `z`.
+    pub num_public_inputs: usize,
     pub vk_w: ZKMLVerifierKey<E>,
     pub vk_index: VerifierKey<E>,
     pub vk_mask: MaskVerifierKey<E>,
 }
 
+impl<E: Pairing> IndexVerifierKey<E> {
+    /// SHA-256 fingerprint of the compressed key. It is absorbed into the
+    /// transcript, binding proofs to the key.
+    pub fn digest(&self) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(VK_DIGEST_DOMAIN);
+        self.serialize_compressed(&mut hasher)
+            .expect("Writing to a hasher can not fail");
+        hasher.finalize().into()
+    }
+}
+
 pub struct Indexer<E: Pairing> {
     _marker: PhantomData<E>,
 }
@@ -77,14 +102,15 @@ impl<E: Pairing> Indexer<E> {
     ) -> (IndexProverKey<E>, IndexVerifierKey<E>) {
         let log_instance_size = r1cs.log2_instance_size();
 
-        // Convert sparse matrix entries from noir-r1cs format to our format
+        // Convert sparse matrix entries from noir-r1cs format to our format,
+        // with the columns at the positions of the variables in `z`
         let convert_matrix =
             |matrix: &noir_r1cs::SparseMatrix| -> Vec<SparseMatEntry<E::ScalarField>> {
                 let hydrated = matrix.hydrate(&r1cs.interner);
                 let mut entries = Vec::new();
 
                 for ((row, col), value) in hydrated.iter() {
-                    entries.push(SparseMatEntry::new(row, col, value));
+                    entries.push(SparseMatEntry::new(row, r1cs.z_index(col), value));
                 }
 
                 entries
@@ -180,9 +206,10 @@ impl<E: Pairing> Indexer<E> {
         v_c.resize(count, E::ScalarField::zero());
         row.resize(count, usize::MAX);
         col.resize(count, usize::MAX);
+        // Only `w`, the odd half of `z`, is committed
         let (ck_w, vk_w) = ZKMLCommit::<E, SparsePolynomial<E::ScalarField, SparseTerm>>::trim(
             param,
-            padded_num_var,
+            padded_num_var - 1,
             2,
         );
         let (ck_index, vk_index) = MultilinearPC::trim(param_index, num_non_zero_var);
@@ -214,6 +241,10 @@ impl<E: Pairing> Indexer<E> {
         let col_poly = DenseMultilinearExtension::from_evaluations_vec(num_non_zero_var, col_vec);
         let freq_row = normalized_multiplicities(&row_poly, &domain_poly);
         let freq_col = normalized_multiplicities(&col_poly, &domain_poly);
+        let row_oracle = MultilinearPC::commit(&ck_index, &row_poly);
+        let col_oracle = MultilinearPC::commit(&ck_index, &col_poly);
+        let freq_r_oracle = MultilinearPC::commit(&ck_index, &freq_row);
+        let freq_c_oracle = MultilinearPC::commit(&ck_index, &freq_col);
 
         //let (ck, vk) = MultilinearPC::trim(param, num_non_zero_var);
         (
@@ -243,9 +274,14 @@ impl<E: Pairing> Indexer<E> {
                 // col: (col),
                 real_len_val,
                 padded_num_var,
+                num_public_inputs: r1cs.public_inputs,
                 val_a_oracle,
                 val_b_oracle,
                 val_c_oracle,
+                row_oracle,
+                col_oracle,
+                freq_r_oracle,
+                freq_c_oracle,
                 num_variables_val: (num_non_zero_var),
                 vk_w,
                 vk_index,
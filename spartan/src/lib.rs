@@ -3,6 +3,7 @@
 pub mod indexer;
 pub mod logup;
 pub mod math;
+pub mod prover;
 pub mod r1cs;
 pub mod transcript;
 pub mod utils;
@@ -11,7 +12,7 @@ pub mod zk;
 
 use ark_ec::pairing::Pairing;
 use ark_poly_commit::multilinear_pc::data_structures::Commitment;
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use bytesize::ByteSize;
 pub use indexer::{IndexProverKey, IndexVerifierKey, Indexer};
 pub use logup::LogLookupProof;
@@ -19,8 +20,28 @@ pub use r1cs::R1CS;
 pub use zk::SRS;
 use zk::{ZKMLProof, ZKSumcheckProof};
 
+/// Version of the [`R1CSProof`] encoding. The major version changes when
+/// proofs of the previous version can no longer be read.
+pub const PROOF_FORMAT_VERSION: (u16, u16) = (1, 0);
+
 /// The SNARK proof, composed of all prover's messages sent throughout the protocol.
-#[derive(CanonicalSerialize)]
+///
+/// The raw encoding of a proof is its uncompressed canonical serialization. It
+/// is unversioned: the versioned wire format is the `co-spartan` artifact
+/// container, whose header records [`PROOF_FORMAT_VERSION`] next to the proof
+/// bytes. The encoding is the concatenation of the fields in declaration
+/// order, recursively. Leaves are encoded as
+///
+/// - `usize`: 8 bytes, little endian,
+/// - `Vec`: its length as a `usize`, then its elements,
+/// - `Option`: one byte, 0 or 1, then the value if present,
+/// - scalars: the canonical integer in 32 bytes, little endian,
+/// - G1 points: `x` and `y` as base field elements in the same way, with the
+///   `SWFlags` of the point in the two top bits of the last byte.
+///
+/// `testdata/r1cs_proof_v1.hex` annotates the encoding of a small proof field
+/// by field. Changing the encoding requires bumping [`PROOF_FORMAT_VERSION`].
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
 pub struct R1CSProof<E: Pairing> {
     pub witness_commitment: Commitment<E>,
 
@@ -79,3 +100,130 @@ impl<E: Pairing> R1CSProof<E> {
         );
     }
 }
+
+/// The proof encoded in `testdata/r1cs_proof_v1.hex`, with small, distinct
+/// values in every field.
+#[cfg(test)]
+fn golden_proof() -> R1CSProof<ark_bn254::Bn254> {
+    use ark_bn254::{Fr, G1Affine};
+    use ark_ec::AffineRepr;
+    use ark_linear_sumcheck::ml_sumcheck::protocol::{prover::ProverMsg, PolynomialInfo};
+    use ark_poly_commit::{
+        kzg10, marlin_pc, marlin_pst13_pc, multilinear_pc::data_structures::Proof,
+    };
+
+    use crate::verifier::BatchOracleEval;
+
+    let g = G1Affine::generator();
+    let f = |v: u64| Fr::from(v);
+    let commitment = |nv| Commitment { nv, g_product: g };
+    let sumcheck = |first| ZKSumcheckProof {
+        g_commit: marlin_pc::Commitment {
+            comm: kzg10::Commitment(g),
+            shifted_comm: None,
+        },
+        sumcheck_proof: vec![ProverMsg {
+            evaluations: vec![f(first), f(first + 1)],
+        }],
+        poly_info: PolynomialInfo {
+            max_multiplicands: 3,
+            num_variables: 1,
+        },
+        g_proof: marlin_pst13_pc::Proof {
+            w: vec![g],
+            random_v: None,
+        },
+        g_value: f(first + 2),
+    };
+    R1CSProof {
+        witness_commitment: commitment(2),
+        first_sumcheck_msgs: sumcheck(1),
+        va: f(4),
+        vb: f(5),
+        vc: f(6),
+        second_sumcheck_msgs: sumcheck(7),
+        witness_eval: f(10),
+        val_m: f(11),
+        witness_proof: (Proof { proofs: vec![-g] }, f(12)),
+        eq_tilde_rx_commitment: commitment(1),
+        eq_tilde_ry_commitment: commitment(1),
+        lookup_proof: LogLookupProof {
+            sumcheck_pfs: vec![ProverMsg {
+                evaluations: vec![f(13), f(14)],
+            }],
+            info: PolynomialInfo {
+                max_multiplicands: 2,
+                num_variables: 1,
+            },
+            point: vec![f(15)],
+            batch_oracle: BatchOracleEval {
+                val: vec![f(16)],
+                commitment: vec![commitment(1)],
+                proof: Proof { proofs: vec![g] },
+            },
+            degree_diff: 0,
+        },
+    }
+}
+
+#[test]
+fn test_proof_encoding_is_stable() {
+    use ark_serialize::{Compress, Validate};
+
+    let golden = include_str!("../testdata/r1cs_proof_v1.hex")
+        .lines()
+        .flat_map(|line| {
+            let hex = line.split('#').next().unwrap().trim();
+            (0..hex.len())
+                .step_by(2)
+                .map(move |i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        })
+        .collect::<Vec<u8>>();
+
+    let mut bytes = Vec::new();
+    golden_proof().serialize_uncompressed(&mut bytes).unwrap();
+    assert_eq!(
+        bytes, golden,
+        "the proof encoding changed, see PROOF_FORMAT_VERSION"
+    );
+
+    let proof = R1CSProof::<ark_bn254::Bn254>::deserialize_with_mode(
+        &golden[..],
+        Compress::No,
+        Validate::Yes,
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    proof.serialize_uncompressed(&mut bytes).unwrap();
+    assert_eq!(bytes, golden);
+}
+
+#[test]
+fn test_proof_roundtrip() {
+    use ark_bn254::Bn254;
+    use ark_serialize::{Compress, Validate};
+
+    use ark_std::test_rng;
+
+    use crate::{prover::test_instance, transcript::TranscriptMerlin};
+
+    let (r1cs, witness, ipk, vk) = test_instance(5);
+    let proof = R1CSProof::prove(
+        &ipk,
+        &vk,
+        &r1cs,
+        &witness,
+        &mut TranscriptMerlin::new(b"dfs"),
+        &mut test_rng(),
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    proof.serialize_uncompressed(&mut bytes).unwrap();
+
+    let proof =
+        R1CSProof::<Bn254>::deserialize_with_mode(&bytes[..], Compress::No, Validate::Yes).unwrap();
+    proof
+        .verify(&vk, &witness[1..2], &mut TranscriptMerlin::new(b"dfs"))
+        .unwrap();
+    assert!(R1CSProof::<Bn254>::deserialize_uncompressed(&bytes[..bytes.len() - 1]).is_err());
+}
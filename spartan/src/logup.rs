@@ -1,18 +1,22 @@
+use std::cmp::max;
+
 use ark_ec::pairing::Pairing;
 use ark_ff::{AdditiveGroup, Field, One, Zero};
-use ark_linear_sumcheck::ml_sumcheck::{protocol::PolynomialInfo, MLSumcheck};
+use ark_linear_sumcheck::ml_sumcheck::{
+    data_structures::ListOfProductsOfPolynomials, protocol::PolynomialInfo, MLSumcheck,
+};
 use ark_poly::{DenseMultilinearExtension, MultilinearExtension};
 use ark_poly_commit::multilinear_pc::{
     data_structures::{Commitment, CommitterKey, VerifierKey},
     MultilinearPC,
 };
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::cfg_iter;
+use ark_std::{cfg_into_iter, cfg_iter, rc::Rc};
 use rayon::prelude::*;
 
 use crate::{
     transcript::Transcript,
-    utils::{boost_degree, eq_eval, map_poly, two_pow_n},
+    utils::{boost_degree, dense_scalar_prod, eq_eval, map_poly, partial_generate_eq, two_pow_n},
     verifier::{batch_verify_poly, BatchOracleEval, VerificationResult},
 };
 
@@ -113,10 +117,15 @@ impl<E: Pairing> LogLookupProof<E> {
         info: &PolynomialInfo,
         sumcheck_pfs: &SumcheckProof<E>,
         batch_oracle: &BatchOracleEval<E>,
+        commitments: &[Commitment<E>],
         degree_diff: usize,
         lookup_x: &[E::ScalarField],
         z: &Vec<Vec<E::ScalarField>>,
         lambda: &[E::ScalarField],
+        eq_points: &[&[E::ScalarField]],
+        v_msg: E::ScalarField,
+        query_evals: &[E::ScalarField],
+        freq_evals: &[E::ScalarField],
         vk: &VerifierKey<E>,
         transcript: &mut T,
         aux_eval: E::ScalarField,
@@ -140,16 +149,16 @@ impl<E: Pairing> LogLookupProof<E> {
 
         for i in 0..z.len() {
             let batch_oracle_val = &batch_oracle.val[2 * i..2 * i + 2];
-            let batch_oracle_debug_val = &batch_oracle.debug_val[3 * i..3 * i + 3];
+            let table_eval =
+                eval_eq_table(eq_points[i], &v_msg, &point[..point.len() - degree_diff]);
 
             let mut eta = lambda[i];
             eta = eta * lambda[i];
             let q0 = batch_oracle_val[0] * lambda[i]
                 + eq_eval(&point, &z[i])
                     * eta
-                    * (batch_oracle_val[0]
-                        * (batch_oracle_debug_val[2] + (lookup_x[i] * scaling_factor))
-                        - scaling_factor * batch_oracle_debug_val[0]);
+                    * (batch_oracle_val[0] * (table_eval + (lookup_x[i] * scaling_factor))
+                        - scaling_factor * freq_evals[i]);
 
             eta = eta * lambda[i];
             let mut neg_h_1_oracle = batch_oracle_val[1];
@@ -157,14 +166,14 @@ impl<E: Pairing> LogLookupProof<E> {
             let q1 = neg_h_1_oracle * lambda[i]
                 + eq_eval(&point, &z[i])
                     * eta
-                    * (batch_oracle_val[1] * (batch_oracle_debug_val[1] + lookup_x[i])
+                    * (batch_oracle_val[1] * (query_evals[i] + lookup_x[i])
                         - E::ScalarField::one());
             res = res + q0 + q1;
         }
 
         let eta = transcript.get_scalar_challenge(b"eta");
         let poly_oracle_verifications = batch_verify_poly(
-            &batch_oracle.commitment,
+            commitments,
             &batch_oracle.val,
             vk,
             &batch_oracle.proof,
@@ -189,3 +198,137 @@ impl<E: Pairing> LogLookupProof<E> {
         }
     }
 }
+
+/// Add the products of the logup sumcheck for one lookup, `h` and `phi` as
+/// returned by [`LogLookupProof::prove`], to `q_polys`. Only the chunk of
+/// `2^log_chunk_size` evaluations starting at `start` is built.
+pub fn append_sumcheck_polys<F: Field>(
+    h: (DenseMultilinearExtension<F>, DenseMultilinearExtension<F>),
+    phi: (DenseMultilinearExtension<F>, DenseMultilinearExtension<F>),
+    m: DenseMultilinearExtension<F>,
+    degree_diff: usize,
+    q_polys: &mut ListOfProductsOfPolynomials<F>,
+    z: &Vec<F>,
+    lambda: &F,
+    start: usize,
+    log_chunk_size: usize,
+) {
+    assert_eq!(h.0.num_vars, phi.0.num_vars);
+    assert_eq!(h.0.num_vars, m.num_vars);
+
+    let mut eta: F = *lambda;
+
+    let lagrange = partial_generate_eq(&z, start, log_chunk_size);
+
+    let q_0_h = vec![Rc::new(h.0.clone())];
+    let q_0_h_times_phi = vec![Rc::new(lagrange.clone()), Rc::new(h.0), Rc::new(phi.0)];
+    let q_0_m = vec![Rc::new(lagrange.clone()), Rc::new(m)];
+
+    q_polys.add_product(q_0_h, *lambda);
+    eta = eta * lambda;
+    q_polys.add_product(q_0_h_times_phi, eta);
+    q_polys.add_product(
+        q_0_m,
+        two_pow_n::<F>(degree_diff).inverse().unwrap() * eta.neg(),
+    );
+
+    let q_1_h = vec![Rc::new(h.1.clone())];
+    let q_1_h_times_phi = vec![Rc::new(lagrange.clone()), Rc::new(h.1), Rc::new(phi.1)];
+    let q_1_m = vec![Rc::new(lagrange)];
+
+    // eta = eta * lambda;
+    q_polys.add_product(q_1_h, lambda.neg());
+    eta = eta * lambda;
+    q_polys.add_product(q_1_h_times_phi, eta);
+    q_polys.add_product(q_1_m, eta.neg());
+}
+
+/// Addresses of `index` as a polynomial in `num_vars` variables. Padding
+/// entries (`usize::MAX`) take the address `first`, the first entry of the
+/// whole index, as in [`crate::utils::pad_with_first_term`].
+pub fn address_poly<F: Field>(
+    index: &[usize],
+    first: usize,
+    num_vars: usize,
+) -> DenseMultilinearExtension<F> {
+    DenseMultilinearExtension::from_evaluations_vec(
+        num_vars,
+        cfg_iter!(index)
+            .map(|&i| {
+                F::from(if i == usize::MAX {
+                    first as u64
+                } else {
+                    i as u64
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Query of the lookup showing that `eq_tilde[i] = eq(r, address[i])`: each
+/// address fingerprinted with its value, `address[i] + v_msg · eq_tilde[i]`.
+/// The verifier evaluates it from the openings of `address` and `eq_tilde`.
+pub fn eq_query<F: Field>(
+    address: &DenseMultilinearExtension<F>,
+    eq_tilde: &DenseMultilinearExtension<F>,
+    v_msg: &F,
+) -> DenseMultilinearExtension<F> {
+    address + &dense_scalar_prod(v_msg, eq_tilde)
+}
+
+/// Table of the lookup for `eq = eq(r, ·)`: `d + v_msg · eq(r, d)` for the
+/// `2^log_size` addresses `d` starting at `start`. Addresses past the end of
+/// `eq` have `eq(r, d) = 0`.
+pub fn eq_table<F: Field>(
+    eq: &DenseMultilinearExtension<F>,
+    v_msg: &F,
+    start: usize,
+    log_size: usize,
+) -> DenseMultilinearExtension<F> {
+    DenseMultilinearExtension::from_evaluations_vec(
+        log_size,
+        cfg_into_iter!(start..start + (1 << log_size))
+            .map(|d| F::from(d as u64) + *v_msg * eq.evaluations.get(d).unwrap_or(&F::zero()))
+            .collect(),
+    )
+}
+
+/// Evaluation of the full [`eq_table`] for `eq(r, ·)` at `point`, computed
+/// by the verifier in `O(|r| + |point|)`.
+pub fn eval_eq_table<F: Field>(r: &[F], v_msg: &F, point: &[F]) -> F {
+    // The address of `point`, least significant variable first
+    let address = point
+        .iter()
+        .rev()
+        .fold(F::zero(), |acc, p| acc.double() + p);
+    // Padding the shorter point with zeros gives the restriction of eq(r, ·)
+    // to the table when the table is smaller, and its extension by zero
+    // when the table is larger.
+    let len = max(r.len(), point.len());
+    let pad = |x: &[F]| {
+        let mut x = x.to_vec();
+        x.resize(len, F::zero());
+        x
+    };
+    address + *v_msg * eq_eval(&pad(r), &pad(point))
+}
+
+#[test]
+fn test_eval_eq_table() {
+    use ark_bn254::Fr;
+    use ark_ff::UniformRand;
+    use ark_poly::Polynomial;
+    use ark_std::test_rng;
+
+    use crate::utils::generate_eq;
+
+    let rng = &mut test_rng();
+    let v_msg = Fr::rand(rng);
+    // Tables smaller than, as large as and larger than the domain of eq(r, ·)
+    for (r_len, num_vars) in [(5, 3), (4, 4), (3, 5)] {
+        let r = (0..r_len).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        let point = (0..num_vars).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        let table = eq_table(&generate_eq(&r), &v_msg, 0, num_vars);
+        assert_eq!(table.evaluate(&point), eval_eq_table(&r, &v_msg, &point));
+    }
+}
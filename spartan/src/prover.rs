@@ -0,0 +1,411 @@
+use std::cmp::max;
+
+use anyhow::{ensure, Result};
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ec::pairing::Pairing;
+use ark_ff::{Field, One, Zero};
+use ark_linear_sumcheck::ml_sumcheck::{
+    data_structures::ListOfProductsOfPolynomials, protocol::IPForMLSumcheck,
+};
+use ark_poly::{DenseMultilinearExtension, Polynomial};
+use ark_poly_commit::multilinear_pc::MultilinearPC;
+use ark_std::{
+    cfg_into_iter, cfg_iter,
+    rand::{CryptoRng, RngCore},
+    rc::Rc,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{
+    indexer::{IndexProverKey, IndexVerifierKey},
+    logup::{address_poly, append_sumcheck_polys, eq_query, eq_table, LogLookupProof},
+    math::{MaskPolynomial, Math},
+    r1cs::R1CS,
+    transcript::{init_transcript, Transcript},
+    utils::{aggregate_poly, boost_degree, dense_scalar_prod, generate_eq},
+    verifier::{BatchOracleEval, DFSVerifier, VerifierState},
+    zk::{zk_sumcheck_prover_wrapper, ZKMLCommit},
+    R1CSProof,
+};
+
+impl<E: Pairing> R1CSProof<E> {
+    /// Single process prover for the full witness `(1, public_inputs, w)` of
+    /// `r1cs`. It follows the distributed prover of `co-spartan` message by
+    /// message.
+    ///
+    /// The prover absorbs `vk` into the transcript like the verifier does. It
+    /// is not derived from `ipk`, which lacks the verifier parts of the SRS.
+    /// The masks hiding the witness are sampled from `rng`, never from the
+    /// transcript, which the verifier can replay.
+    #[tracing::instrument(skip_all, name = "R1CSProof::prove")]
+    pub fn prove<T: Transcript + CryptographicSponge, R: RngCore + CryptoRng>(
+        ipk: &IndexProverKey<E>,
+        vk: &IndexVerifierKey<E>,
+        r1cs: &R1CS<E::ScalarField>,
+        witness: &[E::ScalarField],
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<Self> {
+        ensure!(
+            witness.len() == r1cs.witnesses,
+            "expected {} witnesses, got {}",
+            r1cs.witnesses,
+            witness.len()
+        );
+        ensure!(
+            witness[0] == E::ScalarField::one(),
+            "witness must start with the constant one"
+        );
+        let public_inputs = &witness[1..=r1cs.public_inputs];
+        Self::prove_for_public_inputs(ipk, vk, r1cs, witness, public_inputs, transcript, rng)
+    }
+
+    /// Prove the satisfiability of `r1cs` by `witness`, claiming the public
+    /// values `public_inputs`. Only `prove` claims those of the witness.
+    fn prove_for_public_inputs<T: Transcript + CryptographicSponge, R: RngCore + CryptoRng>(
+        ipk: &IndexProverKey<E>,
+        vk: &IndexVerifierKey<E>,
+        r1cs: &R1CS<E::ScalarField>,
+        witness: &[E::ScalarField],
+        public_inputs: &[E::ScalarField],
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let num_variables = ipk.padded_num_var;
+        let [az, bz, cz] = r1cs.products(witness);
+        if let Some(row) = (0..r1cs.constraints).find(|&row| az[row] * bz[row] != cz[row]) {
+            anyhow::bail!("witness does not satisfy constraint {row}");
+        }
+        let az = Rc::new(pad_to_poly(az, num_variables));
+        let bz = Rc::new(pad_to_poly(bz, num_variables));
+        let cz = Rc::new(pad_to_poly(cz, num_variables));
+        let z = Rc::new(pad_to_poly(r1cs.z(witness), num_variables));
+        // Only the odd half of `z` is committed, the verifier adds the io part
+        // back in, see `R1CSProof::verify`.
+        let w = DenseMultilinearExtension::from_evaluations_vec(
+            num_variables - 1,
+            z.evaluations.iter().skip(1).step_by(2).copied().collect(),
+        );
+
+        let mut v_state: VerifierState<E> = DFSVerifier::verifier_init(num_variables);
+        init_transcript(transcript, vk, public_inputs);
+
+        // Commit to the witness
+        let (witness_commitment, witness_mask) =
+            ZKMLCommit::<E, MaskPolynomial<E>>::commit(&ipk.ck_w, &w, 2, None, rng);
+        transcript.append_serializable(b"w_commitment", &witness_commitment);
+
+        // Sumcheck of (Az ∘ Bz - Cz)(x) · eq(tau, x) = 0
+        let tau = DFSVerifier::verifier_first_round(&mut v_state, transcript).verifier_message;
+        let eq_tau = Rc::new(generate_eq(&tau));
+        let mut polys = ListOfProductsOfPolynomials::new(num_variables);
+        polys.add_product(
+            [az.clone(), bz.clone(), eq_tau.clone()],
+            E::ScalarField::one(),
+        );
+        polys.add_product([cz.clone(), eq_tau], -E::ScalarField::one());
+        let (first_sumcheck_msgs, r_x) =
+            zk_sumcheck_prover_wrapper(&ipk.ck_mask, &polys, transcript, rng);
+
+        let (va, vb, vc) = (az.evaluate(&r_x), bz.evaluate(&r_x), cz.evaluate(&r_x));
+        transcript.append_serializable(b"val_r1", &vec![va, vb, vc]);
+        transcript.append_serializable(b"first_sumcheck_msgs", &first_sumcheck_msgs);
+
+        // Sumcheck of Σ_i gamma_i · M_i(r_x, y) · z(y) for M = A, B, C
+        let gamma = DFSVerifier::verifier_second_round(&mut v_state, transcript).verifier_message;
+        let eq_rx = generate_eq(&r_x);
+        let mut m_rx = [(); 3].map(|_| vec![E::ScalarField::zero(); num_variables.exp2()]);
+        for i in 0..ipk.real_len_val {
+            let (row, col) = (ipk.rows[i], ipk.cols[i]);
+            m_rx[0][col] += ipk.val_a[i] * eq_rx[row];
+            m_rx[1][col] += ipk.val_b[i] * eq_rx[row];
+            m_rx[2][col] += ipk.val_c[i] * eq_rx[row];
+        }
+        let mut polys = ListOfProductsOfPolynomials::new(num_variables);
+        for (m_rx, gamma) in m_rx.into_iter().zip(&gamma) {
+            let m_rx = DenseMultilinearExtension::from_evaluations_vec(num_variables, m_rx);
+            polys.add_product([Rc::new(m_rx), z.clone()], *gamma);
+        }
+        let (second_sumcheck_msgs, r_y) =
+            zk_sumcheck_prover_wrapper(&ipk.ck_mask, &polys, transcript, rng);
+        transcript.append_serializable(b"second_sumcheck_msgs", &second_sumcheck_msgs);
+
+        // Evaluations at (r_x, r_y) and commitments to eq(r_x, row) and
+        // eq(r_y, col) over the non-zero entries, padded with the first entry
+        let witness_eval = w.evaluate(&r_y[1..].to_vec());
+        let eq_ry = generate_eq(&r_y);
+        let eq_tilde = |eq: &DenseMultilinearExtension<E::ScalarField>, index: &[usize]| {
+            DenseMultilinearExtension::from_evaluations_vec(
+                ipk.num_variables_val,
+                cfg_into_iter!(0..ipk.num_variables_val.exp2())
+                    .map(|i| {
+                        if i < ipk.real_len_val {
+                            eq[index[i]]
+                        } else {
+                            eq[index[0]]
+                        }
+                    })
+                    .collect(),
+            )
+        };
+        let eq_tilde_rx = eq_tilde(&eq_rx, &ipk.rows);
+        let eq_tilde_ry = eq_tilde(&eq_ry, &ipk.cols);
+        let val_m_poly = dense_scalar_prod(&gamma[0], &ipk.val_a)
+            + dense_scalar_prod(&gamma[1], &ipk.val_b)
+            + dense_scalar_prod(&gamma[2], &ipk.val_c);
+        let val_m: E::ScalarField = cfg_iter!(val_m_poly.evaluations)
+            .zip(&eq_tilde_rx.evaluations)
+            .zip(&eq_tilde_ry.evaluations)
+            .map(|((v, rx), ry)| *v * rx * ry)
+            .sum();
+        transcript.append_serializable(b"witness_eval", &[witness_eval, val_m]);
+
+        let eq_tilde_rx_commitment = MultilinearPC::commit(&ipk.ck_index, &eq_tilde_rx);
+        let eq_tilde_ry_commitment = MultilinearPC::commit(&ipk.ck_index, &eq_tilde_ry);
+        transcript.append_serializable(b"eq_tilde_rx_comm", &eq_tilde_rx_commitment);
+        transcript.append_serializable(b"eq_tilde_ry_comm", &eq_tilde_ry_commitment);
+
+        let witness_proof =
+            ZKMLCommit::<E, MaskPolynomial<E>>::open(&ipk.ck_w, &w, &witness_mask, &r_y[1..]);
+        transcript.append_serializable(b"w_proof", &witness_proof);
+
+        // Lookups showing that eq_tilde_rx and eq_tilde_ry are well formed
+        let v_msg =
+            DFSVerifier::verifier_fourth_round(&mut v_state, transcript).verifier_message[0];
+        let lookup_proof = prove_lookups(
+            ipk,
+            [&eq_rx, &eq_ry],
+            [&eq_tilde_rx, &eq_tilde_ry],
+            val_m_poly,
+            v_msg,
+            transcript,
+        );
+        transcript.append_serializable(b"lookup_proof", &lookup_proof);
+
+        Ok(R1CSProof {
+            witness_commitment,
+            first_sumcheck_msgs,
+            va,
+            vb,
+            vc,
+            second_sumcheck_msgs,
+            witness_eval,
+            val_m,
+            witness_proof,
+            eq_tilde_rx_commitment,
+            eq_tilde_ry_commitment,
+            lookup_proof,
+        })
+    }
+}
+
+/// Prove the row and column lookups together with the sum of
+/// `eq_tilde_rx · eq_tilde_ry · val_m_poly`, batching all openings at the
+/// final sumcheck point.
+fn prove_lookups<E: Pairing, T: Transcript>(
+    ipk: &IndexProverKey<E>,
+    [eq_rx, eq_ry]: [&DenseMultilinearExtension<E::ScalarField>; 2],
+    [eq_tilde_rx, eq_tilde_ry]: [&DenseMultilinearExtension<E::ScalarField>; 2],
+    val_m_poly: DenseMultilinearExtension<E::ScalarField>,
+    v_msg: E::ScalarField,
+    transcript: &mut T,
+) -> LogLookupProof<E> {
+    let num_variables = ipk.num_variables_val;
+    let q_num_vars = ipk.real_len_val.log_2();
+
+    let row = address_poly(&ipk.rows, ipk.rows[0], q_num_vars);
+    let col = address_poly(&ipk.cols, ipk.cols[0], q_num_vars);
+    let q_row = eq_query(&row, eq_tilde_rx, &v_msg);
+    let q_col = eq_query(&col, eq_tilde_ry, &v_msg);
+    let t_row = eq_table(eq_rx, &v_msg, 0, num_variables);
+    let t_col = eq_table(eq_ry, &v_msg, 0, num_variables);
+
+    let x_r: E::ScalarField = transcript.get_scalar_challenge(b"x_r");
+    let x_c: E::ScalarField = transcript.get_scalar_challenge(b"x_c");
+    let (h_row, h_row_comms, phi_row) =
+        LogLookupProof::prove(&q_row, &t_row, &ipk.freq_r, &ipk.ck_index, &x_r);
+    let (h_col, h_col_comms, phi_col) =
+        LogLookupProof::prove(&q_col, &t_col, &ipk.freq_c, &ipk.ck_index, &x_c);
+    for comms in [&h_row_comms, &h_col_comms] {
+        transcript.append_serializable(b"batch_comm1", &comms[0]);
+        transcript.append_serializable(b"batch_comm2", &comms[1]);
+    }
+
+    let mut q_polys = ListOfProductsOfPolynomials::new(max(q_num_vars, num_variables));
+    q_polys.add_product(
+        [
+            Rc::new(eq_tilde_rx.clone()),
+            Rc::new(eq_tilde_ry.clone()),
+            Rc::new(val_m_poly),
+        ],
+        E::ScalarField::one(),
+    );
+    for (h, phi, freq, q, t) in [
+        (&h_row, &phi_row, &ipk.freq_r, &q_row, &t_row),
+        (&h_col, &phi_col, &ipk.freq_c, &q_col, &t_col),
+    ] {
+        let z: Vec<E::ScalarField> = transcript.get_vector_challenge(b"z", q_num_vars);
+        let lambda: E::ScalarField = transcript.get_scalar_challenge(b"lambda");
+        append_sumcheck_polys(
+            (h[0].clone(), h[1].clone()),
+            (phi[0].clone(), phi[1].clone()),
+            boost_degree(freq, q.num_vars),
+            q.num_vars - t.num_vars,
+            &mut q_polys,
+            &z,
+            &lambda,
+            0,
+            num_variables,
+        );
+    }
+
+    let poly_info = q_polys.info();
+    transcript.feed(&poly_info).unwrap(); // feed same as in ark_linear_sumcheck
+    let mut prover_state = IPForMLSumcheck::prover_init(&q_polys);
+    let mut sumcheck_pfs = Vec::with_capacity(poly_info.num_variables);
+    let mut point = Vec::with_capacity(poly_info.num_variables);
+    let mut verifier_msg = None;
+    for _ in 0..poly_info.num_variables {
+        let prover_msg = IPForMLSumcheck::prove_round(&mut prover_state, &verifier_msg);
+        transcript.feed(&prover_msg).unwrap();
+        sumcheck_pfs.push(prover_msg);
+        let msg = IPForMLSumcheck::sample_round(transcript);
+        point.push(msg.randomness);
+        verifier_msg = Some(msg);
+    }
+
+    // Only the commitments to the `h` polynomials are sent, the verifier has
+    // the others. It evaluates the queries from the openings of the addresses
+    // and `eq_tilde_*`, and the tables itself.
+    let eta: E::ScalarField = transcript.get_scalar_challenge(b"eta");
+    let polys = [
+        &h_row[0],
+        &h_row[1],
+        &h_col[0],
+        &h_col[1],
+        eq_tilde_rx,
+        eq_tilde_ry,
+        &ipk.val_a,
+        &ipk.val_b,
+        &ipk.val_c,
+        &row,
+        &col,
+        &ipk.freq_r,
+        &ipk.freq_c,
+    ];
+    let proof = MultilinearPC::open(&ipk.ck_index, &aggregate_poly(eta, &polys), &point);
+    let val = polys.iter().map(|p| p.evaluate(&point)).collect::<Vec<_>>();
+
+    LogLookupProof {
+        sumcheck_pfs,
+        info: poly_info,
+        point,
+        degree_diff: q_num_vars - num_variables,
+        batch_oracle: BatchOracleEval {
+            val,
+            commitment: [h_row_comms, h_col_comms].concat(),
+            proof,
+        },
+    }
+}
+
+/// Pad `evaluations` with zeros to a polynomial in `num_variables` variables.
+fn pad_to_poly<F: Field>(
+    mut evaluations: Vec<F>,
+    num_variables: usize,
+) -> DenseMultilinearExtension<F> {
+    evaluations.resize(num_variables.exp2(), F::zero());
+    DenseMultilinearExtension::from_evaluations_vec(num_variables, evaluations)
+}
+
+/// A small instance with `z = (1, x, x², x³, x + c)` and `x = 3` public. All
+/// instances are indexed with the same test SRS.
+#[cfg(test)]
+pub(crate) fn test_instance(
+    c: u64,
+) -> (
+    R1CS<ark_bn254::Fr>,
+    Vec<ark_bn254::Fr>,
+    IndexProverKey<ark_bn254::Bn254>,
+    IndexVerifierKey<ark_bn254::Bn254>,
+) {
+    use ark_bn254::{Bn254, Fr};
+    use ark_std::test_rng;
+
+    use crate::{Indexer, SRS};
+
+    let mut r1cs = noir_r1cs::R1CSBuilder::new();
+    let [one, x, x2, x3, xc] = [(); 5].map(|_| r1cs.new_witness());
+    r1cs.public_inputs = 1;
+    let f = |v: u64| noir_r1cs::FieldElement::from(v);
+    r1cs.add_constraint(&[(f(1), x)], &[(f(1), x)], &[(f(1), x2)]);
+    r1cs.add_constraint(&[(f(1), x2)], &[(f(1), x)], &[(f(1), x3)]);
+    r1cs.add_constraint(&[(f(1), x), (f(c), one)], &[(f(1), one)], &[(f(1), xc)]);
+    let r1cs = R1CS::<Fr>::from(r1cs.build());
+    let witness = [1, 3, 9, 27, 3 + c].map(Fr::from).to_vec();
+
+    let mut rng = test_rng();
+    let srs = SRS::<Bn254, _>::generate_srs(r1cs.log2_instance_size() + 2, 4, &mut rng);
+    let (ipk, vk) = Indexer::index_for_prover_and_verifier(&r1cs, &srs);
+    (r1cs, witness, ipk, vk)
+}
+
+#[test]
+fn test_prove_and_verify() {
+    use ark_bn254::Fr;
+    use ark_std::test_rng;
+
+    use crate::transcript::TranscriptMerlin;
+
+    let (r1cs, witness, ipk, vk) = test_instance(5);
+    let prove = |witness: &[Fr]| {
+        R1CSProof::prove(
+            &ipk,
+            &vk,
+            &r1cs,
+            witness,
+            &mut TranscriptMerlin::new(b"dfs"),
+            &mut test_rng(),
+        )
+    };
+    let proof = prove(&witness).unwrap();
+    proof
+        .verify(&vk, &witness[1..2], &mut TranscriptMerlin::new(b"dfs"))
+        .unwrap();
+    assert!(proof
+        .verify(&vk, &[Fr::from(4u64)], &mut TranscriptMerlin::new(b"dfs"))
+        .is_err());
+
+    let mut bad_witness = witness;
+    bad_witness[3] += Fr::one();
+    assert!(prove(&bad_witness).is_err());
+}
+
+#[test]
+fn test_forged_public_inputs() {
+    use ark_bn254::Fr;
+    use ark_std::test_rng;
+
+    use crate::transcript::TranscriptMerlin;
+
+    // A prover claiming `x = 4` with the witness for `x = 3`. Were `w`
+    // committed over all of `z`, with `z = io + w`, the prover could cancel
+    // the difference with a nonzero io entry in `w`. Now `w` has no io
+    // entries and `z(r_y)` takes the claimed value.
+    let (r1cs, witness, ipk, vk) = test_instance(5);
+    let forged_inputs = [Fr::from(4u64)];
+    let proof = R1CSProof::prove_for_public_inputs(
+        &ipk,
+        &vk,
+        &r1cs,
+        &witness,
+        &forged_inputs,
+        &mut TranscriptMerlin::new(b"dfs"),
+        &mut test_rng(),
+    )
+    .unwrap();
+    assert!(proof
+        .verify(&vk, &forged_inputs, &mut TranscriptMerlin::new(b"dfs"))
+        .is_err());
+}
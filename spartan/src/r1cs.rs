@@ -2,7 +2,10 @@ use std::cmp::max;
 
 use ark_ff::PrimeField;
 use ark_serialize::SerializationError;
-use noir_r1cs::{serde_ark, HydratedSparseMatrix, Interner, SparseMatrix};
+use noir_r1cs::{
+    matrix_vector_products, r1cs_digest, serde_ark, Digest, HydratedSparseMatrix, Interner,
+    SparseMatrix,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,8 +33,54 @@ impl<F: PrimeField> R1CS<F> {
         self.c.hydrate(&self.interner)
     }
 
+    /// Compute `A·z`, `B·z` and `C·z` for a witness `z`.
+    pub fn products(&self, z: &[F]) -> [Vec<F>; 3] {
+        matrix_vector_products([self.a(), self.b(), self.c()], z)
+    }
+
+    /// Canonical digest of the constraint system, equal to that of the
+    /// [`noir_r1cs::R1CS`] it was converted from.
+    pub fn digest(&self) -> Digest {
+        r1cs_digest(
+            self.public_inputs,
+            self.witnesses,
+            self.constraints,
+            [self.a(), self.b(), self.c()],
+        )
+    }
+
+    /// Position of variable `i` of the witness `(1, public_inputs, w)` in `z`.
+    /// The io part `(1, public_inputs)` takes the even positions and `w` the
+    /// odd ones, so that `z(r) = (1 − r[0])·io(r[1..]) + r[0]·w(r[1..])` and
+    /// the committed `w` can not change the public values.
+    pub fn z_index(&self, i: usize) -> usize {
+        if i <= self.public_inputs {
+            2 * i
+        } else {
+            2 * (i - self.public_inputs - 1) + 1
+        }
+    }
+
+    /// Length of `z`, twice the longer of the io part and `w`.
+    pub fn z_len(&self) -> usize {
+        2 * max(
+            self.public_inputs + 1,
+            self.witnesses.saturating_sub(self.public_inputs + 1),
+        )
+    }
+
+    /// Lay out the witness `(1, public_inputs, w)` as `z`, see
+    /// [`R1CS::z_index`].
+    pub fn z(&self, witness: &[F]) -> Vec<F> {
+        let mut z = vec![F::zero(); self.z_len()];
+        for (i, value) in witness.iter().enumerate() {
+            z[self.z_index(i)] = *value;
+        }
+        z
+    }
+
     /// Returns ⌈log₂(instance_size)⌉ where:
-    /// instance_size = max(#constraints M, #vars m, #nonzeros N)
+    /// instance_size = max(#constraints M, |z|, #nonzeros N)
     pub fn log2_instance_size(&self) -> usize {
         // Count non-zero entries across A, B, C
         let nonzeros = max(
@@ -39,7 +88,7 @@ impl<F: PrimeField> R1CS<F> {
             max(self.b.num_entries(), self.c.num_entries()),
         );
         // Determine maximal component
-        let max_size = *[self.constraints, self.witnesses, nonzeros]
+        let max_size = *[self.constraints, self.z_len(), nonzeros]
             .iter()
             .max()
             .unwrap();
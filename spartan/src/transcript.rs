@@ -2,11 +2,18 @@
 use std::vec::Vec;
 
 use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge, FieldElementSize};
+use ark_ec::pairing::Pairing;
 use ark_ff::{BigInteger, Field, PrimeField};
 use ark_linear_sumcheck::rng::FeedableRNG;
 use ark_serialize::CanonicalSerialize;
 use rand::RngCore;
 
+use crate::indexer::IndexVerifierKey;
+
+/// Version of the proof protocol. Changes to the messages or their order
+/// must bump it, so that transcripts of different versions never agree.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Clone)]
 pub struct TranscriptMerlin(merlin::Transcript);
 
@@ -16,6 +23,28 @@ impl TranscriptMerlin {
     }
 }
 
+/// Start the transcript of a proof that `z = (1, public_inputs, w)` satisfies
+/// the circuit of `vk`, before the prover sends its first message. The
+/// prover and the verifier must both call this on a fresh transcript.
+pub fn init_transcript<E: Pairing>(
+    transcript: &mut impl Transcript,
+    vk: &IndexVerifierKey<E>,
+    public_inputs: &[E::ScalarField],
+) {
+    transcript.append_serializable(b"protocol_version", &PROTOCOL_VERSION);
+    transcript.append_serializable(b"vk_digest", &vk.digest());
+    transcript.append_serializable(
+        b"dimensions",
+        &[
+            vk.padded_num_var,
+            vk.num_variables_val,
+            vk.real_len_val,
+            vk.num_public_inputs,
+        ],
+    );
+    transcript.append_serializable(b"public_inputs", &public_inputs.to_vec());
+}
+
 /// A Transcript with some shorthands for feeding scalars, group elements, and obtaining challenges as field elements.
 pub trait Transcript: RngCore + FeedableRNG<Error = ark_linear_sumcheck::Error> {
     fn append_serializable<S: CanonicalSerialize>(&mut self, label: &'static [u8], msg: &S);
@@ -179,3 +208,22 @@ pub fn get_vector_challenge<
 ) -> Vec<F> {
     (0..size).map(|_| get_scalar_challenge(rng)).collect()
 }
+
+#[test]
+fn test_init_transcript_binds_statement() {
+    use ark_bn254::{Bn254, Fr};
+
+    use crate::prover::test_instance;
+
+    let (_, _, _, vk) = test_instance(5);
+    let (_, _, _, other_vk) = test_instance(6);
+    let challenge = |vk: &IndexVerifierKey<Bn254>, public_inputs: &[Fr]| {
+        let mut transcript = TranscriptMerlin::new(b"dfs");
+        init_transcript(&mut transcript, vk, public_inputs);
+        transcript.get_scalar_challenge::<Fr>(b"challenge")
+    };
+    let expected = challenge(&vk, &[Fr::from(3u64)]);
+    assert_eq!(challenge(&vk, &[Fr::from(3u64)]), expected);
+    assert_ne!(challenge(&vk, &[Fr::from(4u64)]), expected);
+    assert_ne!(challenge(&other_vk, &[Fr::from(3u64)]), expected);
+}
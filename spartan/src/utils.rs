@@ -4,7 +4,7 @@ use ark_ec::pairing::Pairing;
 use ark_ff::Field;
 use ark_poly::{DenseMultilinearExtension, SparseMultilinearExtension};
 use ark_poly_commit::multilinear_pc::data_structures::{Commitment, Proof};
-use ark_std::{cfg_into_iter, cfg_iter};
+use ark_std::{cfg_into_iter, cfg_iter, cfg_iter_mut};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
@@ -176,6 +176,30 @@ pub fn aggregate_eval<F: Field>(eta: F, vals: &[F]) -> F {
     res
 }
 
+pub fn aggregate_poly<F: Field>(
+    eta: F,
+    polys: &[&DenseMultilinearExtension<F>],
+) -> DenseMultilinearExtension<F> {
+    let mut vars = 0;
+    for p in polys {
+        if p.num_vars > vars {
+            vars = p.num_vars;
+        }
+    }
+    let mut evals = vec![F::zero(); 1 << vars];
+    let mut x = F::one();
+    for p in polys {
+        cfg_iter_mut!(evals)
+            .zip(&p.evaluations)
+            .for_each(|(a, b)| *a += x * b);
+        x *= eta
+    }
+    DenseMultilinearExtension {
+        evaluations: evals,
+        num_vars: vars,
+    }
+}
+
 pub fn normalized_multiplicities<F: Field>(
     query: &DenseMultilinearExtension<F>,
     table: &DenseMultilinearExtension<F>,
@@ -1,9 +1,9 @@
-use std::marker::PhantomData;
+use std::{iter, marker::PhantomData};
 
 use anyhow::{ensure, Context};
 use ark_crypto_primitives::sponge::CryptographicSponge;
 use ark_ec::pairing::Pairing;
-use ark_ff::{UniformRand, Zero};
+use ark_ff::{One, UniformRand, Zero};
 use ark_poly::SparseMultilinearExtension;
 use ark_poly_commit::multilinear_pc::{
     data_structures::{Commitment, Proof as PCProof, VerifierKey},
@@ -20,7 +20,7 @@ use super::{
 };
 use crate::{
     math::MaskPolynomial,
-    transcript::Transcript,
+    transcript::{init_transcript, Transcript},
     utils::{aggregate_comm, aggregate_eval, eq_eval},
 };
 
@@ -29,21 +29,34 @@ pub type VerificationResult = anyhow::Result<()>;
 
 impl<E: Pairing> R1CSProof<E> {
     /// Verification function for SNARK proof.
-    /// The input contains the R1CS instance and the verification key
-    /// of polynomial commitment.
+    /// The input contains the verification key and the public values of the
+    /// R1CS instance. Only `w` is committed, the verifier fills in the io part
+    /// `(1, public_inputs)` of `z` itself, see [`crate::R1CS::z_index`].
     #[tracing::instrument(skip_all, name = "R1CSProof::verify")]
     pub fn verify<T: Transcript + CryptographicSponge>(
         &self,
         vk: &IndexVerifierKey<E>,
-        assignment: &Vec<E::ScalarField>,
+        public_inputs: &[E::ScalarField],
         transcript: &mut T,
     ) -> VerificationResult {
+        ensure!(
+            public_inputs.len() == vk.num_public_inputs,
+            "expected {} public inputs, got {}",
+            vk.num_public_inputs,
+            public_inputs.len()
+        );
         let mut v_state: VerifierState<E> = DFSVerifier::verifier_init(vk.padded_num_var);
-        let mle_io_1_evals = assignment.iter().copied().enumerate().collect::<Vec<_>>();
-        let mle_io_1 =
-            SparseMultilinearExtension::from_evaluations(vk.padded_num_var, mle_io_1_evals.iter());
+        let mle_io_1_evals = iter::once(E::ScalarField::one())
+            .chain(public_inputs.iter().copied())
+            .enumerate()
+            .collect::<Vec<_>>();
+        let mle_io_1 = SparseMultilinearExtension::from_evaluations(
+            vk.padded_num_var - 1,
+            mle_io_1_evals.iter(),
+        );
         let w_commitment = &self.witness_commitment;
 
+        init_transcript(transcript, vk, public_inputs);
         transcript.append_serializable(b"w_commitment", w_commitment);
         let _ = DFSVerifier::verifier_first_round(&mut v_state, transcript);
 
@@ -95,7 +108,7 @@ impl<E: Pairing> R1CSProof<E> {
         let flag_zkml = ZKMLCommit::<E, MaskPolynomial<E>>::check(
             &vk.vk_w,
             &w_commitment,
-            &r_y,
+            &r_y[1..],
             w_value,
             &w_proof,
         );
@@ -106,7 +119,9 @@ impl<E: Pairing> R1CSProof<E> {
                 .context("while verifying second sumcheck")
         );
 
-        let z = crate::utils::eval_sparse_mle(&mle_io_1, &r_y[..]) + w_value;
+        // z(r_y) = (1 − r_y[0])·io(r_y[1..]) + r_y[0]·w(r_y[1..])
+        let io_value = crate::utils::eval_sparse_mle(&mle_io_1, &r_y[1..]);
+        let z = (E::ScalarField::one() - r_y[0]) * io_value + r_y[0] * w_value;
         ensure!(
             sub_claim_2.expected_evaluation == self.val_m * z,
             anyhow::anyhow!(
@@ -122,29 +137,68 @@ impl<E: Pairing> R1CSProof<E> {
         transcript.append_serializable(b"eq_tilde_ry_comm", &self.eq_tilde_ry_commitment);
         transcript.append_serializable(b"w_proof", &w_proof.clone());
 
-        let _ = DFSVerifier::verifier_fourth_round(&mut v_state, transcript);
+        let v_msg =
+            DFSVerifier::verifier_fourth_round(&mut v_state, transcript).verifier_message[0];
+
+        let batch_oracle = &self.lookup_proof.batch_oracle;
+        ensure!(
+            batch_oracle.commitment.len() == 4
+                && batch_oracle.val.len() == 13
+                && self.lookup_proof.info.num_variables == vk.num_variables_val
+                && self.lookup_proof.degree_diff == 0,
+            "malformed lookup proof"
+        );
+        // Only the commitments to the `h` polynomials of the lookups come with
+        // the opening. The other committed polynomials are `eq_tilde_rx`,
+        // `eq_tilde_ry`, absorbed above, and the index polynomials of the key.
+        let batch_commitments = [
+            &batch_oracle.commitment[..],
+            &[
+                self.eq_tilde_rx_commitment.clone(),
+                self.eq_tilde_ry_commitment.clone(),
+                vk.val_a_oracle.clone(),
+                vk.val_b_oracle.clone(),
+                vk.val_c_oracle.clone(),
+                vk.row_oracle.clone(),
+                vk.col_oracle.clone(),
+                vk.freq_r_oracle.clone(),
+                vk.freq_c_oracle.clone(),
+            ],
+        ]
+        .concat();
 
         let (lookup_x, z, lambda) = LogLookupProof::<E>::get_sumcheck_verifier_challenges(
             &self.lookup_proof.info,
-            &self.lookup_proof.batch_oracle,
+            batch_oracle,
             2,
             transcript,
         );
 
-        let aux_eval = self.lookup_proof.batch_oracle.val[4]
-            * self.lookup_proof.batch_oracle.val[5]
-            * (self.lookup_proof.batch_oracle.val[6] * v_state.self_randomness[1][0]
-                + self.lookup_proof.batch_oracle.val[7] * v_state.self_randomness[1][1]
-                + self.lookup_proof.batch_oracle.val[8] * v_state.self_randomness[1][2]);
+        // The queries `row + v · eq_tilde_rx` and `col + v · eq_tilde_ry` are
+        // evaluated from the openings, the tables by `LogLookupProof::verify`.
+        let query_evals = [
+            batch_oracle.val[9] + v_msg * batch_oracle.val[4],
+            batch_oracle.val[10] + v_msg * batch_oracle.val[5],
+        ];
+        let aux_eval = batch_oracle.val[4]
+            * batch_oracle.val[5]
+            * (batch_oracle.val[6] * v_state.self_randomness[1][0]
+                + batch_oracle.val[7] * v_state.self_randomness[1][1]
+                + batch_oracle.val[8] * v_state.self_randomness[1][2]);
 
         LogLookupProof::<E>::verify(
             &self.lookup_proof.info,
             &self.lookup_proof.sumcheck_pfs,
-            &self.lookup_proof.batch_oracle,
+            batch_oracle,
+            &batch_commitments,
             self.lookup_proof.degree_diff,
             &lookup_x,
             &z,
             &lambda,
+            &[&r_x[..], &r_y[..]],
+            v_msg,
+            &query_evals,
+            &batch_oracle.val[11..13],
             &vk.vk_index,
             transcript,
             aux_eval,
@@ -229,10 +283,14 @@ pub struct OracleEval<E: Pairing> {
     pub proof: PCProof<E>,
 }
 
+/// Batched opening of the polynomials of a [`LogLookupProof`] at one point.
 #[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
 pub struct BatchOracleEval<E: Pairing> {
+    /// Evaluations of the committed polynomials, in the order of the
+    /// commitments assembled by the verifier.
     pub val: Vec<E::ScalarField>,
-    pub debug_val: Vec<E::ScalarField>,
+    /// Commitments sent along with the opening. Commitments the verifier
+    /// already has, from its key or earlier messages, are not repeated here.
     pub commitment: Vec<Commitment<E>>,
     pub proof: PCProof<E>,
 }
@@ -243,19 +301,6 @@ impl<E: Pairing> OracleEval<E> {
     }
 }
 
-impl<E: Pairing> BatchOracleEval<E> {
-    pub fn verify(
-        &self,
-        eta: E::ScalarField,
-        vk: &VerifierKey<E>,
-        point: &[E::ScalarField],
-    ) -> bool {
-        let batch_comm = aggregate_comm(eta, &self.commitment);
-        let batch_eval = aggregate_eval(eta, &self.val[0..self.commitment.len()]);
-        MultilinearPC::check(vk, &batch_comm, point, batch_eval, &self.proof)
-    }
-}
-
 /// Batch verify polynomial
 pub fn batch_verify_poly<E: Pairing>(
     comms: &[Commitment<E>],
@@ -273,3 +318,49 @@ pub fn batch_verify_poly<E: Pairing>(
     let res = MultilinearPC::check(vk, &batch_comm, final_point, batch_eval, proof);
     res
 }
+
+#[test]
+fn test_tampered_proofs() {
+    use ark_bn254::{Bn254, Fr};
+    use ark_std::test_rng;
+
+    use crate::{prover::test_instance, transcript::TranscriptMerlin, IndexProverKey, R1CS};
+
+    let (r1cs, witness, ipk, vk) = test_instance(5);
+    let prove = |ipk: &IndexProverKey<Bn254>, r1cs: &R1CS<Fr>, witness: &[Fr]| {
+        let mut transcript = TranscriptMerlin::new(b"dfs");
+        R1CSProof::prove(ipk, &vk, r1cs, witness, &mut transcript, &mut test_rng()).unwrap()
+    };
+    let verify = |proof: &R1CSProof<Bn254>| {
+        proof.verify(&vk, &witness[1..2], &mut TranscriptMerlin::new(b"dfs"))
+    };
+    let proof = prove(&ipk, &r1cs, &witness);
+    verify(&proof).unwrap();
+
+    let tampers: [fn(&mut R1CSProof<Bn254>); 5] = [
+        |proof| proof.lookup_proof.batch_oracle.commitment.swap(0, 1),
+        |proof| proof.lookup_proof.batch_oracle.val[6] += Fr::one(),
+        |proof| proof.eq_tilde_rx_commitment = proof.eq_tilde_ry_commitment.clone(),
+        // A commitment list in the format where the prover sent all of them
+        |proof| {
+            let batch_oracle = &mut proof.lookup_proof.batch_oracle;
+            batch_oracle
+                .commitment
+                .push(proof.eq_tilde_rx_commitment.clone());
+        },
+        |proof| {
+            proof.lookup_proof.batch_oracle.val.pop();
+        },
+    ];
+    for tamper in tampers {
+        let mut proof = proof.clone();
+        tamper(&mut proof);
+        assert!(verify(&proof).is_err());
+    }
+
+    // Proving with the index polynomials of another circuit on the same SRS
+    // must fail against this circuit's key.
+    let (other_r1cs, other_witness, other_ipk, _) = test_instance(6);
+    let proof = prove(&other_ipk, &other_r1cs, &other_witness);
+    assert!(verify(&proof).is_err());
+}
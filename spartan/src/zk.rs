@@ -11,7 +11,8 @@ use ark_ec::{
 };
 use ark_ff::{Field, One, PrimeField, UniformRand};
 use ark_linear_sumcheck::ml_sumcheck::{
-    protocol::{verifier::SubClaim, PolynomialInfo},
+    data_structures::ListOfProductsOfPolynomials,
+    protocol::{prover::ProverMsg, verifier::SubClaim, IPForMLSumcheck, PolynomialInfo},
     MLSumcheck,
 };
 use ark_poly::{
@@ -754,6 +755,92 @@ fn eq_extension<F: Field>(t: &[F]) -> Vec<DenseMultilinearExtension<F>> {
 
 }*/
 
+/// Prove a sumcheck over `polynomial` in zero knowledge, the counterpart of
+/// [`zk_sumcheck_verifier_wrapper`]. The messages are masked by a random
+/// polynomial sampled from `rng` and committed with `mask_key`. Returns the
+/// proof and the point the sum is reduced to.
+#[tracing::instrument(skip_all, name = "zk_sumcheck_prover")]
+pub fn zk_sumcheck_prover_wrapper<E: Pairing, T: Transcript + CryptographicSponge>(
+    mask_key: &MaskCommitterKey<E, SparsePolynomial<E::ScalarField, SparseTerm>>,
+    polynomial: &ListOfProductsOfPolynomials<E::ScalarField>,
+    transcript: &mut T,
+    rng: &mut impl RngCore,
+) -> (ZKSumcheckProof<E>, Vec<E::ScalarField>) {
+    let poly_info = polynomial.info();
+
+    let mask_poly = generate_mask_polynomial(
+        rng,
+        poly_info.num_variables,
+        poly_info.max_multiplicands,
+        true,
+    );
+    let vec_mask_poly = vec![LabeledPolynomial::new(
+        String::from("mask_poly_for_sumcheck"),
+        mask_poly.clone(),
+        Some(poly_info.max_multiplicands),
+        None,
+    )];
+    let (mask_commit, mask_randomness) =
+        MarlinPST13::<_, _>::commit(mask_key, &vec_mask_poly, Some(rng)).unwrap();
+    let g_commit = mask_commit[0].commitment();
+    transcript.append_serializable(b"g_commit", g_commit);
+    let challenge = transcript.get_scalar_challenge(b"r1");
+
+    transcript.feed(&poly_info).unwrap(); // feed same as in ark_linear_sumcheck
+    let mut prover_zk_state = IPForMLSumcheck::mask_init(
+        &mask_poly,
+        poly_info.num_variables,
+        poly_info.max_multiplicands,
+        challenge,
+    );
+    let mut prover_state = IPForMLSumcheck::prover_init(polynomial);
+
+    let mut prover_msgs = Vec::with_capacity(poly_info.num_variables);
+    let mut final_point = Vec::with_capacity(poly_info.num_variables);
+    let mut verifier_msg = None;
+    for _ in 0..poly_info.num_variables {
+        let prover_message = IPForMLSumcheck::prove_round(&mut prover_state, &verifier_msg);
+        let mask = IPForMLSumcheck::mask_round(&mut prover_zk_state, &verifier_msg);
+        let final_msg = ProverMsg {
+            evaluations: prover_message
+                .evaluations
+                .iter()
+                .zip(mask.evaluations.iter())
+                .map(|(msg, mask)| *msg + mask)
+                .collect(),
+        };
+
+        transcript.feed(&final_msg).unwrap();
+        prover_msgs.push(final_msg);
+        let msg = IPForMLSumcheck::sample_round(transcript);
+        final_point.push(msg.randomness);
+        verifier_msg = Some(msg);
+    }
+
+    let g_proof = MarlinPST13::<_, _>::open(
+        mask_key,
+        &vec_mask_poly,
+        &mask_commit,
+        &final_point,
+        transcript,
+        &mask_randomness,
+        None,
+    )
+    .unwrap();
+    let g_value = vec_mask_poly[0].evaluate(&final_point);
+
+    (
+        ZKSumcheckProof {
+            g_commit: *g_commit,
+            sumcheck_proof: prover_msgs,
+            poly_info,
+            g_proof,
+            g_value,
+        },
+        final_point,
+    )
+}
+
 #[tracing::instrument(skip_all, name = "zk_sumcheck_verifier")]
 pub fn zk_sumcheck_verifier_wrapper<E: Pairing, T: Transcript + CryptographicSponge>(
     mask_vk: &MaskVerifierKey<E>,